@@ -0,0 +1,91 @@
+use std::{collections::BTreeSet, fs, path::PathBuf, sync::Mutex};
+
+use crate::{config::Config, messages::Text};
+
+/// Decides who may submit icons. Banned users are kept in a file, one id
+/// per line, so bans survive restarts.
+pub struct AccessList {
+    path: PathBuf,
+    admin_ids: Vec<i64>,
+    allowed_ids: Vec<i64>,
+    banned: Mutex<BTreeSet<i64>>,
+}
+
+impl AccessList {
+    pub fn load(config: &Config) -> Self {
+        let banned = match fs::read_to_string(&config.banlist_path) {
+            Ok(banlist) => banlist
+                .lines()
+                .filter_map(|line| line.trim().parse().ok())
+                .collect(),
+            Err(e) => {
+                log::info!("No bans loaded from {}: {e}", config.banlist_path);
+
+                BTreeSet::new()
+            }
+        };
+
+        Self {
+            path: PathBuf::from(&config.banlist_path),
+            admin_ids: config.admin_ids.clone(),
+            allowed_ids: config.allowed_ids.clone(),
+            banned: Mutex::new(banned),
+        }
+    }
+
+    pub fn is_admin(&self, user_id: i64) -> bool {
+        self.admin_ids.contains(&user_id)
+    }
+
+    /// Why `user_id` may not submit icons, if they may not.
+    pub fn refusal(&self, user_id: i64) -> Option<Text<'static>> {
+        if self.is_admin(user_id) {
+            None
+        } else if self.banned.lock().unwrap().contains(&user_id) {
+            Some(Text::Banned)
+        } else if !self.allowed_ids.is_empty() && !self.allowed_ids.contains(&user_id) {
+            Some(Text::TrustedOnly)
+        } else {
+            None
+        }
+    }
+
+    /// Bans `user_id`. Returns whether they weren't banned already.
+    pub fn ban(&self, user_id: i64) -> bool {
+        let mut banned = self.banned.lock().unwrap();
+        let added = banned.insert(user_id);
+
+        if added {
+            self.save(&banned);
+        }
+
+        added
+    }
+
+    /// Lifts the ban of `user_id`. Returns whether they were banned.
+    pub fn unban(&self, user_id: i64) -> bool {
+        let mut banned = self.banned.lock().unwrap();
+        let removed = banned.remove(&user_id);
+
+        if removed {
+            self.save(&banned);
+        }
+
+        removed
+    }
+
+    pub fn banned(&self) -> Vec<i64> {
+        self.banned.lock().unwrap().iter().copied().collect()
+    }
+
+    fn save(&self, banned: &BTreeSet<i64>) {
+        let banlist = banned
+            .iter()
+            .map(|user_id| format!("{user_id}\n"))
+            .collect::<String>();
+
+        if let Err(e) = fs::write(&self.path, banlist) {
+            log::warn!("Failed to save the banlist to {}: {e}", self.path.display());
+        }
+    }
+}
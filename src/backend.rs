@@ -0,0 +1,410 @@
+use std::{error::Error, sync::Arc};
+
+use serde::Serialize;
+
+use crate::{
+    config::Config,
+    error::gitlab_error_for_status,
+    icon_map::IconMap,
+    overlay::{
+        self, drawable_file, ExistingDrawable, IconCommit, IconRemoval, PushFailed, ICON_MAP_PATH,
+    },
+};
+
+/// How the bot reads from and commits to the overlay repository, selected
+/// with `SUBMISSION_BACKEND`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubmissionBackend {
+    /// A local cache plus a temporary clone per submission, pushed with git.
+    Git,
+    /// GitLab's repository files and commits APIs. Only needs `GITLAB_TOKEN`.
+    GitLabApi,
+}
+
+#[derive(Serialize, Debug)]
+struct CommitParams<'a> {
+    branch: &'a str,
+    start_branch: &'a str,
+    commit_message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author_name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author_email: Option<&'a str>,
+    actions: Vec<CommitAction>,
+    force: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct CommitAction {
+    action: &'static str,
+    file_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+}
+
+impl SubmissionBackend {
+    /// Makes sure the latest state of `target_branches` is used for the next
+    /// submission.
+    pub async fn refresh(
+        self,
+        config: &Arc<Config>,
+        target_branches: &[String],
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match self {
+            Self::Git => {
+                let config = config.clone();
+                let target_branches = target_branches.to_vec();
+
+                tokio::task::spawn_blocking(move || {
+                    overlay::refresh_cache(&config, &target_branches)
+                })
+                .await?
+            }
+            // Every request reads the current state anyway.
+            Self::GitLabApi => Ok(()),
+        }
+    }
+
+    /// Looks for a drawable named `icon_name` on any of `target_branches`.
+    pub async fn find_existing_drawable(
+        self,
+        config: &Arc<Config>,
+        client: &reqwest::Client,
+        target_branches: &[String],
+        icon_name: &str,
+    ) -> Result<Option<ExistingDrawable>, Box<dyn Error + Send + Sync>> {
+        match self {
+            Self::Git => {
+                let config = config.clone();
+                let target_branches = target_branches.to_vec();
+                let icon_name = icon_name.to_owned();
+
+                tokio::task::spawn_blocking(move || {
+                    overlay::find_existing_drawable(&config, &target_branches, &icon_name)
+                })
+                .await?
+            }
+            Self::GitLabApi => {
+                let drawable = format!("@drawable/themed_icon_{icon_name}");
+                let drawable_path = drawable_file(&drawable).unwrap_or_default();
+
+                for branch in target_branches {
+                    if !file_exists(config, client, branch, &drawable_path).await? {
+                        continue;
+                    }
+
+                    let package = match read_icon_map(config, client, branch).await {
+                        Ok(icon_map) => icon_map
+                            .icons
+                            .into_iter()
+                            .find(|icon| icon.drawable == drawable)
+                            .map(|icon| icon.package),
+                        Err(_) => None,
+                    };
+
+                    return Ok(Some(ExistingDrawable {
+                        branch: branch.clone(),
+                        package,
+                    }));
+                }
+
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns the drawable `package` is mapped to on each of
+    /// `target_branches`, without the `@drawable/` prefix.
+    pub async fn mapped_drawables(
+        self,
+        config: &Arc<Config>,
+        client: &reqwest::Client,
+        target_branches: &[String],
+        package: &str,
+    ) -> Result<Vec<Option<String>>, Box<dyn Error + Send + Sync>> {
+        match self {
+            Self::Git => {
+                let config = config.clone();
+                let target_branches = target_branches.to_vec();
+                let package = package.to_owned();
+
+                tokio::task::spawn_blocking(move || {
+                    overlay::mapped_drawables(&config, &target_branches, &package)
+                })
+                .await?
+            }
+            Self::GitLabApi => {
+                let mut drawables = Vec::with_capacity(target_branches.len());
+
+                for branch in target_branches {
+                    let icon_map = read_icon_map(config, client, branch).await?;
+
+                    drawables.push(icon_map.drawable_for(package).map(|drawable| {
+                        drawable
+                            .strip_prefix("@drawable/")
+                            .unwrap_or(drawable)
+                            .to_owned()
+                    }));
+                }
+
+                Ok(drawables)
+            }
+        }
+    }
+
+    /// The icon map of `branch`.
+    pub async fn icon_map(
+        self,
+        config: &Arc<Config>,
+        client: &reqwest::Client,
+        branch: &str,
+    ) -> Result<IconMap, Box<dyn Error + Send + Sync>> {
+        match self {
+            Self::Git => {
+                let config = config.clone();
+                let branch = branch.to_owned();
+
+                tokio::task::spawn_blocking(move || overlay::icon_map(&config, &branch)).await?
+            }
+            Self::GitLabApi => read_icon_map(config, client, branch).await,
+        }
+    }
+
+    /// Commits `icon` to a new branch on top of its target branch. Failures
+    /// are returned as [`PushFailed`]. In dry-run mode nothing is pushed and
+    /// a summary of the changes is returned instead.
+    pub async fn push_icon(
+        self,
+        config: &Arc<Config>,
+        client: &reqwest::Client,
+        icon: IconCommit,
+    ) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+        match self {
+            Self::Git => {
+                let config = config.clone();
+
+                tokio::task::spawn_blocking(move || overlay::commit_and_push_icon(&config, &icon))
+                    .await?
+            }
+            Self::GitLabApi => commit_icon(config, client, &icon).await.map_err(|e| {
+                log::error!("Failed to commit {}: {e}", icon.branch_name);
+
+                PushFailed(e).into()
+            }),
+        }
+    }
+
+    /// Commits `removal` to a new branch on top of its target branch.
+    /// Failures are returned as [`PushFailed`]. Like [`Self::push_icon`],
+    /// dry-run mode only returns a summary of the changes.
+    pub async fn push_removal(
+        self,
+        config: &Arc<Config>,
+        client: &reqwest::Client,
+        removal: IconRemoval,
+    ) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+        match self {
+            Self::Git => {
+                let config = config.clone();
+
+                tokio::task::spawn_blocking(move || {
+                    overlay::commit_and_push_removal(&config, &removal)
+                })
+                .await?
+            }
+            Self::GitLabApi => commit_removal(config, client, &removal).await.map_err(|e| {
+                log::error!("Failed to commit {}: {e}", removal.branch_name);
+
+                PushFailed(e).into()
+            }),
+        }
+    }
+}
+
+fn files_url(config: &Config, path: &str) -> String {
+    format!(
+        "https://gitlab.com/api/v4/projects/{}/repository/files/{}",
+        config.gitlab_project_id,
+        path.replace('/', "%2F").replace('.', "%2E")
+    )
+}
+
+async fn file_exists(
+    config: &Config,
+    client: &reqwest::Client,
+    branch: &str,
+    path: &str,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let response = client
+        .head(files_url(config, path))
+        .query(&[("ref", branch)])
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(false);
+    }
+    gitlab_error_for_status(response).await?;
+
+    Ok(true)
+}
+
+async fn read_icon_map(
+    config: &Config,
+    client: &reqwest::Client,
+    branch: &str,
+) -> Result<IconMap, Box<dyn Error + Send + Sync>> {
+    let response = client
+        .get(format!("{}/raw", files_url(config, ICON_MAP_PATH)))
+        .query(&[("ref", branch)])
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .send()
+        .await?;
+    let xml = gitlab_error_for_status(response).await?.text().await?;
+
+    Ok(IconMap::parse(&xml)?)
+}
+
+/// Creates the icon branch with a single commit that adds or updates the
+/// drawables, updates the icon map and drops orphaned old drawables.
+async fn commit_icon(
+    config: &Config,
+    client: &reqwest::Client,
+    icon: &IconCommit,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    let mut icon_map = read_icon_map(config, client, &icon.target_branch).await?;
+    let mut actions = Vec::with_capacity(icon.icons.len() + 1);
+    let mut orphaned = Vec::new();
+
+    for new_icon in &icon.icons {
+        let drawable = format!("@drawable/themed_icon_{}", new_icon.icon_name);
+        let drawable_path = drawable_file(&drawable).unwrap_or_default();
+
+        orphaned.extend(icon_map.replace(drawable, new_icon.app_path.clone()));
+
+        let drawable_action =
+            if file_exists(config, client, &icon.target_branch, &drawable_path).await? {
+                "update"
+            } else {
+                "create"
+            };
+
+        actions.push(CommitAction {
+            action: drawable_action,
+            file_path: drawable_path,
+            content: Some(String::from_utf8(new_icon.vd_bytes.clone())?),
+        });
+    }
+
+    actions.push(CommitAction {
+        action: "update",
+        file_path: ICON_MAP_PATH.to_owned(),
+        content: Some(icon_map.to_xml()),
+    });
+
+    // A later icon of the batch may have taken over the old drawable.
+    for drawable in orphaned {
+        if icon_map
+            .icons
+            .iter()
+            .any(|mapped| mapped.drawable == drawable)
+        {
+            continue;
+        }
+
+        if let Some(orphaned) = drawable_file(&drawable) {
+            if file_exists(config, client, &icon.target_branch, &orphaned).await? {
+                actions.push(CommitAction {
+                    action: "delete",
+                    file_path: orphaned,
+                    content: None,
+                });
+            }
+        }
+    }
+
+    let params = CommitParams {
+        branch: &icon.branch_name,
+        start_branch: &icon.target_branch,
+        commit_message: &icon.commit_msg,
+        author_name: config.git_author_name.as_deref(),
+        author_email: config.git_author_email.as_deref(),
+        actions,
+        force: icon.force,
+    };
+
+    post_commit(config, client, &params).await
+}
+
+/// Creates the removal branch with a single commit that drops the package
+/// from the icon map and deletes its drawable if no other package uses it.
+async fn commit_removal(
+    config: &Config,
+    client: &reqwest::Client,
+    removal: &IconRemoval,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    let mut icon_map = read_icon_map(config, client, &removal.target_branch).await?;
+    let orphaned = icon_map
+        .remove(&removal.app_path)
+        .and_then(|drawable| drawable_file(&drawable));
+
+    let mut actions = vec![CommitAction {
+        action: "update",
+        file_path: ICON_MAP_PATH.to_owned(),
+        content: Some(icon_map.to_xml()),
+    }];
+
+    if let Some(orphaned) = orphaned {
+        if file_exists(config, client, &removal.target_branch, &orphaned).await? {
+            actions.push(CommitAction {
+                action: "delete",
+                file_path: orphaned,
+                content: None,
+            });
+        }
+    }
+
+    let params = CommitParams {
+        branch: &removal.branch_name,
+        start_branch: &removal.target_branch,
+        commit_message: &removal.commit_msg,
+        author_name: config.git_author_name.as_deref(),
+        author_email: config.git_author_email.as_deref(),
+        actions,
+        force: removal.force,
+    };
+
+    post_commit(config, client, &params).await
+}
+
+/// Creates the commit described by `params`. In dry-run mode only the
+/// actions it would take are returned.
+async fn post_commit(
+    config: &Config,
+    client: &reqwest::Client,
+    params: &CommitParams<'_>,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    if config.dry_run {
+        return Ok(Some(
+            params
+                .actions
+                .iter()
+                .map(|action| format!("{} {}\n", action.action, action.file_path))
+                .collect(),
+        ));
+    }
+
+    let response = client
+        .post(format!(
+            "https://gitlab.com/api/v4/projects/{}/repository/commits",
+            config.gitlab_project_id
+        ))
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .json(params)
+        .send()
+        .await?;
+    gitlab_error_for_status(response).await?;
+
+    Ok(None)
+}
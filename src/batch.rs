@@ -0,0 +1,70 @@
+use crate::{messages::Text, overlay::NewIcon, store::AppDetails};
+
+/// Icons submitted together in one merge request.
+pub const MAX_BATCH_SIZE: usize = 10;
+
+/// A converted icon that is submitted together with the other icons of its
+/// batch.
+#[derive(Clone, Debug)]
+pub struct BatchedIcon {
+    pub app_path: String,
+    pub icon_name: String,
+    /// What the app store told us about the app, if it was found there.
+    pub app: Option<AppDetails>,
+    pub description: String,
+    pub vd_bytes: Vec<u8>,
+    /// The SVG the drawable was converted from, shown to reviewers.
+    pub svg: Option<String>,
+}
+
+impl BatchedIcon {
+    pub fn to_new_icon(&self) -> NewIcon {
+        NewIcon {
+            icon_name: self.icon_name.clone(),
+            app_path: self.app_path.clone(),
+            vd_bytes: self.vd_bytes.clone(),
+        }
+    }
+}
+
+/// Why an icon for `app_path` can't be added to `batch`, if it can't.
+pub fn app_path_conflict<'a>(batch: &[BatchedIcon], app_path: &'a str) -> Option<Text<'a>> {
+    batch
+        .iter()
+        .any(|icon| icon.app_path == app_path)
+        .then(|| Text::AppPathInBatch { app_path })
+}
+
+/// Why `icon_name` can't be used for an icon of `app_path` in `batch`, if it
+/// can't.
+pub fn icon_name_conflict<'a>(
+    batch: &'a [BatchedIcon],
+    app_path: &str,
+    icon_name: &'a str,
+) -> Option<Text<'a>> {
+    batch
+        .iter()
+        .find(|icon| icon.icon_name == icon_name && icon.app_path != app_path)
+        .map(|icon| Text::IconNameInBatch {
+            icon_name,
+            app_path: &icon.app_path,
+        })
+}
+
+/// Lists the names of the icons in `batch`, e.g. for messages and titles.
+pub fn icon_names(batch: &[BatchedIcon]) -> String {
+    batch
+        .iter()
+        .map(|icon| icon.icon_name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Lists the packages of the icons in `batch`.
+pub fn app_paths(batch: &[BatchedIcon]) -> String {
+    batch
+        .iter()
+        .map(|icon| icon.app_path.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,700 @@
+use std::{collections::HashSet, env, error::Error, fmt, str::FromStr, time::Duration};
+
+use reqwest::Url;
+use teloxide::types::ChatId;
+
+use crate::backend::SubmissionBackend;
+
+const DEFAULT_OTA_DCOS: &str =
+    "https://raw.githubusercontent.com/DavinciCodeOS/ota-data/main/davinci.json";
+const DEFAULT_OTA_DCOS_PRE: &str =
+    "https://raw.githubusercontent.com/DavinciCodeOS/ota-data/main/davinci_pre.json";
+const DEFAULT_OTA_DCOSX: &str =
+    "https://raw.githubusercontent.com/DavinciCodeOS/ota-data/main/davincix.json";
+const DEFAULT_OTA_DCOSX_PRE: &str =
+    "https://raw.githubusercontent.com/DavinciCodeOS/ota-data/main/davincix_pre.json";
+
+const DEFAULT_GITLAB_PROJECT_ID: u64 = 35606329;
+const DEFAULT_REVIEW_TIMEOUT_SECS: u64 = 2 * 24 * 60 * 60;
+const DEFAULT_SUBMISSION_TIMEOUT_SECS: u64 = 10 * 60;
+const DEFAULT_MAX_SUBMISSIONS_PER_DAY: usize = 3;
+const DEFAULT_SUBMISSION_LOG_PATH: &str = "submissions.log";
+const DEFAULT_BANLIST_PATH: &str = "banlist.txt";
+const DEFAULT_TRACKED_MERGE_REQUESTS_PATH: &str = "merge_requests.txt";
+const DEFAULT_SUBMISSION_HISTORY_PATH: &str = "submission_history.txt";
+const DEFAULT_LANGUAGES_PATH: &str = "languages.txt";
+const DEFAULT_MERGE_REQUEST_POLL_SECS: u64 = 5 * 60;
+const DEFAULT_OVERLAY_BRANCH: &str = "12.1";
+const DEFAULT_HEALTH_MAX_AGE_SECS: u64 = 120;
+const DEFAULT_SHUTDOWN_GRACE_SECS: u64 = 60;
+const DEFAULT_LATEST_COOLDOWN_SECS: u64 = 60;
+const DEFAULT_PLAYSTORE_RATE_LIMIT_SECS: f64 = 2.0;
+const DEFAULT_FDROID_RATE_LIMIT_SECS: f64 = 1.0;
+const DEFAULT_GITHUB_RATE_LIMIT_SECS: f64 = 0.5;
+const DEFAULT_MODERATION_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_MODERATION_MAX_REJECTIONS: u32 = 3;
+
+const DEFAULT_ALPHA_THRESHOLD: u8 = 128;
+const DEFAULT_CROP_MARGIN_PERCENT: f32 = 5.0;
+const DEFAULT_FILTER_SPECKLE: usize = 4;
+const DEFAULT_CORNER_THRESHOLD: i32 = 60;
+const DEFAULT_LENGTH_THRESHOLD: f64 = 4.0;
+pub const MIN_LENGTH_THRESHOLD: f64 = 3.5;
+pub const MAX_LENGTH_THRESHOLD: f64 = 10.0;
+const DEFAULT_MAX_ICON_DIMENSION: u32 = 1024;
+const DEFAULT_MIN_ICON_DIMENSION: u32 = 128;
+const DEFAULT_RECOMMENDED_ICON_DIMENSION: u32 = 256;
+const DEFAULT_MAX_ICON_FILE_SIZE: u32 = 10 * 1024 * 1024;
+
+/// Returned when the environment is missing variables or has invalid values.
+/// Lists every problem at once so they can all be fixed in one go.
+#[derive(Debug)]
+pub struct InvalidConfig(Vec<String>);
+
+impl fmt::Display for InvalidConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "invalid configuration:")?;
+
+        for problem in &self.0 {
+            writeln!(f, "  - {problem}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for InvalidConfig {}
+
+/// A branch of the overlay repository icons can be submitted for.
+#[derive(Clone, Debug)]
+pub struct OverlayBranch {
+    pub name: String,
+    pub label: String,
+}
+
+/// A release channel shown by `/latest`, with the URL of its OTA metadata.
+#[derive(Clone, Debug)]
+pub struct ReleaseChannel {
+    /// Short name inline queries are matched against, e.g. `dcosx-pre`.
+    pub key: String,
+    pub display_name: String,
+    pub url: String,
+}
+
+/// Everything the bot reads from the environment, loaded once at startup.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub bot_token: String,
+    /// Chat that is told about failed submissions.
+    pub maintainer_chat_id: Option<ChatId>,
+    /// Channel every created merge request is recorded in.
+    pub audit_chat_id: Option<ChatId>,
+    /// Chat submissions have to be approved in before they are submitted.
+    /// Review mode is off if this is not set.
+    pub review_chat_id: Option<ChatId>,
+    /// Telegram user ids allowed to approve or reject submissions.
+    pub reviewer_ids: Vec<i64>,
+    /// How long a submission waits for review before it is dropped.
+    pub review_timeout: Duration,
+    /// How long the bot may work on a submission, not counting the time it
+    /// waits for the user or a reviewer.
+    pub submission_timeout: Duration,
+    pub max_submissions_per_day: usize,
+    /// Telegram user ids not subject to the submission limit.
+    pub rate_limit_exempt_ids: Vec<i64>,
+    /// File the submissions counted against the limit are kept in.
+    pub submission_log_path: String,
+    /// Telegram user ids that may use the admin commands.
+    pub admin_ids: Vec<i64>,
+    /// If not empty, only these users and the admins may submit icons.
+    pub allowed_ids: Vec<i64>,
+    /// File the ids of banned users are kept in.
+    pub banlist_path: String,
+    /// File the merge requests submitters are waiting on are kept in.
+    pub tracked_merge_requests_path: String,
+    /// File the completed submissions counted by `/stats` are kept in.
+    pub submission_history_path: String,
+    /// File the languages chats picked with `/language` are kept in.
+    pub languages_path: String,
+    /// How often tracked merge requests are checked for being merged or
+    /// closed.
+    pub merge_request_poll_interval: Duration,
+    pub gitlab_token: String,
+    pub gitlab_project_id: u64,
+    /// Comma separated labels added to created merge requests.
+    pub gitlab_mr_labels: Option<String>,
+    /// GitLab user created merge requests are assigned to.
+    pub gitlab_mr_assignee_id: Option<u64>,
+    /// GitLab users asked to review created merge requests.
+    pub gitlab_mr_reviewer_ids: Option<Vec<u64>>,
+    pub submission_backend: SubmissionBackend,
+    /// Local checkout of the overlay, used as cache by the git backend.
+    pub overlay_path: Option<String>,
+    pub overlay_remote_url: Option<String>,
+    /// The first branch is the default one.
+    pub overlay_branches: Vec<OverlayBranch>,
+    pub ssh_key_path: Option<String>,
+    /// Identity the bot commits with. The identity configured in the overlay
+    /// checkout is used if these are not set.
+    pub git_author_name: Option<String>,
+    pub git_author_email: Option<String>,
+    /// Path of an ssh key commits are signed with. Only the git backend signs
+    /// commits.
+    pub git_signing_key: Option<String>,
+    /// In the order they are shown.
+    pub release_channels: Vec<ReleaseChannel>,
+    /// How long `/latest` isn't posted again in a group chat after it was
+    /// answered there.
+    pub latest_cooldown: Duration,
+    /// Minimum time between requests to the Play Store.
+    pub playstore_rate_limit: Duration,
+    /// Minimum time between requests to F-Droid.
+    pub fdroid_rate_limit: Duration,
+    /// Minimum time between requests for the OTA metadata on GitHub.
+    pub github_rate_limit: Duration,
+    /// Endpoint uploaded images are screened with. Uploads aren't screened
+    /// if this is not set.
+    pub moderation_url: Option<String>,
+    pub moderation_timeout: Duration,
+    /// Rejected uploads after which a user is banned, 0 to never ban.
+    pub moderation_max_rejections: u32,
+    pub svg2vd_bin: String,
+    /// Alpha value from which on a pixel is considered part of the icon.
+    pub alpha_threshold: u8,
+    pub filter_speckle: usize,
+    pub corner_threshold: i32,
+    pub length_threshold: f64,
+    /// Transparent margin kept around the icon after cropping, as a fraction
+    /// of its size.
+    pub crop_margin: f32,
+    /// Maximum width and height an icon is traced at, larger images are
+    /// scaled down first.
+    pub max_icon_dimension: u32,
+    /// Minimum width and height of a PNG icon, smaller ones trace too
+    /// jagged to be used.
+    pub min_icon_dimension: u32,
+    /// PNG icons below this width or height are accepted with a warning.
+    pub recommended_icon_dimension: u32,
+    /// Maximum size in bytes of an uploaded icon file.
+    pub max_icon_file_size: u32,
+    /// Port `/healthz` and `/metrics` are served on. No server is started if
+    /// this is not set.
+    pub metrics_port: Option<u16>,
+    /// How long Telegram may not answer before `/healthz` fails.
+    pub health_max_age: Duration,
+    /// How long running handlers may take to finish after a shutdown signal.
+    pub shutdown_grace: Duration,
+    /// Prepares submissions and removals without pushing them or opening
+    /// merge requests, for testing.
+    pub dry_run: bool,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, InvalidConfig> {
+        let mut problems = Vec::new();
+
+        let bot_token = required("TELOXIDE_TOKEN", &mut problems);
+        let gitlab_token = required("GITLAB_TOKEN", &mut problems);
+        let maintainer_chat_id = chat_id("MAINTAINER_CHAT_ID", &mut problems);
+        let audit_chat_id = chat_id("AUDIT_CHAT_ID", &mut problems);
+        let review_chat_id = chat_id("REVIEW_CHAT_ID", &mut problems);
+        let reviewer_ids: Vec<i64> = ids("REVIEWER_IDS", &mut problems);
+
+        if review_chat_id.is_some() && reviewer_ids.is_empty() {
+            problems.push(String::from(
+                "REVIEW_CHAT_ID is set, but REVIEWER_IDS is empty",
+            ));
+        }
+        let overlay_path = optional("PATH_TO_ICONS_OVERLAY");
+        let overlay_remote_url = optional("OVERLAY_REMOTE_URL");
+
+        let submission_backend = match optional("SUBMISSION_BACKEND").as_deref() {
+            None | Some("git") => SubmissionBackend::Git,
+            Some("gitlab_api") => SubmissionBackend::GitLabApi,
+            Some(other) => {
+                problems.push(format!(
+                    "SUBMISSION_BACKEND must be git or gitlab_api, not {other}"
+                ));
+
+                SubmissionBackend::Git
+            }
+        };
+
+        if submission_backend == SubmissionBackend::Git
+            && overlay_path.is_none()
+            && overlay_remote_url.is_none()
+        {
+            problems.push(String::from(
+                "the git backend needs PATH_TO_ICONS_OVERLAY or OVERLAY_REMOTE_URL",
+            ));
+        }
+
+        let min_icon_dimension = parsed(
+            "MIN_ICON_DIMENSION",
+            DEFAULT_MIN_ICON_DIMENSION,
+            |dimension| *dimension > 0,
+            &mut problems,
+        );
+        let recommended_icon_dimension = parsed(
+            "RECOMMENDED_ICON_DIMENSION",
+            DEFAULT_RECOMMENDED_ICON_DIMENSION.max(min_icon_dimension),
+            |dimension| *dimension >= min_icon_dimension,
+            &mut problems,
+        );
+
+        let git_author_name = optional("GIT_AUTHOR_NAME");
+        let git_author_email = optional("GIT_AUTHOR_EMAIL");
+
+        if git_author_name.is_some() != git_author_email.is_some() {
+            problems.push(String::from(
+                "GIT_AUTHOR_NAME and GIT_AUTHOR_EMAIL must be set together",
+            ));
+        }
+
+        let gitlab_mr_assignee_id = optional("GITLAB_MR_ASSIGNEE_ID")
+            .map(|_| parsed("GITLAB_MR_ASSIGNEE_ID", 0, |id| *id > 0, &mut problems));
+        let gitlab_mr_reviewer_ids = Some(ids("GITLAB_MR_REVIEWER_IDS", &mut problems))
+            .filter(|reviewer_ids: &Vec<u64>| !reviewer_ids.is_empty());
+
+        let metrics_port = optional("METRICS_PORT")
+            .map(|_| parsed("METRICS_PORT", 0, |port| *port > 0, &mut problems));
+
+        let config = Self {
+            bot_token,
+            maintainer_chat_id,
+            audit_chat_id,
+            review_chat_id,
+            reviewer_ids,
+            review_timeout: Duration::from_secs(parsed(
+                "REVIEW_TIMEOUT_SECS",
+                DEFAULT_REVIEW_TIMEOUT_SECS,
+                |secs| *secs > 0,
+                &mut problems,
+            )),
+            submission_timeout: Duration::from_secs(parsed(
+                "SUBMISSION_TIMEOUT_SECS",
+                DEFAULT_SUBMISSION_TIMEOUT_SECS,
+                |secs| *secs > 0,
+                &mut problems,
+            )),
+            max_submissions_per_day: parsed(
+                "MAX_SUBMISSIONS_PER_DAY",
+                DEFAULT_MAX_SUBMISSIONS_PER_DAY,
+                |max| *max > 0,
+                &mut problems,
+            ),
+            rate_limit_exempt_ids: ids("RATE_LIMIT_EXEMPT_IDS", &mut problems),
+            submission_log_path: optional("SUBMISSION_LOG_PATH")
+                .unwrap_or_else(|| DEFAULT_SUBMISSION_LOG_PATH.to_owned()),
+            admin_ids: ids("ADMIN_IDS", &mut problems),
+            allowed_ids: ids("ALLOWED_IDS", &mut problems),
+            banlist_path: optional("BANLIST_PATH")
+                .unwrap_or_else(|| DEFAULT_BANLIST_PATH.to_owned()),
+            tracked_merge_requests_path: optional("TRACKED_MERGE_REQUESTS_PATH")
+                .unwrap_or_else(|| DEFAULT_TRACKED_MERGE_REQUESTS_PATH.to_owned()),
+            submission_history_path: optional("SUBMISSION_HISTORY_PATH")
+                .unwrap_or_else(|| DEFAULT_SUBMISSION_HISTORY_PATH.to_owned()),
+            languages_path: optional("LANGUAGES_PATH")
+                .unwrap_or_else(|| DEFAULT_LANGUAGES_PATH.to_owned()),
+            merge_request_poll_interval: Duration::from_secs(parsed(
+                "MERGE_REQUEST_POLL_SECS",
+                DEFAULT_MERGE_REQUEST_POLL_SECS,
+                |secs| *secs > 0,
+                &mut problems,
+            )),
+            gitlab_token,
+            gitlab_project_id: parsed(
+                "OVERLAY_GITLAB_PROJECT_ID",
+                DEFAULT_GITLAB_PROJECT_ID,
+                |_| true,
+                &mut problems,
+            ),
+            gitlab_mr_labels: optional("GITLAB_MR_LABELS"),
+            gitlab_mr_assignee_id,
+            gitlab_mr_reviewer_ids,
+            submission_backend,
+            overlay_path,
+            overlay_remote_url,
+            overlay_branches: overlay_branches(),
+            ssh_key_path: optional("SSH_KEY_PATH").or_else(|| optional("SSH_KEY")),
+            git_author_name,
+            git_author_email,
+            git_signing_key: optional("GIT_SIGNING_KEY"),
+            release_channels: release_channels(&mut problems),
+            latest_cooldown: Duration::from_secs(parsed(
+                "LATEST_COOLDOWN_SECS",
+                DEFAULT_LATEST_COOLDOWN_SECS,
+                |_| true,
+                &mut problems,
+            )),
+            playstore_rate_limit: rate_limit(
+                "PLAYSTORE_RATE_LIMIT_SECS",
+                DEFAULT_PLAYSTORE_RATE_LIMIT_SECS,
+                &mut problems,
+            ),
+            fdroid_rate_limit: rate_limit(
+                "FDROID_RATE_LIMIT_SECS",
+                DEFAULT_FDROID_RATE_LIMIT_SECS,
+                &mut problems,
+            ),
+            github_rate_limit: rate_limit(
+                "GITHUB_RATE_LIMIT_SECS",
+                DEFAULT_GITHUB_RATE_LIMIT_SECS,
+                &mut problems,
+            ),
+            moderation_url: optional("MODERATION_URL"),
+            moderation_timeout: Duration::from_secs(parsed(
+                "MODERATION_TIMEOUT_SECS",
+                DEFAULT_MODERATION_TIMEOUT_SECS,
+                |secs| *secs > 0,
+                &mut problems,
+            )),
+            moderation_max_rejections: parsed(
+                "MODERATION_MAX_REJECTIONS",
+                DEFAULT_MODERATION_MAX_REJECTIONS,
+                |_| true,
+                &mut problems,
+            ),
+            svg2vd_bin: optional("SVG2VD_BIN").unwrap_or_else(|| String::from("svg2vd")),
+            alpha_threshold: parsed(
+                "ALPHA_THRESHOLD",
+                DEFAULT_ALPHA_THRESHOLD,
+                |threshold| *threshold > 0,
+                &mut problems,
+            ),
+            filter_speckle: parsed(
+                "TRACE_FILTER_SPECKLE",
+                DEFAULT_FILTER_SPECKLE,
+                |_| true,
+                &mut problems,
+            ),
+            corner_threshold: parsed(
+                "TRACE_CORNER_THRESHOLD",
+                DEFAULT_CORNER_THRESHOLD,
+                |angle| (0..=180).contains(angle),
+                &mut problems,
+            ),
+            length_threshold: parsed(
+                "TRACE_LENGTH_THRESHOLD",
+                DEFAULT_LENGTH_THRESHOLD,
+                |length| (MIN_LENGTH_THRESHOLD..=MAX_LENGTH_THRESHOLD).contains(length),
+                &mut problems,
+            ),
+            crop_margin: parsed(
+                "CROP_MARGIN_PERCENT",
+                DEFAULT_CROP_MARGIN_PERCENT,
+                |percent| *percent >= 0.0,
+                &mut problems,
+            ) / 100.0,
+            max_icon_dimension: parsed(
+                "MAX_ICON_DIMENSION",
+                DEFAULT_MAX_ICON_DIMENSION,
+                |dimension| *dimension > 0,
+                &mut problems,
+            ),
+            min_icon_dimension,
+            recommended_icon_dimension,
+            max_icon_file_size: parsed(
+                "MAX_ICON_FILE_SIZE",
+                DEFAULT_MAX_ICON_FILE_SIZE,
+                |_| true,
+                &mut problems,
+            ),
+            metrics_port,
+            health_max_age: Duration::from_secs(parsed(
+                "HEALTH_MAX_AGE_SECS",
+                DEFAULT_HEALTH_MAX_AGE_SECS,
+                |secs| *secs > 0,
+                &mut problems,
+            )),
+            shutdown_grace: Duration::from_secs(parsed(
+                "SHUTDOWN_GRACE_SECS",
+                DEFAULT_SHUTDOWN_GRACE_SECS,
+                |_| true,
+                &mut problems,
+            )),
+            dry_run: parsed("DRY_RUN", 0u8, |value| *value <= 1, &mut problems) == 1,
+        };
+
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(InvalidConfig(problems))
+        }
+    }
+
+    pub fn default_overlay_branch(&self) -> &str {
+        &self.overlay_branches[0].name
+    }
+
+    /// The configuration with every optional feature off, for tests. Doesn't
+    /// read the environment, so tests can change it without racing.
+    #[cfg(test)]
+    pub fn for_tests() -> Self {
+        Self {
+            bot_token: String::from("token"),
+            maintainer_chat_id: None,
+            audit_chat_id: None,
+            review_chat_id: None,
+            reviewer_ids: Vec::new(),
+            review_timeout: Duration::from_secs(DEFAULT_REVIEW_TIMEOUT_SECS),
+            submission_timeout: Duration::from_secs(DEFAULT_SUBMISSION_TIMEOUT_SECS),
+            max_submissions_per_day: DEFAULT_MAX_SUBMISSIONS_PER_DAY,
+            rate_limit_exempt_ids: Vec::new(),
+            submission_log_path: DEFAULT_SUBMISSION_LOG_PATH.to_owned(),
+            admin_ids: Vec::new(),
+            allowed_ids: Vec::new(),
+            banlist_path: DEFAULT_BANLIST_PATH.to_owned(),
+            tracked_merge_requests_path: DEFAULT_TRACKED_MERGE_REQUESTS_PATH.to_owned(),
+            submission_history_path: DEFAULT_SUBMISSION_HISTORY_PATH.to_owned(),
+            languages_path: DEFAULT_LANGUAGES_PATH.to_owned(),
+            merge_request_poll_interval: Duration::from_secs(DEFAULT_MERGE_REQUEST_POLL_SECS),
+            gitlab_token: String::from("gitlab-token"),
+            gitlab_project_id: DEFAULT_GITLAB_PROJECT_ID,
+            gitlab_mr_labels: None,
+            gitlab_mr_assignee_id: None,
+            gitlab_mr_reviewer_ids: None,
+            submission_backend: SubmissionBackend::Git,
+            overlay_path: None,
+            overlay_remote_url: None,
+            overlay_branches: vec![OverlayBranch {
+                name: String::from(DEFAULT_OVERLAY_BRANCH),
+                label: String::from("Android 12L"),
+            }],
+            ssh_key_path: None,
+            git_author_name: None,
+            git_author_email: None,
+            git_signing_key: None,
+            release_channels: Vec::new(),
+            latest_cooldown: Duration::from_secs(DEFAULT_LATEST_COOLDOWN_SECS),
+            playstore_rate_limit: Duration::from_secs_f64(DEFAULT_PLAYSTORE_RATE_LIMIT_SECS),
+            fdroid_rate_limit: Duration::from_secs_f64(DEFAULT_FDROID_RATE_LIMIT_SECS),
+            github_rate_limit: Duration::from_secs_f64(DEFAULT_GITHUB_RATE_LIMIT_SECS),
+            moderation_url: None,
+            moderation_timeout: Duration::from_secs(DEFAULT_MODERATION_TIMEOUT_SECS),
+            moderation_max_rejections: DEFAULT_MODERATION_MAX_REJECTIONS,
+            svg2vd_bin: String::from("svg2vd"),
+            alpha_threshold: DEFAULT_ALPHA_THRESHOLD,
+            filter_speckle: DEFAULT_FILTER_SPECKLE,
+            corner_threshold: DEFAULT_CORNER_THRESHOLD,
+            length_threshold: DEFAULT_LENGTH_THRESHOLD,
+            crop_margin: DEFAULT_CROP_MARGIN_PERCENT / 100.0,
+            max_icon_dimension: DEFAULT_MAX_ICON_DIMENSION,
+            min_icon_dimension: DEFAULT_MIN_ICON_DIMENSION,
+            recommended_icon_dimension: DEFAULT_RECOMMENDED_ICON_DIMENSION,
+            max_icon_file_size: DEFAULT_MAX_ICON_FILE_SIZE,
+            metrics_port: None,
+            health_max_age: Duration::from_secs(DEFAULT_HEALTH_MAX_AGE_SECS),
+            shutdown_grace: Duration::from_secs(DEFAULT_SHUTDOWN_GRACE_SECS),
+            dry_run: false,
+        }
+    }
+}
+
+fn optional(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+fn required(key: &str, problems: &mut Vec<String>) -> String {
+    optional(key).unwrap_or_else(|| {
+        problems.push(format!("{key} is not set"));
+
+        String::new()
+    })
+}
+
+fn chat_id(key: &str, problems: &mut Vec<String>) -> Option<ChatId> {
+    let value = optional(key)?;
+
+    match value.parse() {
+        Ok(id) => Some(ChatId(id)),
+        Err(_) => {
+            problems.push(format!("{key} has the invalid value {value}"));
+
+            None
+        }
+    }
+}
+
+/// Reads the comma separated user ids in `key`.
+fn ids<T: FromStr>(key: &str, problems: &mut Vec<String>) -> Vec<T> {
+    optional(key)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .filter_map(|id| match id.parse() {
+            Ok(id) => Some(id),
+            Err(_) => {
+                problems.push(format!("{key} contains the invalid id {id}"));
+
+                None
+            }
+        })
+        .collect()
+}
+
+/// Parses `key` if it is set, recording a problem if it doesn't parse or
+/// `valid` rejects it.
+fn parsed<T: FromStr + fmt::Display>(
+    key: &str,
+    default: T,
+    valid: impl Fn(&T) -> bool,
+    problems: &mut Vec<String>,
+) -> T {
+    let value = match optional(key) {
+        Some(value) => value,
+        None => return default,
+    };
+
+    match value.parse() {
+        Ok(parsed) if valid(&parsed) => parsed,
+        _ => {
+            problems.push(format!("{key} has the invalid value {value}"));
+
+            default
+        }
+    }
+}
+
+/// Reads the minimum time between requests to a host from the seconds in
+/// `key`, which may be fractional.
+fn rate_limit(key: &str, default_secs: f64, problems: &mut Vec<String>) -> Duration {
+    Duration::from_secs_f64(parsed(
+        key,
+        default_secs,
+        |secs| secs.is_finite() && *secs > 0.0,
+        problems,
+    ))
+}
+
+/// Reads the overlay branches accepting submissions, formatted as
+/// `branch=Label` pairs separated by commas, e.g.
+/// `12.1=Android 12L,13=Android 13`.
+fn overlay_branches() -> Vec<OverlayBranch> {
+    let branches: Vec<OverlayBranch> = optional("OVERLAY_BRANCHES")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((name, label)) => OverlayBranch {
+                name: name.trim().to_owned(),
+                label: label.trim().to_owned(),
+            },
+            None => OverlayBranch {
+                name: entry.to_owned(),
+                label: entry.to_owned(),
+            },
+        })
+        .collect();
+
+    if branches.is_empty() {
+        vec![OverlayBranch {
+            name: String::from(DEFAULT_OVERLAY_BRANCH),
+            label: String::from("Android 12L"),
+        }]
+    } else {
+        branches
+    }
+}
+
+/// Reads the release channels, formatted as `key=Display name=url` entries
+/// separated by commas, e.g.
+/// `dcos=DCOS (stable)=https://example.com/davinci.json`. Without them, the
+/// DCOS and DCOSX channels are used, whose URLs can still be overridden one by
+/// one.
+fn release_channels(problems: &mut Vec<String>) -> Vec<ReleaseChannel> {
+    let mut channels: Vec<ReleaseChannel> = optional("RELEASE_CHANNELS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let mut fields = entry.splitn(3, '=').map(str::trim);
+
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(key), Some(display_name), Some(url)) => Some(ReleaseChannel {
+                    key: key.to_owned(),
+                    display_name: display_name.to_owned(),
+                    url: url.to_owned(),
+                }),
+                _ => {
+                    problems.push(format!(
+                        "RELEASE_CHANNELS contains the invalid entry {entry}, expected key=Display name=url"
+                    ));
+
+                    None
+                }
+            }
+        })
+        .collect();
+
+    if channels.is_empty() {
+        let channel =
+            |key: &str, display_name: &str, url_key: &str, default_url: &str| ReleaseChannel {
+                key: key.to_owned(),
+                display_name: display_name.to_owned(),
+                url: optional(url_key).unwrap_or_else(|| default_url.to_owned()),
+            };
+
+        channels = vec![
+            channel("dcos", "DCOS (stable)", "OTA_DCOS_URL", DEFAULT_OTA_DCOS),
+            channel(
+                "dcos-pre",
+                "DCOS (pre-release)",
+                "OTA_DCOS_PRE_URL",
+                DEFAULT_OTA_DCOS_PRE,
+            ),
+            channel(
+                "dcosx",
+                "DCOSX (stable)",
+                "OTA_DCOSX_URL",
+                DEFAULT_OTA_DCOSX,
+            ),
+            channel(
+                "dcosx-pre",
+                "DCOSX (pre-release)",
+                "OTA_DCOSX_PRE_URL",
+                DEFAULT_OTA_DCOSX_PRE,
+            ),
+        ];
+    }
+
+    let mut keys = HashSet::new();
+
+    for channel in &channels {
+        if channel.key.is_empty()
+            || !channel
+                .key
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            problems.push(format!(
+                "the release channel key {:?} may only contain lowercase letters, digits and -",
+                channel.key
+            ));
+        }
+        if !keys.insert(channel.key.as_str()) {
+            problems.push(format!(
+                "the release channel key {} is used more than once",
+                channel.key
+            ));
+        }
+        if channel.display_name.is_empty() {
+            problems.push(format!(
+                "the release channel {} has no display name",
+                channel.key
+            ));
+        }
+        if !matches!(Url::parse(&channel.url), Ok(url) if url.scheme() == "https" || url.scheme() == "http")
+        {
+            problems.push(format!(
+                "the release channel {} has the invalid URL {}",
+                channel.key, channel.url
+            ));
+        }
+    }
+
+    channels
+}
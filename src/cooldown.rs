@@ -0,0 +1,43 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use teloxide::types::ChatId;
+
+/// Remembers where `/latest` was answered in group chats, so the releases
+/// aren't posted again every time someone asks on release days.
+pub struct LatestCooldown {
+    cooldown: Duration,
+    /// When and with which message `/latest` was last answered, by chat.
+    answered: Mutex<HashMap<ChatId, (Instant, i32)>>,
+}
+
+impl LatestCooldown {
+    pub fn new(cooldown: Duration) -> Self {
+        Self {
+            cooldown,
+            answered: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The message that answered `/latest` in `chat_id` within the cooldown,
+    /// if there is one.
+    pub fn recent(&self, chat_id: ChatId) -> Option<i32> {
+        let mut answered = self.answered.lock().unwrap();
+        // Chats whose cooldown is over are dropped, so this doesn't keep
+        // growing with every group the bot is in.
+        answered.retain(|_, (at, _)| at.elapsed() < self.cooldown);
+
+        answered.get(&chat_id).map(|(_, message_id)| *message_id)
+    }
+
+    /// Starts the cooldown of `chat_id`, which `message_id` answered.
+    pub fn answered(&self, chat_id: ChatId, message_id: i32) {
+        self.answered
+            .lock()
+            .unwrap()
+            .insert(chat_id, (Instant::now(), message_id));
+    }
+}
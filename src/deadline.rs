@@ -0,0 +1,294 @@
+use std::{
+    error::Error,
+    fmt,
+    future::Future,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use tokio::time::{timeout_at, Instant};
+
+/// Ids of submissions, so users can refer to them and they can be found in
+/// the log.
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A step of the submission pipeline, used to report where a submission ran
+/// out of time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Download,
+    Trace,
+    Convert,
+    Fetch,
+    Push,
+    MergeRequest,
+}
+
+impl Stage {
+    pub const ALL: [Self; 6] = [
+        Self::Download,
+        Self::Trace,
+        Self::Convert,
+        Self::Fetch,
+        Self::Push,
+        Self::MergeRequest,
+    ];
+
+    /// Short name of the stage for metrics.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Download => "download",
+            Self::Trace => "trace",
+            Self::Convert => "convert",
+            Self::Fetch => "fetch",
+            Self::Push => "push",
+            Self::MergeRequest => "merge_request",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Download => "downloading the image",
+            Self::Trace => "tracing the image",
+            Self::Convert => "converting the SVG to a VectorDrawable",
+            Self::Fetch => "updating the overlay repository",
+            Self::Push => "pushing the icon",
+            Self::MergeRequest => "creating the merge request",
+        })
+    }
+}
+
+/// Returned when a submission did not finish a stage before its deadline.
+#[derive(Debug)]
+pub struct DeadlineExceeded(pub Stage);
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the submission took too long while {}", self.0)
+    }
+}
+
+impl Error for DeadlineExceeded {}
+
+/// Point in time by which a submission has to be processed, started once per
+/// submission. Time spent waiting for the user to answer is not counted, the
+/// deadline is paused meanwhile, see [`Deadline::pause`].
+#[derive(Clone, Copy, Debug)]
+pub struct Deadline {
+    request_id: u64,
+    expires: Instant,
+}
+
+impl Deadline {
+    /// Starts the deadline of a new submission, `timeout` from now.
+    pub fn after(timeout: Duration) -> Self {
+        Self {
+            request_id: NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed),
+            expires: Instant::now() + timeout,
+        }
+    }
+
+    /// The id the submission is reported to the user and in the log with.
+    pub fn request_id(self) -> u64 {
+        self.request_id
+    }
+
+    /// Stops the clock while the bot waits for the user.
+    pub fn pause(self) -> PausedDeadline {
+        PausedDeadline {
+            request_id: self.request_id,
+            remaining: self.expires.saturating_duration_since(Instant::now()),
+        }
+    }
+
+    /// Fails if the deadline has already passed before `stage` is started.
+    /// Used for stages that must not be interrupted once they are running.
+    pub fn check(self, stage: Stage) -> Result<(), DeadlineExceeded> {
+        if Instant::now() >= self.expires {
+            log::warn!(
+                "Deadline of submission #{} exceeded before {stage}",
+                self.request_id
+            );
+
+            Err(DeadlineExceeded(stage))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Runs `fut` as `stage` of the submission and gives up on it once the
+    /// deadline passes.
+    pub async fn run<T, E, F>(self, stage: Stage, fut: F) -> Result<T, Box<dyn Error + Send + Sync>>
+    where
+        F: Future<Output = Result<T, E>>,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        self.check(stage)?;
+
+        match timeout_at(self.expires, fut).await {
+            Ok(result) => result.map_err(Into::into),
+            Err(_) => {
+                log::warn!(
+                    "Deadline of submission #{} exceeded while {stage}",
+                    self.request_id
+                );
+
+                Err(DeadlineExceeded(stage).into())
+            }
+        }
+    }
+}
+
+/// A [`Deadline`] whose clock is stopped while the bot waits for the user.
+#[derive(Clone, Copy, Debug)]
+pub struct PausedDeadline {
+    request_id: u64,
+    remaining: Duration,
+}
+
+impl PausedDeadline {
+    /// Starts the clock again with the time that was left.
+    pub fn resume(self) -> Deadline {
+        Deadline {
+            request_id: self.request_id,
+            expires: Instant::now() + self.remaining,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use tokio::time::sleep;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// The stage `result` ran out of time in, if it did.
+    fn exceeded<T>(result: Result<T, Box<dyn Error + Send + Sync>>) -> Option<String> {
+        match result {
+            Ok(_) => None,
+            Err(e) => Some(e.downcast_ref::<DeadlineExceeded>()?.0.to_string()),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn every_stage_is_cut_off_at_the_deadline() {
+        for stage in Stage::ALL {
+            let deadline = Deadline::after(TIMEOUT);
+            let start = Instant::now();
+
+            let result = deadline
+                .run(stage, async {
+                    sleep(TIMEOUT * 2).await;
+
+                    Ok::<_, DeadlineExceeded>(())
+                })
+                .await;
+
+            assert_eq!(exceeded(result), Some(stage.to_string()));
+            assert_eq!(start.elapsed(), TIMEOUT);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stages_finishing_in_time_succeed() {
+        let deadline = Deadline::after(TIMEOUT);
+
+        for stage in Stage::ALL {
+            let result = deadline
+                .run(stage, async {
+                    sleep(TIMEOUT / 10).await;
+
+                    Ok::<_, DeadlineExceeded>(stage.to_string())
+                })
+                .await;
+
+            assert_eq!(result.unwrap(), stage.to_string());
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn later_stages_share_the_deadline() {
+        let deadline = Deadline::after(TIMEOUT);
+
+        let download = deadline
+            .run(Stage::Download, async {
+                sleep(TIMEOUT - Duration::from_secs(1)).await;
+
+                Ok::<_, DeadlineExceeded>(())
+            })
+            .await;
+        let trace = deadline
+            .run(Stage::Trace, async {
+                sleep(Duration::from_secs(2)).await;
+
+                Ok::<_, DeadlineExceeded>(())
+            })
+            .await;
+
+        assert!(download.is_ok());
+        assert_eq!(exceeded(trace), Some(Stage::Trace.to_string()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stages_after_the_deadline_are_not_started() {
+        let deadline = Deadline::after(TIMEOUT);
+        tokio::time::advance(TIMEOUT).await;
+
+        for stage in Stage::ALL {
+            let started = Cell::new(false);
+
+            let result = deadline
+                .run(stage, async {
+                    started.set(true);
+
+                    Ok::<_, DeadlineExceeded>(())
+                })
+                .await;
+
+            assert_eq!(exceeded(result), Some(stage.to_string()));
+            assert!(!started.get());
+            assert!(deadline.check(stage).is_err());
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn errors_of_a_stage_are_passed_on() {
+        let deadline = Deadline::after(TIMEOUT);
+
+        let result = deadline
+            .run(Stage::Push, async { Err::<(), _>("push rejected") })
+            .await;
+
+        assert_eq!(result.unwrap_err().to_string(), "push rejected");
+        assert!(deadline.check(Stage::MergeRequest).is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waiting_for_the_user_is_not_counted() {
+        let deadline = Deadline::after(TIMEOUT);
+        sleep(TIMEOUT - Duration::from_secs(1)).await;
+
+        let paused = deadline.pause();
+        sleep(TIMEOUT * 10).await;
+        let deadline = paused.resume();
+
+        assert!(deadline.check(Stage::Push).is_ok());
+        sleep(Duration::from_secs(1)).await;
+        assert!(deadline.check(Stage::Push).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn submissions_keep_their_request_id() {
+        let first = Deadline::after(TIMEOUT);
+        let second = Deadline::after(TIMEOUT);
+
+        assert_ne!(first.request_id(), second.request_id());
+        assert_eq!(second.pause().resume().request_id(), second.request_id());
+    }
+}
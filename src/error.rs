@@ -0,0 +1,138 @@
+use std::error::Error;
+
+use teloxide::{dispatching::dialogue::InMemStorageError, DownloadError, RequestError};
+
+use crate::{
+    deadline::Stage,
+    messages::{Language, Text},
+    preprocess::InvalidVectorDrawable,
+    tools::CommandFailed,
+};
+
+/// Everything that can make a handler fail. Each variant maps to a message
+/// the user can act on, see [`BotError::user_message`].
+#[derive(Debug, thiserror::Error)]
+pub enum BotError {
+    #[error("failed to download the file from Telegram")]
+    Download(#[source] Box<dyn Error + Send + Sync>),
+    #[error("failed to decode the image")]
+    ImageDecode(#[from] image::ImageError),
+    #[error("failed to trace the image: {0}")]
+    Trace(String),
+    #[error("failed to convert the SVG to a VectorDrawable")]
+    Vd(#[source] Box<dyn Error + Send + Sync>),
+    #[error("overlay repository operation failed")]
+    Git(#[from] git2::Error),
+    #[error("GitLab API responded with {status}: {message}")]
+    GitLabApi {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("failed to fetch the OTA metadata")]
+    Ota(#[source] reqwest::Error),
+    #[error("HTTP request failed")]
+    Http(#[from] reqwest::Error),
+    #[error("Telegram request failed")]
+    Telegram(#[from] RequestError),
+    #[error("failed to store the dialogue state")]
+    Dialogue(#[from] InMemStorageError),
+    #[error(transparent)]
+    Other(Box<dyn Error + Send + Sync>),
+}
+
+impl BotError {
+    /// A short explanation for the chat. The details only go to the log.
+    pub fn user_message(&self, language: Language) -> String {
+        language.text(match self {
+            Self::Download(_) => Text::DownloadFailed,
+            Self::ImageDecode(_) => Text::DecodeFailed,
+            Self::Trace(_) => Text::TraceFailed,
+            Self::Vd(_) => Text::VdFailed,
+            Self::Git(_) => Text::GitFailed,
+            Self::GitLabApi { status, .. } => Text::GitLabRejected { status: *status },
+            Self::Ota(_) => Text::OtaFailed,
+            Self::Http(_) => Text::ServiceUnavailable,
+            Self::Telegram(_) | Self::Dialogue(_) | Self::Other(_) => Text::InternalError,
+        })
+    }
+
+    /// The submission stage this kind of error comes from, if it is specific
+    /// to one.
+    pub fn stage(&self) -> Option<Stage> {
+        match self {
+            Self::Download(_) => Some(Stage::Download),
+            Self::ImageDecode(_) | Self::Trace(_) => Some(Stage::Trace),
+            Self::Vd(_) => Some(Stage::Convert),
+            Self::GitLabApi { .. } => Some(Stage::MergeRequest),
+            _ => None,
+        }
+    }
+
+    /// The error followed by all of its sources, for the log.
+    pub fn chain(&self) -> String {
+        let mut chain = self.to_string();
+        let mut source = self.source();
+
+        while let Some(error) = source {
+            chain.push_str(&format!(": {error}"));
+            source = error.source();
+        }
+
+        chain
+    }
+}
+
+impl From<Box<dyn Error + Send + Sync>> for BotError {
+    /// Recovers the variant of errors that were boxed on the way, e.g. by
+    /// [`crate::deadline::Deadline::run`].
+    fn from(e: Box<dyn Error + Send + Sync>) -> Self {
+        let e = match e.downcast::<Self>() {
+            Ok(e) => return *e,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<git2::Error>() {
+            Ok(e) => return Self::Git(*e),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<image::ImageError>() {
+            Ok(e) => return Self::ImageDecode(*e),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<reqwest::Error>() {
+            Ok(e) => return Self::Http(*e),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<RequestError>() {
+            Ok(e) => return Self::Telegram(*e),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<InMemStorageError>() {
+            Ok(e) => return Self::Dialogue(*e),
+            Err(e) => e,
+        };
+
+        if e.is::<DownloadError>() {
+            Self::Download(e)
+        } else if e.is::<CommandFailed>() || e.is::<InvalidVectorDrawable>() {
+            Self::Vd(e)
+        } else {
+            Self::Other(e)
+        }
+    }
+}
+
+/// Like [`reqwest::Response::error_for_status`], but keeps the message
+/// GitLab sent along with the status.
+pub async fn gitlab_error_for_status(
+    response: reqwest::Response,
+) -> Result<reqwest::Response, BotError> {
+    let status = response.status();
+
+    if status.is_client_error() || status.is_server_error() {
+        let message = response.text().await.unwrap_or_default();
+
+        Err(BotError::GitLabApi { status, message })
+    } else {
+        Ok(response)
+    }
+}
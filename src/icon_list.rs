@@ -0,0 +1,104 @@
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+use crate::{
+    icon_map::IconMap,
+    messages::{Language, Text},
+};
+
+/// Entries per message, short enough to stay below Telegram's limit of 4096
+/// characters.
+const PAGE_SIZE: usize = 40;
+
+/// Callback data may be at most 64 bytes, the filter has to fit next to the
+/// prefix and page number.
+pub const MAX_FILTER_LEN: usize = 48;
+
+/// Renders page `page` of the entries of `icon_map` on `branch` that contain
+/// `filter`, sorted by package. Returns the text and the buttons to the
+/// neighbouring pages, if there are any.
+pub fn render_page(
+    language: Language,
+    icon_map: &IconMap,
+    branch: &str,
+    filter: &str,
+    page: usize,
+) -> (String, Option<InlineKeyboardMarkup>) {
+    let filter_lowercase = filter.to_lowercase();
+    let mut icons = icon_map
+        .icons
+        .iter()
+        .filter(|icon| {
+            icon.package.to_lowercase().contains(&filter_lowercase)
+                || icon.drawable.to_lowercase().contains(&filter_lowercase)
+        })
+        .collect::<Vec<_>>();
+    icons.sort_by(|a, b| a.package.cmp(&b.package));
+
+    let pages = ((icons.len() + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+    let page = page.min(pages - 1);
+
+    let mut text = language.text(if filter.is_empty() {
+        Text::IconCount {
+            count: icons.len(),
+            branch,
+        }
+    } else {
+        Text::IconMatches {
+            count: icons.len(),
+            total: icon_map.icons.len(),
+            branch,
+            filter,
+        }
+    });
+
+    if pages > 1 {
+        text.push_str(&language.text(Text::Page {
+            page: page + 1,
+            pages,
+        }));
+    }
+    text.push('\n');
+
+    for icon in icons.iter().skip(page * PAGE_SIZE).take(PAGE_SIZE) {
+        let drawable = icon
+            .drawable
+            .strip_prefix("@drawable/")
+            .unwrap_or(&icon.drawable);
+
+        text.push_str(&format!("\n{}: {drawable}", icon.package));
+    }
+
+    let mut buttons = Vec::new();
+    if page > 0 {
+        buttons.push(InlineKeyboardButton::callback(
+            language.text(Text::PreviousPage),
+            callback_data(page - 1, filter),
+        ));
+    }
+    if page + 1 < pages {
+        buttons.push(InlineKeyboardButton::callback(
+            language.text(Text::NextPage),
+            callback_data(page + 1, filter),
+        ));
+    }
+
+    let keyboard = if buttons.is_empty() {
+        None
+    } else {
+        Some(InlineKeyboardMarkup::default().append_row(buttons))
+    };
+
+    (text, keyboard)
+}
+
+/// Builds the callback data of a page button.
+fn callback_data(page: usize, filter: &str) -> String {
+    format!("icons:{page}:{filter}")
+}
+
+/// Parses callback data built by [`callback_data`] into the page and filter.
+pub fn parse_callback_data(data: &str) -> Option<(usize, &str)> {
+    let (page, filter) = data.strip_prefix("icons:")?.split_once(':')?;
+
+    Some((page.parse().ok()?, filter))
+}
@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Icon {
+    pub drawable: String,
+    pub package: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct Icons {
+    #[serde(rename = "icon", default)]
+    icons: Vec<Icon>,
+}
+
+/// The contents of `grayscale_icon_map.xml`, mapping packages to drawables.
+pub struct IconMap {
+    /// Everything up to and including the root start tag, so the XML
+    /// declaration and root attributes survive a round trip.
+    header: String,
+    trailing_newline: bool,
+    pub icons: Vec<Icon>,
+}
+
+impl IconMap {
+    pub fn parse(xml: &str) -> Result<Self, quick_xml::DeError> {
+        let Icons { icons } = quick_xml::de::from_str(xml)?;
+
+        let root_start = xml.find("<icons").unwrap_or(0);
+        let header = match xml[root_start..].find('>') {
+            Some(end) => {
+                let header = &xml[..root_start + end + 1];
+
+                match header.strip_suffix("/>") {
+                    Some(header) => format!("{}>", header.trim_end()),
+                    None => header.to_owned(),
+                }
+            }
+            None => String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<icons>"),
+        };
+
+        Ok(Self {
+            header,
+            trailing_newline: xml.ends_with('\n'),
+            icons,
+        })
+    }
+
+    /// The drawable `package` is currently mapped to.
+    pub fn drawable_for(&self, package: &str) -> Option<&str> {
+        self.icons
+            .iter()
+            .find(|icon| icon.package == package)
+            .map(|icon| icon.drawable.as_str())
+    }
+
+    /// Maps `package` to `drawable`, replacing any previous entry for the
+    /// package, and keeps the entries sorted by drawable.
+    pub fn insert(&mut self, drawable: String, package: String) {
+        self.icons.retain(|icon| icon.package != package);
+        self.icons.push(Icon { drawable, package });
+        self.icons
+            .sort_by_key(|icon| (icon.drawable.to_lowercase(), icon.package.clone()));
+    }
+
+    /// Maps `package` to `drawable` like [`IconMap::insert`]. Returns the
+    /// drawable the package used before if no package uses it anymore.
+    pub fn replace(&mut self, drawable: String, package: String) -> Option<String> {
+        let previous = self.drawable_for(&package).map(str::to_owned);
+        self.insert(drawable, package);
+
+        previous.filter(|previous| !self.icons.iter().any(|icon| &icon.drawable == previous))
+    }
+
+    /// Removes the entry of `package`. Returns the drawable it used if no
+    /// package uses it anymore.
+    pub fn remove(&mut self, package: &str) -> Option<String> {
+        let previous = self.drawable_for(package).map(str::to_owned);
+        self.icons.retain(|icon| icon.package != package);
+
+        previous.filter(|previous| !self.icons.iter().any(|icon| &icon.drawable == previous))
+    }
+
+    /// The entries for the package `name` or the drawable `name`, which may
+    /// leave out the `@drawable/themed_icon_` prefix.
+    pub fn lookup(&self, name: &str) -> Vec<&Icon> {
+        let name = name.trim();
+
+        self.icons
+            .iter()
+            .filter(|icon| {
+                icon.package == name
+                    || icon.drawable == name
+                    || icon.drawable.strip_prefix("@drawable/") == Some(name)
+                    || icon.drawable.strip_prefix("@drawable/themed_icon_") == Some(name)
+            })
+            .collect()
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut xml = self.header.clone();
+        xml.push('\n');
+
+        for icon in &self.icons {
+            xml.push_str(&format!(
+                "    <icon drawable=\"{}\" package=\"{}\" />\n",
+                escape(&icon.drawable),
+                escape(&icon.package)
+            ));
+        }
+
+        xml.push_str("</icons>");
+
+        if self.trailing_newline {
+            xml.push('\n');
+        }
+
+        xml
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+        <icons xmlns:tools=\"http://schemas.android.com/tools\">";
+
+    /// The icon map with an entry for each of the `(drawable, package)` pairs.
+    fn xml(entries: &[(&str, &str)]) -> String {
+        let mut xml = format!("{HEADER}\n");
+
+        for (drawable, package) in entries {
+            xml.push_str(&format!(
+                "    <icon drawable=\"@drawable/themed_icon_{drawable}\" package=\"{package}\" />\n"
+            ));
+        }
+
+        xml + "</icons>\n"
+    }
+
+    fn fixture() -> IconMap {
+        IconMap::parse(&xml(&[
+            ("camera", "org.example.camera"),
+            ("maps", "org.example.maps"),
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trip_keeps_the_file() {
+        let original = xml(&[
+            ("camera", "org.example.camera"),
+            ("maps", "org.example.maps"),
+        ]);
+
+        assert_eq!(IconMap::parse(&original).unwrap().to_xml(), original);
+    }
+
+    #[test]
+    fn insert_at_start() {
+        let mut icon_map = fixture();
+        icon_map.insert(
+            String::from("@drawable/themed_icon_browser"),
+            String::from("org.example.browser"),
+        );
+
+        assert_eq!(
+            icon_map.to_xml(),
+            xml(&[
+                ("browser", "org.example.browser"),
+                ("camera", "org.example.camera"),
+                ("maps", "org.example.maps"),
+            ])
+        );
+    }
+
+    #[test]
+    fn insert_in_middle() {
+        let mut icon_map = fixture();
+        icon_map.insert(
+            String::from("@drawable/themed_icon_dialer"),
+            String::from("org.example.dialer"),
+        );
+
+        assert_eq!(
+            icon_map.to_xml(),
+            xml(&[
+                ("camera", "org.example.camera"),
+                ("dialer", "org.example.dialer"),
+                ("maps", "org.example.maps"),
+            ])
+        );
+    }
+
+    #[test]
+    fn insert_at_end() {
+        let mut icon_map = fixture();
+        icon_map.insert(
+            String::from("@drawable/themed_icon_weather"),
+            String::from("org.example.weather"),
+        );
+
+        assert_eq!(
+            icon_map.to_xml(),
+            xml(&[
+                ("camera", "org.example.camera"),
+                ("maps", "org.example.maps"),
+                ("weather", "org.example.weather"),
+            ])
+        );
+    }
+
+    #[test]
+    fn replace_existing_entry() {
+        let mut icon_map = fixture();
+        let unused = icon_map.replace(
+            String::from("@drawable/themed_icon_atlas"),
+            String::from("org.example.maps"),
+        );
+
+        assert_eq!(unused.as_deref(), Some("@drawable/themed_icon_maps"));
+        assert_eq!(
+            icon_map.to_xml(),
+            xml(&[
+                ("atlas", "org.example.maps"),
+                ("camera", "org.example.camera"),
+            ])
+        );
+    }
+
+    #[test]
+    fn replace_keeps_drawable_used_by_another_package() {
+        let mut icon_map = fixture();
+        icon_map.insert(
+            String::from("@drawable/themed_icon_maps"),
+            String::from("org.example.navigation"),
+        );
+        let unused = icon_map.replace(
+            String::from("@drawable/themed_icon_atlas"),
+            String::from("org.example.maps"),
+        );
+
+        assert_eq!(unused, None);
+        assert_eq!(
+            icon_map.drawable_for("org.example.navigation"),
+            Some("@drawable/themed_icon_maps")
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use std::{collections::HashMap, fs, path::PathBuf, sync::Mutex};
+
+use teloxide::types::{ChatId, User};
+
+use crate::{config::Config, messages::Language};
+
+/// The language the bot speaks in each chat. Languages picked with
+/// `/language` are kept in a file, one `chat_id code` per line, so they
+/// survive restarts. Other chats get the language of the client of whoever
+/// wrote there last, if there are messages in it.
+pub struct ChatLanguages {
+    path: PathBuf,
+    picked: Mutex<HashMap<ChatId, Language>>,
+    detected: Mutex<HashMap<ChatId, Language>>,
+}
+
+impl ChatLanguages {
+    pub fn load(config: &Config) -> Self {
+        let picked = match fs::read_to_string(&config.languages_path) {
+            Ok(languages) => languages.lines().filter_map(parse_line).collect(),
+            Err(e) => {
+                log::info!(
+                    "No chat languages loaded from {}: {e}",
+                    config.languages_path
+                );
+
+                HashMap::new()
+            }
+        };
+
+        Self {
+            path: PathBuf::from(&config.languages_path),
+            picked: Mutex::new(picked),
+            detected: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The language to answer `user` in `chat_id` in. Their client's
+    /// language is remembered for messages sent to the chat later, e.g. once
+    /// a review is done.
+    pub fn for_user(&self, chat_id: ChatId, user: Option<&User>) -> Language {
+        if let Some(language) = self.picked.lock().unwrap().get(&chat_id) {
+            return *language;
+        }
+
+        match user.and_then(|user| user.language_code.as_deref()) {
+            Some(code) => {
+                let language = Language::from_code(code).unwrap_or_default();
+                self.detected.lock().unwrap().insert(chat_id, language);
+
+                language
+            }
+            None => self.for_chat(chat_id),
+        }
+    }
+
+    /// The language of messages sent to `chat_id` outside of a conversation.
+    pub fn for_chat(&self, chat_id: ChatId) -> Language {
+        if let Some(language) = self.picked.lock().unwrap().get(&chat_id) {
+            return *language;
+        }
+
+        self.detected
+            .lock()
+            .unwrap()
+            .get(&chat_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Speaks `language` in `chat_id` from now on, whatever its users'
+    /// clients are set to.
+    pub fn pick(&self, chat_id: ChatId, language: Language) {
+        let mut picked = self.picked.lock().unwrap();
+        picked.insert(chat_id, language);
+
+        let lines = picked
+            .iter()
+            .map(|(chat_id, language)| format!("{} {}\n", chat_id.0, language.code()))
+            .collect::<String>();
+
+        if let Err(e) = fs::write(&self.path, lines) {
+            log::warn!(
+                "Failed to save the chat languages to {}: {e}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Option<(ChatId, Language)> {
+    let (chat_id, code) = line.trim().split_once(' ')?;
+
+    Some((ChatId(chat_id.parse().ok()?), Language::from_code(code)?))
+}
@@ -0,0 +1,116 @@
+use std::{collections::HashMap, fs, path::PathBuf, sync::Mutex};
+
+use time::OffsetDateTime;
+
+use crate::config::Config;
+
+/// Length of the window submissions are counted in.
+const WINDOW_SECS: i64 = 24 * 60 * 60;
+
+/// Caps how many icons a user can submit per day. Submissions are counted
+/// once they were completed, so failed or rejected ones don't use up the
+/// limit, and kept in a file so a restart doesn't reset the count.
+pub struct SubmissionLimits {
+    path: PathBuf,
+    max_per_day: usize,
+    exempt_ids: Vec<i64>,
+    /// Unix timestamps of the submissions within the window, per user id.
+    submissions: Mutex<HashMap<i64, Vec<i64>>>,
+}
+
+impl SubmissionLimits {
+    /// Loads the submissions recorded at `SUBMISSION_LOG_PATH`, one
+    /// `user_id timestamp` pair per line.
+    pub fn load(config: &Config) -> Self {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let mut submissions: HashMap<i64, Vec<i64>> = HashMap::new();
+
+        match fs::read_to_string(&config.submission_log_path) {
+            Ok(log) => {
+                for (user_id, timestamp) in log.lines().filter_map(parse_line) {
+                    if now - timestamp < WINDOW_SECS {
+                        submissions.entry(user_id).or_default().push(timestamp);
+                    }
+                }
+            }
+            Err(e) => log::info!(
+                "No submissions loaded from {}: {e}",
+                config.submission_log_path
+            ),
+        }
+
+        Self {
+            path: PathBuf::from(&config.submission_log_path),
+            max_per_day: config.max_submissions_per_day,
+            exempt_ids: config.rate_limit_exempt_ids.clone(),
+            submissions: Mutex::new(submissions),
+        }
+    }
+
+    pub fn max_per_day(&self) -> usize {
+        self.max_per_day
+    }
+
+    /// Returns when `user_id` can submit again, or `None` if they can right
+    /// now.
+    pub fn blocked_until(&self, user_id: i64) -> Option<OffsetDateTime> {
+        if self.exempt_ids.contains(&user_id) {
+            return None;
+        }
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let submissions = self.submissions.lock().unwrap();
+        let mut recent = submissions
+            .get(&user_id)
+            .into_iter()
+            .flatten()
+            .filter(|timestamp| now - **timestamp < WINDOW_SECS)
+            .collect::<Vec<_>>();
+
+        if recent.len() < self.max_per_day {
+            return None;
+        }
+
+        // Once enough of the oldest submissions left the window, there is
+        // room for one more.
+        recent.sort_unstable();
+        let oldest = recent[recent.len() - self.max_per_day];
+
+        OffsetDateTime::from_unix_timestamp(oldest + WINDOW_SECS).ok()
+    }
+
+    /// Counts a completed submission of `user_id`.
+    pub fn record(&self, user_id: i64) {
+        if self.exempt_ids.contains(&user_id) {
+            return;
+        }
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let mut submissions = self.submissions.lock().unwrap();
+        submissions.entry(user_id).or_default().push(now);
+
+        for timestamps in submissions.values_mut() {
+            timestamps.retain(|timestamp| now - timestamp < WINDOW_SECS);
+        }
+        submissions.retain(|_, timestamps| !timestamps.is_empty());
+
+        let log = submissions
+            .iter()
+            .flat_map(|(user_id, timestamps)| {
+                timestamps
+                    .iter()
+                    .map(move |timestamp| format!("{user_id} {timestamp}\n"))
+            })
+            .collect::<String>();
+
+        if let Err(e) = fs::write(&self.path, log) {
+            log::warn!("Failed to save submissions to {}: {e}", self.path.display());
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Option<(i64, i64)> {
+    let (user_id, timestamp) = line.split_once(' ')?;
+
+    Some((user_id.parse().ok()?, timestamp.trim().parse().ok()?))
+}
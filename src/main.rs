@@ -1,69 +1,214 @@
-use git2::{Cred, IndexAddOption, PushOptions, RemoteCallbacks, Repository};
-use image::load_from_memory;
+use reqwest::multipart::{Form, Part};
 use serde::{Deserialize, Serialize};
-use svg_trace::{convert_image_to_svg, Config, Preset};
 use teloxide::{
     dispatching::{
         dialogue::{self, GetChatId, InMemStorage},
-        UpdateFilterExt,
+        ShutdownToken, UpdateFilterExt,
     },
+    dptree::di::DependencyMap,
     net::Download,
     payloads::SendMessageSetters,
     prelude::*,
-    types::{InlineKeyboardButton, InlineKeyboardMarkup, InputFile, ParseMode},
+    types::{
+        ChatId, Document, InlineKeyboardButton, InlineKeyboardMarkup, InlineQueryResult,
+        InlineQueryResultArticle, InputFile, InputMessageContent, InputMessageContentText,
+        ParseMode, Sticker,
+    },
     utils::command::BotCommands,
 };
 use time::OffsetDateTime;
-use tokio::{io::AsyncWriteExt, process::Command as TokioCommand};
-
-use std::{
-    env,
-    error::Error,
-    fs,
-    path::{Path, PathBuf},
-    process::Stdio,
+
+use std::{error::Error, fmt, ops::ControlFlow, path::Path, sync::Arc, time::Duration};
+
+use access::AccessList;
+use backend::SubmissionBackend;
+use batch::{
+    app_path_conflict, app_paths, icon_name_conflict, icon_names, BatchedIcon, MAX_BATCH_SIZE,
+};
+use config::Config;
+use cooldown::LatestCooldown;
+use deadline::{Deadline, DeadlineExceeded, PausedDeadline, Stage};
+use error::{gitlab_error_for_status, BotError};
+use icon_map::IconMap;
+use languages::ChatLanguages;
+use limits::SubmissionLimits;
+use messages::{Button, Language, Text};
+use metrics::Metrics;
+use moderation::Moderator;
+use notify::{post_audit_entry, post_rejection, username, AuditEntry, MaintainerNotifier};
+use overlay::{IconCommit, IconRemoval, PushFailed};
+use pipeline::{download_complete, remove_png_background, svg_to_vd, trace_png, TraceOptions};
+use preprocess::{
+    check_svg, check_vector_drawable, EmptyImage, ExtremeAspectRatio, ImageTooSmall,
+    InvalidVectorDrawable, NoTransparency,
 };
+use preview::render_png;
+use ratelimit::RateLimiter;
+use releases::{
+    download_button, format_release, format_release_time, format_releases, matches_query,
+    releases_keyboard, ReleaseCache,
+};
+use retry::{with_retry, DEFAULT_ATTEMPTS, DEFAULT_BASE_DELAY};
+use review::{PendingReviews, Review};
+use shutdown::{InFlight, Work};
+use store::{AppStores, StoreCheck};
+use tools::{CommandFailed, Tools};
+use tracking::{TrackedMergeRequest, TrackedMergeRequests};
+
+mod access;
+mod backend;
+mod batch;
+mod config;
+mod cooldown;
+mod deadline;
+mod error;
+mod icon_list;
+mod icon_map;
+mod languages;
+mod limits;
+mod messages;
+mod metrics;
+mod moderation;
+mod notify;
+mod overlay;
+mod pipeline;
+mod preprocess;
+mod preview;
+mod ratelimit;
+mod releases;
+mod retry;
+mod review;
+mod shutdown;
+mod store;
+mod tools;
+mod tracking;
 
 // const DCOS_SUPPORT_ID: i64 = 1638468462;
 // const DCOS_RELEASES_ID: i64 = 1791772972;
 
-const OTA_DCOS: &str = "https://raw.githubusercontent.com/DavinciCodeOS/ota-data/main/davinci.json";
-const OTA_DCOS_PRE: &str =
-    "https://raw.githubusercontent.com/DavinciCodeOS/ota-data/main/davinci_pre.json";
-const OTA_DCOSX: &str =
-    "https://raw.githubusercontent.com/DavinciCodeOS/ota-data/main/davincix.json";
-const OTA_DCOSX_PRE: &str =
-    "https://raw.githubusercontent.com/DavinciCodeOS/ota-data/main/davincix_pre.json";
-
-const OVERLAY_GITLAB_PROJECT_ID: u64 = 35606329;
+const PREVIEW_SIZE: u32 = 512;
+const GIT_LOCK_NOTICE_DELAY: Duration = Duration::from_secs(2);
+/// How long `/stats` waits for each of its sources.
+const STATS_TIMEOUT: Duration = Duration::from_secs(10);
+/// Most items GitLab returns per page.
+const GITLAB_PAGE_SIZE: usize = 100;
+/// Precedes the package in the description of icon merge requests.
+const PACKAGE_TRAILER: &str = "Package: ";
+const REVIEW_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const TELEGRAM_PROBE_INTERVAL: Duration = Duration::from_secs(30);
 
 type LeonardoBot = AutoSend<Bot>;
 type AppIconDialogue = Dialogue<State, InMemStorage<State>>;
+/// Guards fetches into the overlay cache, which all submissions share.
+type GitLock = tokio::sync::Mutex<()>;
 
 #[derive(Clone)]
 pub enum State {
     Start,
-    ReceiveAppPath,
+    ReceiveTargetBranch,
+    ReceiveAppPath {
+        target_branches: Vec<String>,
+        batch: Vec<BatchedIcon>,
+    },
     ConfirmingAppPath {
         app_path: String,
+        target_branches: Vec<String>,
+        batch: Vec<BatchedIcon>,
+    },
+    ConfirmingUpdate {
+        app_path: String,
+        target_branches: Vec<String>,
+        batch: Vec<BatchedIcon>,
     },
     ReceiveIconFile {
         app_path: String,
+        /// Kept when the user sends a different image for an icon they
+        /// already named.
+        icon_name: Option<String>,
+        target_branches: Vec<String>,
+        batch: Vec<BatchedIcon>,
     },
     ReceiveIconName {
         app_path: String,
         file_id: String,
+        is_svg: bool,
+        target_branches: Vec<String>,
+        batch: Vec<BatchedIcon>,
+    },
+    ConfirmingIconName {
+        app_path: String,
+        file_id: String,
+        is_svg: bool,
+        icon_name: String,
+        target_branches: Vec<String>,
+        batch: Vec<BatchedIcon>,
     },
     ReceiveDescription {
         app_path: String,
         file_id: String,
+        is_svg: bool,
+        icon_name: String,
+        target_branches: Vec<String>,
+        batch: Vec<BatchedIcon>,
+    },
+    ConfirmingBackgroundRemoval {
+        app_path: String,
         icon_name: String,
+        description: String,
+        png_bytes: Vec<u8>,
+        background: [u8; 4],
+        target_branches: Vec<String>,
+        batch: Vec<BatchedIcon>,
+        /// The conversion continues once the user answered.
+        deadline: PausedDeadline,
     },
     ConfirmingCreation {
         vd_bytes: Vec<u8>,
+        svg: String,
+        app_path: String,
+        icon_name: String,
+        description: String,
+        png_bytes: Option<Vec<u8>>,
+        trace_options: TraceOptions,
+        target_branches: Vec<String>,
+        batch: Vec<BatchedIcon>,
+    },
+    /// The user wants to rename the converted icon, `icon_name` is its old
+    /// name.
+    ReceiveNewIconName {
+        vd_bytes: Vec<u8>,
+        svg: String,
         app_path: String,
         icon_name: String,
         description: String,
+        png_bytes: Option<Vec<u8>>,
+        trace_options: TraceOptions,
+        target_branches: Vec<String>,
+        batch: Vec<BatchedIcon>,
+    },
+    /// Every icon of the request is converted, the user decides whether to
+    /// add another one.
+    ConfirmingBatch {
+        icons: Vec<BatchedIcon>,
+        target_branches: Vec<String>,
+    },
+    ConfirmingMergeRequestUpdate {
+        icons: Vec<BatchedIcon>,
+        target_branches: Vec<String>,
+        /// The submission continues once the user answered.
+        deadline: PausedDeadline,
+    },
+    RetryingCreation {
+        icons: Vec<BatchedIcon>,
+        target_branches: Vec<String>,
+    },
+    /// The submission was sent to the reviewers chat.
+    AwaitingReview,
+    ReceiveRemovalTarget,
+    ConfirmingRemoval {
+        app_path: String,
+        icon_name: String,
+        target_branches: Vec<String>,
     },
 }
 
@@ -73,41 +218,177 @@ impl Default for State {
     }
 }
 
+impl State {
+    /// The app path of the icon being submitted, the target branches and the
+    /// icons added to the request before it, once the user is past choosing
+    /// the app. `None` before that.
+    fn submission(self) -> Option<(String, Vec<String>, Vec<BatchedIcon>)> {
+        match self {
+            Self::ConfirmingUpdate {
+                app_path,
+                target_branches,
+                batch,
+            }
+            | Self::ReceiveIconFile {
+                app_path,
+                target_branches,
+                batch,
+                ..
+            }
+            | Self::ReceiveIconName {
+                app_path,
+                target_branches,
+                batch,
+                ..
+            }
+            | Self::ConfirmingIconName {
+                app_path,
+                target_branches,
+                batch,
+                ..
+            }
+            | Self::ReceiveDescription {
+                app_path,
+                target_branches,
+                batch,
+                ..
+            }
+            | Self::ConfirmingBackgroundRemoval {
+                app_path,
+                target_branches,
+                batch,
+                ..
+            }
+            | Self::ConfirmingCreation {
+                app_path,
+                target_branches,
+                batch,
+                ..
+            }
+            | Self::ReceiveNewIconName {
+                app_path,
+                target_branches,
+                batch,
+                ..
+            } => Some((app_path, target_branches, batch)),
+            // The last icon is the one that was added most recently.
+            Self::ConfirmingBatch {
+                mut icons,
+                target_branches,
+            }
+            | Self::ConfirmingMergeRequestUpdate {
+                mut icons,
+                target_branches,
+                ..
+            }
+            | Self::RetryingCreation {
+                mut icons,
+                target_branches,
+            } => {
+                let icon = icons.pop()?;
+
+                Some((icon.app_path, target_branches, icons))
+            }
+            Self::Start
+            | Self::AwaitingReview
+            | Self::ReceiveRemovalTarget
+            | Self::ConfirmingRemoval { .. }
+            | Self::ReceiveTargetBranch
+            | Self::ReceiveAppPath { .. }
+            | Self::ConfirmingAppPath { .. } => None,
+        }
+    }
+
+    /// The name of the icon being submitted, once the user provided one.
+    fn icon_name(&self) -> Option<&str> {
+        match self {
+            Self::ConfirmingIconName { icon_name, .. }
+            | Self::ReceiveDescription { icon_name, .. }
+            | Self::ConfirmingBackgroundRemoval { icon_name, .. }
+            | Self::ConfirmingCreation { icon_name, .. }
+            | Self::ReceiveNewIconName { icon_name, .. } => Some(icon_name),
+            Self::ReceiveIconFile { icon_name, .. } => icon_name.as_deref(),
+            Self::ConfirmingBatch { icons, .. }
+            | Self::ConfirmingMergeRequestUpdate { icons, .. }
+            | Self::RetryingCreation { icons, .. } => {
+                icons.last().map(|icon| icon.icon_name.as_str())
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(BotCommands, Clone)]
 #[command(rename = "lowercase", description = "These commands are supported:")]
 enum Command {
     #[command(description = "display this text.")]
     Help,
-    #[command(description = "get the latest DCOS/DCOSX releases.")]
+    #[command(description = "get the latest releases.")]
     Latest,
+    #[command(description = "show the bot version, uptime and enabled features.")]
+    About,
     #[command(description = "submit an icon for the pixel launcher overlay.")]
     AddIcon,
+    #[command(description = "remove the icon of an app from the overlay.")]
+    DeleteIcon,
+    #[command(
+        description = "list the themed apps, optionally only those containing the given text."
+    )]
+    ListIcons(String),
+    /// Takes the package of the app to look up.
+    #[command(description = "check whether an app, e.g. com.discord, already has an icon.")]
+    IconExists(String),
+    #[command(description = "show overlay and submission statistics.")]
+    Stats,
+    #[command(description = "show or set the language of this chat, e.g. /language de.")]
+    Language(String),
+    /// Sent by Telegram when a chat is opened, possibly from a `t.me` link
+    /// with a payload.
+    #[command(description = "off")]
+    Start(String),
+    #[command(description = "off")]
+    Ban(String),
+    #[command(description = "off")]
+    Unban(String),
+    #[command(description = "off")]
+    BanList,
 }
 
-#[derive(Deserialize, Debug)]
-struct OtaData {
-    datetime: i64,
-    url: String,
-}
-
-#[derive(Debug)]
-struct AllReleases {
-    dcos: Option<OtaData>,
-    dcos_pre: Option<OtaData>,
-    dcosx: Option<OtaData>,
-    dcosx_pre: Option<OtaData>,
-}
+impl Command {
+    /// Names of all commands, as counted by [`Metrics`].
+    const NAMES: [&'static str; 13] = [
+        "help",
+        "latest",
+        "about",
+        "addicon",
+        "deleteicon",
+        "listicons",
+        "iconexists",
+        "stats",
+        "language",
+        "start",
+        "ban",
+        "unban",
+        "banlist",
+    ];
 
-#[derive(Deserialize, Serialize, Debug)]
-struct Icon {
-    drawable: String,
-    package: String,
-}
-
-#[derive(Deserialize, Serialize, Debug)]
-struct Icons {
-    #[serde(rename = "icon")]
-    icons: Vec<Icon>,
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Help => "help",
+            Self::Latest => "latest",
+            Self::About => "about",
+            Self::AddIcon => "addicon",
+            Self::DeleteIcon => "deleteicon",
+            Self::ListIcons(_) => "listicons",
+            Self::IconExists(_) => "iconexists",
+            Self::Stats => "stats",
+            Self::Language(_) => "language",
+            Self::Start(_) => "start",
+            Self::Ban(_) => "ban",
+            Self::Unban(_) => "unban",
+            Self::BanList => "banlist",
+        }
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -118,6 +399,59 @@ struct MergeRequestParams {
     remove_source_branch: bool,
     title: String,
     description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    labels: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assignee_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reviewer_ids: Option<Vec<u64>>,
+}
+
+#[derive(Serialize, Debug)]
+struct MergeRequestUpdateParams {
+    description: String,
+}
+
+/// A file uploaded to the project.
+#[derive(Deserialize, Debug)]
+struct Upload {
+    /// Embeds the file in Markdown on GitLab.
+    markdown: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct MergeRequest {
+    iid: u64,
+    web_url: String,
+    source_branch: String,
+    description: Option<String>,
+    /// `opened`, `closed`, `locked` or `merged`.
+    #[serde(default)]
+    state: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct MergeRequestNote {
+    body: String,
+    /// Set for notes GitLab adds itself, e.g. when the branch is pushed to.
+    system: bool,
+}
+
+/// What the overlay and GitLab already have for a package.
+struct IconStatus {
+    /// The drawable the package is mapped to on each of the branches that
+    /// were looked at, without the `@drawable/` prefix.
+    drawables: Vec<Option<String>>,
+    /// Open merge requests of the bot that submit an icon for the package.
+    pending: Vec<MergeRequest>,
+}
+
+/// What already exists on GitLab for the source branch of a submission.
+enum RemoteBranch {
+    Missing,
+    /// The branch is left over from a merge request that was closed.
+    Stale,
+    Open(MergeRequest),
 }
 
 #[tokio::main]
@@ -127,180 +461,834 @@ async fn main() {
 
     log::info!("Starting Leonardo");
 
+    let started_at = OffsetDateTime::now_utc();
+
+    let config = match Config::from_env() {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            log::error!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    let tools = Tools::detect(&config.svg2vd_bin).await;
     let client = reqwest::Client::new();
-    let bot = Bot::from_env_with_client(client.clone()).auto_send();
+    let bot = Bot::with_client(config.bot_token.clone(), client.clone()).auto_send();
+    let storage = InMemStorage::<State>::new();
+    let reviews = Arc::new(PendingReviews::new(config.review_timeout));
+    let metrics = Arc::new(Metrics::new(&Command::NAMES));
+    let limiter = Arc::new(RateLimiter::from_config(&config, metrics.clone()));
+    let in_flight = Arc::new(InFlight::default());
+    let tracked = Arc::new(TrackedMergeRequests::load(&config));
+    let languages = Arc::new(ChatLanguages::load(&config));
+    let shutdown_grace = config.shutdown_grace;
+
+    tokio::spawn(probe_telegram(bot.clone(), metrics.clone()));
+    tokio::spawn(watch_merge_requests(
+        bot.clone(),
+        config.clone(),
+        tracked.clone(),
+        languages.clone(),
+    ));
+
+    if let Some(port) = config.metrics_port {
+        tokio::spawn(metrics::serve(port, metrics.clone(), config.health_max_age));
+    }
+
+    if let Some(review_chat_id) = config.review_chat_id {
+        tokio::spawn(expire_reviews(
+            bot.clone(),
+            review_chat_id,
+            storage.clone(),
+            reviews.clone(),
+            languages.clone(),
+        ));
+    }
 
-    Dispatcher::builder(
+    let mut dispatcher = Dispatcher::builder(
         bot,
-        dialogue::enter::<Update, InMemStorage<State>, State, _>()
+        dptree::entry()
+            .branch(Update::filter_inline_query().endpoint(answer_inline_query))
             .branch(
-                Update::filter_message()
-                    .branch(teloxide::handler![State::ReceiveAppPath].endpoint(receive_app_path))
-                    .branch(
-                        teloxide::handler![State::ReceiveIconFile { app_path }]
-                            .endpoint(receive_icon_file),
-                    )
-                    .branch(
-                        teloxide::handler![State::ReceiveIconName { app_path, file_id }]
-                            .endpoint(receive_icon_name),
-                    )
-                    .branch(
-                        teloxide::handler![State::ReceiveDescription {
-                            app_path,
-                            file_id,
-                            icon_name
-                        }]
-                        .endpoint(receive_description),
+                dialogue::enter::<Update, InMemStorage<State>, State, _>()
+                    .map(
+                        |update: Update,
+                         dialogue: AppIconDialogue,
+                         languages: Arc<ChatLanguages>| {
+                            languages.for_user(dialogue.chat_id(), update.user())
+                        },
                     )
-                    .branch(dptree::entry().filter_command::<Command>().endpoint(answer)),
-            )
-            .branch(
-                Update::filter_callback_query()
+                    .chain(dptree::from_fn(|deps: DependencyMap, cont| async move {
+                        match cont(deps.clone()).await {
+                            ControlFlow::Break(Err(error)) => {
+                                // Handlers get the notifier as an `Arc`, that
+                                // is how it was registered.
+                                let notifier = deps.get::<Arc<MaintainerNotifier>>();
+
+                                ControlFlow::Break(
+                                    report_error(
+                                        &deps.get::<LeonardoBot>(),
+                                        &deps.get::<AppIconDialogue>(),
+                                        &**notifier,
+                                        *deps.get::<Language>(),
+                                        error,
+                                    )
+                                    .await,
+                                )
+                            }
+                            flow => flow,
+                        }
+                    }))
                     .branch(
-                        teloxide::handler![State::ConfirmingAppPath { app_path }]
-                            .endpoint(receive_app_path_confirmation),
+                        Update::filter_message()
+                            .branch(
+                                teloxide::handler![State::ReceiveAppPath {
+                                    target_branches,
+                                    batch
+                                }]
+                                .endpoint(receive_app_path),
+                            )
+                            .branch(
+                                teloxide::handler![State::ReceiveIconFile {
+                                    app_path,
+                                    icon_name,
+                                    target_branches,
+                                    batch
+                                }]
+                                .endpoint(receive_icon_file),
+                            )
+                            .branch(
+                                teloxide::handler![State::ReceiveIconName {
+                                    app_path,
+                                    file_id,
+                                    is_svg,
+                                    target_branches,
+                                    batch
+                                }]
+                                .endpoint(receive_icon_name),
+                            )
+                            .branch(
+                                teloxide::handler![State::ReceiveDescription {
+                                    app_path,
+                                    file_id,
+                                    is_svg,
+                                    icon_name,
+                                    target_branches,
+                                    batch
+                                }]
+                                .endpoint(receive_description),
+                            )
+                            .branch(
+                                teloxide::handler![State::ReceiveNewIconName {
+                                    vd_bytes,
+                                    svg,
+                                    icon_name,
+                                    app_path,
+                                    description,
+                                    png_bytes,
+                                    trace_options,
+                                    target_branches,
+                                    batch
+                                }]
+                                .endpoint(receive_new_icon_name),
+                            )
+                            .branch(
+                                teloxide::handler![State::ReceiveRemovalTarget]
+                                    .endpoint(receive_removal_target),
+                            )
+                            .branch(dptree::entry().filter_command::<Command>().endpoint(answer)),
                     )
                     .branch(
-                        teloxide::handler![State::ConfirmingCreation {
-                            vd_bytes,
-                            icon_name,
-                            app_path,
-                            description
-                        }]
-                        .endpoint(receive_creation_confirmation),
+                        Update::filter_callback_query()
+                            .branch(
+                                dptree::filter(|q: CallbackQuery| {
+                                    q.data
+                                        .as_deref()
+                                        .and_then(icon_list::parse_callback_data)
+                                        .is_some()
+                                })
+                                .endpoint(receive_icon_list_page),
+                            )
+                            .branch(
+                                dptree::filter(|q: CallbackQuery| {
+                                    q.data
+                                        .as_deref()
+                                        .and_then(review::parse_callback_data)
+                                        .is_some()
+                                })
+                                .endpoint(receive_review),
+                            )
+                            .branch(
+                                teloxide::handler![State::ReceiveTargetBranch]
+                                    .endpoint(receive_target_branch),
+                            )
+                            .branch(
+                                teloxide::handler![State::ConfirmingAppPath {
+                                    app_path,
+                                    target_branches,
+                                    batch
+                                }]
+                                .endpoint(receive_app_path_confirmation),
+                            )
+                            .branch(
+                                teloxide::handler![State::ConfirmingUpdate {
+                                    app_path,
+                                    target_branches,
+                                    batch
+                                }]
+                                .endpoint(receive_update_confirmation),
+                            )
+                            .branch(
+                                teloxide::handler![State::ConfirmingIconName {
+                                    app_path,
+                                    file_id,
+                                    is_svg,
+                                    icon_name,
+                                    target_branches,
+                                    batch
+                                }]
+                                .endpoint(receive_icon_name_confirmation),
+                            )
+                            .branch(
+                                teloxide::handler![State::ConfirmingBackgroundRemoval {
+                                    app_path,
+                                    icon_name,
+                                    description,
+                                    png_bytes,
+                                    background,
+                                    target_branches,
+                                    batch,
+                                    deadline
+                                }]
+                                .endpoint(receive_background_removal_confirmation),
+                            )
+                            .branch(
+                                teloxide::handler![State::ConfirmingCreation {
+                                    vd_bytes,
+                                    svg,
+                                    icon_name,
+                                    app_path,
+                                    description,
+                                    png_bytes,
+                                    trace_options,
+                                    target_branches,
+                                    batch
+                                }]
+                                .endpoint(receive_creation_confirmation),
+                            )
+                            .branch(
+                                teloxide::handler![State::ConfirmingBatch {
+                                    icons,
+                                    target_branches
+                                }]
+                                .endpoint(receive_batch_confirmation),
+                            )
+                            .branch(
+                                teloxide::handler![State::RetryingCreation {
+                                    icons,
+                                    target_branches
+                                }]
+                                .endpoint(receive_retry_confirmation),
+                            )
+                            .branch(
+                                teloxide::handler![State::ConfirmingMergeRequestUpdate {
+                                    icons,
+                                    target_branches,
+                                    deadline
+                                }]
+                                .endpoint(receive_merge_request_update_confirmation),
+                            )
+                            .branch(
+                                teloxide::handler![State::ConfirmingRemoval {
+                                    app_path,
+                                    icon_name,
+                                    target_branches
+                                }]
+                                .endpoint(receive_removal_confirmation),
+                            )
+                            .branch(dptree::endpoint(receive_stale_callback)),
                     ),
             ),
     )
-    .dependencies(dptree::deps![InMemStorage::<State>::new()])
-    .build()
-    .dispatch()
-    .await;
+    .dependencies(dptree::deps![
+        storage,
+        reviews,
+        Arc::new(MaintainerNotifier::new(config.maintainer_chat_id)),
+        Arc::new(SubmissionLimits::load(&config)),
+        Arc::new(AccessList::load(&config)),
+        Arc::new(LatestCooldown::new(config.latest_cooldown)),
+        config,
+        limiter,
+        Arc::new(ReleaseCache::default()),
+        metrics,
+        Arc::new(AppStores::default()),
+        Arc::new(Moderator::new(&config, client)),
+        Arc::new(tools),
+        Arc::new(GitLock::new(())),
+        in_flight.clone(),
+        tracked,
+        languages,
+        started_at
+    ])
+    .build();
+
+    tokio::spawn(shutdown_on_signal(
+        dispatcher.shutdown_token(),
+        in_flight,
+        shutdown_grace,
+    ));
+
+    dispatcher.dispatch().await;
+
+    log::info!("Stopped");
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn answer(
     bot: LeonardoBot,
     message: Message,
     command: Command,
     dialogue: AppIconDialogue,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
+    config: Arc<Config>,
+    limiter: Arc<RateLimiter>,
+    releases: Arc<ReleaseCache>,
+    cooldown: Arc<LatestCooldown>,
+    tracked: Arc<TrackedMergeRequests>,
+    limits: Arc<SubmissionLimits>,
+    access: Arc<AccessList>,
+    moderator: Arc<Moderator>,
+    tools: Arc<Tools>,
+    git_lock: Arc<GitLock>,
+    metrics: Arc<Metrics>,
+    languages: Arc<ChatLanguages>,
+    lang: Language,
+    started_at: OffsetDateTime,
+) -> Result<(), BotError> {
+    metrics.command_handled(command.name());
+
     match command {
         Command::Help => {
             bot.send_message(message.chat.id, Command::descriptions().to_string())
                 .await?;
         }
+        Command::About => {
+            bot.send_message(
+                message.chat.id,
+                about(lang, &config, &moderator, &tools, started_at)?,
+            )
+            .await?;
+        }
         Command::Latest => {
-            let releases = get_latest_releases(bot.inner().client()).await?;
+            let group = message.chat.is_group() || message.chat.is_supergroup();
 
-            let mut text = String::new();
+            match group.then(|| cooldown.recent(message.chat.id)).flatten() {
+                // Point to the releases that were just posted instead of
+                // burying the conversation under another copy.
+                Some(previous) => {
+                    bot.send_message(message.chat.id, lang.text(Text::LatestRightHere))
+                        .reply_to_message_id(previous)
+                        .await?;
+                }
+                None => {
+                    let releases = releases
+                        .get(
+                            bot.inner().client(),
+                            &limiter,
+                            &metrics,
+                            &config.release_channels,
+                        )
+                        .await
+                        .map_err(BotError::Ota)?;
 
-            for (name, data) in [
-                ("DCOS \\(stable\\)", releases.dcos),
-                ("DCOS \\(pre\\-release\\)", releases.dcos_pre),
-                ("DCOSX \\(stable\\)", releases.dcosx),
-                ("DCOSX \\(pre\\-release\\)", releases.dcosx_pre),
-            ]
-            .into_iter()
-            {
-                if let Some(release) = data {
-                    let dt = OffsetDateTime::from_unix_timestamp(release.datetime)?;
-                    let format = time::format_description::parse(
-                        "[year]\\-[month]\\-[day] [hour]:[minute]:[second]",
-                    )?;
-                    let timestamp = dt.format(&format)?;
-                    let desc = format!(
-                        "{}: [download]({}) \\(Updated {}\\)\n",
-                        name, release.url, timestamp
-                    );
-                    text.push_str(&desc);
-                } else {
-                    text.push_str(name);
-                    text.push_str(": no release available\n");
+                    let request = bot
+                        .send_message(message.chat.id, format_releases(lang, &releases)?)
+                        .parse_mode(ParseMode::MarkdownV2);
+                    let sent = match releases_keyboard(&releases) {
+                        Some(keyboard) => request.reply_markup(keyboard).await?,
+                        None => request.await?,
+                    };
+
+                    if group {
+                        cooldown.answered(message.chat.id, sent.id);
+                    }
+                }
+            }
+        }
+        Command::AddIcon => match message.from() {
+            Some(user) if message.chat.is_private() => {
+                start_submission(
+                    &bot,
+                    &dialogue,
+                    &config,
+                    &limits,
+                    &access,
+                    &tools,
+                    &metrics,
+                    lang,
+                    message.chat.id,
+                    user.id,
+                )
+                .await?;
+            }
+            _ => send_private_chat_link(&bot, lang, message.chat.id).await?,
+        },
+        Command::DeleteIcon => {
+            if !message.chat.is_private() {
+                bot.send_message(message.chat.id, lang.text(Text::DeleteIconPrivateOnly))
+                    .await?;
+            } else if matches!(dialogue.get().await?, Some(State::AwaitingReview)) {
+                bot.send_message(message.chat.id, lang.text(Text::StillAwaitingReview))
+                    .await?;
+            } else if let Some(refusal) = access.refusal(message.chat.id.0) {
+                bot.send_message(message.chat.id, lang.text(refusal))
+                    .await?;
+            } else {
+                bot.send_message(message.chat.id, lang.text(Text::AskRemovalTarget))
+                    .await?;
+
+                dialogue.update(State::ReceiveRemovalTarget).await?;
+            }
+        }
+        Command::IconExists(app_path) => {
+            let app_path = match parse_app_path(&app_path) {
+                Some(app_path) => app_path,
+                None => {
+                    bot.send_message(message.chat.id, lang.text(Text::IconExistsUsage))
+                        .await?;
+
+                    return Ok(());
                 }
+            };
+
+            let branches = config
+                .overlay_branches
+                .iter()
+                .map(|branch| branch.name.clone())
+                .collect::<Vec<_>>();
+
+            let guard = git_lock.lock().await;
+            let refreshed = config.submission_backend.refresh(&config, &branches).await;
+            drop(guard);
+            refreshed?;
+
+            let status = icon_status(&bot, &config, &branches, &app_path).await?;
+            let mut lines = branches
+                .iter()
+                .zip(&status.drawables)
+                .filter_map(|(branch, drawable)| {
+                    drawable.as_ref().map(|drawable| {
+                        lang.text(Text::ThemedWith {
+                            app_path: &app_path,
+                            drawable,
+                            branch,
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            if lines.is_empty() {
+                lines.push(lang.text(Text::NotThemed {
+                    app_path: &app_path,
+                }));
+            }
+            if let Some(pending) = pending_note(lang, &status.pending) {
+                lines.push(pending);
+            }
+
+            bot.send_message(message.chat.id, lines.join("\n")).await?;
+        }
+        Command::ListIcons(filter) => {
+            let filter = filter.trim();
+
+            if filter.len() > icon_list::MAX_FILTER_LEN {
+                bot.send_message(message.chat.id, lang.text(Text::SearchTooLong))
+                    .await?;
+
+                return Ok(());
             }
 
-            bot.send_message(message.chat.id, text)
-                .parse_mode(ParseMode::MarkdownV2)
+            let branch = config.default_overlay_branch();
+            let backend = config.submission_backend;
+
+            // Paging through the list reuses the cache, so only refresh here.
+            let guard = git_lock.lock().await;
+            let refreshed = backend.refresh(&config, &[branch.to_owned()]).await;
+            drop(guard);
+            refreshed?;
+
+            let icon_map = backend
+                .icon_map(&config, bot.inner().client(), branch)
+                .await?;
+            let (text, keyboard) = icon_list::render_page(lang, &icon_map, branch, filter, 0);
+
+            let request = bot.send_message(message.chat.id, text);
+            match keyboard {
+                Some(keyboard) => request.reply_markup(keyboard).await?,
+                None => request.await?,
+            };
+        }
+        Command::Stats => {
+            let text = stats(
+                &bot, &config, &limiter, &releases, &metrics, &tracked, &git_lock, lang,
+            )
+            .await;
+
+            bot.send_message(message.chat.id, text).await?;
+        }
+        Command::Language(code) => {
+            let codes = Language::codes();
+            let text = match code.trim() {
+                "" => lang.text(Text::LanguageCurrent {
+                    name: lang.name(),
+                    codes: &codes,
+                }),
+                code => match Language::from_code(code) {
+                    Some(language) => {
+                        languages.pick(message.chat.id, language);
+
+                        language.text(Text::LanguagePicked {
+                            name: language.name(),
+                        })
+                    }
+                    None => lang.text(Text::UnknownLanguage {
+                        code,
+                        codes: &codes,
+                    }),
+                },
+            };
+
+            bot.send_message(message.chat.id, text).await?;
+        }
+        Command::Start(payload) => match message.from() {
+            Some(user) if payload == "addicon" && message.chat.is_private() => {
+                start_submission(
+                    &bot,
+                    &dialogue,
+                    &config,
+                    &limits,
+                    &access,
+                    &tools,
+                    &metrics,
+                    lang,
+                    message.chat.id,
+                    user.id,
+                )
                 .await?;
+            }
+            _ => {
+                bot.send_message(message.chat.id, Command::descriptions().to_string())
+                    .await?;
+            }
+        },
+        Command::Ban(user_id) => change_ban(&bot, &message, &access, lang, &user_id, true).await?,
+        Command::Unban(user_id) => {
+            change_ban(&bot, &message, &access, lang, &user_id, false).await?
         }
-        Command::AddIcon => {
-            bot.send_message(message.chat.id, "Let's start! What is the app path of the app you want to add an icon for? For example com.discord or com.google.files").await?;
+        Command::BanList => {
+            if !sent_by_admin(&message, &access) {
+                bot.send_message(message.chat.id, lang.text(Text::AdminsOnly))
+                    .await?;
+
+                return Ok(());
+            }
 
-            dialogue.update(State::ReceiveAppPath).await?;
+            let banned = access.banned();
+            let text = if banned.is_empty() {
+                lang.text(Text::NobodyBanned)
+            } else {
+                lang.text(Text::BannedUsers {
+                    user_ids: &banned
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                })
+            };
+
+            bot.send_message(message.chat.id, text).await?;
         }
     };
 
     Ok(())
 }
 
-async fn receive_app_path(
-    bot: LeonardoBot,
-    msg: Message,
-    dialogue: AppIconDialogue,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    if let Some(app_path) = msg.text() {
-        if !app_path.contains('.') {
-            bot.send_message(msg.chat.id, "App path should contain at least a '.', for example: com.discord or com.google.files").await?;
+/// Describes the running build and the optional features it was started
+/// with, for bug reports.
+fn about(
+    lang: Language,
+    config: &Config,
+    moderator: &Moderator,
+    tools: &Tools,
+    started_at: OffsetDateTime,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let format = time::format_description::parse("[year]-[month]-[day] [hour]:[minute]:[second]")?;
+    let uptime = OffsetDateTime::now_utc() - started_at;
 
-            return Ok(());
-        }
+    Ok(lang.text(Text::About {
+        version: &format!("{} ({})", env!("CARGO_PKG_VERSION"), env!("GIT_HASH")),
+        started: &started_at.format(&format)?,
+        days: uptime.whole_days(),
+        hours: uptime.whole_hours() % 24,
+        minutes: uptime.whole_minutes() % 60,
+        backend: match config.submission_backend {
+            SubmissionBackend::Git => "git",
+            SubmissionBackend::GitLabApi => "GitLab API",
+        },
+        submissions_available: tools.icon_submissions_available(),
+        review: config.review_chat_id.is_some(),
+        moderation: moderator.is_enabled(),
+        dry_run: config.dry_run,
+    }))
+}
 
-        if !playstore_app_exists(bot.inner().client(), &app_path).await? {
-            let answers = InlineKeyboardMarkup::default().append_row(
-                vec!["Yes, this is correct", "No, this is wrong"]
-                    .into_iter()
-                    .map(|answer| {
-                        InlineKeyboardButton::callback(answer.to_owned(), answer.to_owned())
-                    }),
-            );
+/// Summarizes the overlay and the submissions through the bot for `/stats`.
+/// Sections whose source is unavailable or slower than [`STATS_TIMEOUT`] are
+/// left out.
+#[allow(clippy::too_many_arguments)]
+async fn stats(
+    bot: &LeonardoBot,
+    config: &Arc<Config>,
+    limiter: &RateLimiter,
+    releases: &ReleaseCache,
+    metrics: &Metrics,
+    tracked: &TrackedMergeRequests,
+    git_lock: &GitLock,
+    lang: Language,
+) -> String {
+    let branch = config.default_overlay_branch();
+    let backend = config.submission_backend;
 
-            bot.send_message(msg.chat.id, "Could not find a playstore application with this name. Are you sure it is correct?").reply_markup(answers).await?;
+    let icon_map = async {
+        let guard = git_lock.lock().await;
+        let refreshed = backend.refresh(config, &[branch.to_owned()]).await;
+        drop(guard);
+        refreshed?;
 
-            dialogue
-                .update(State::ConfirmingAppPath {
-                    app_path: app_path.to_owned(),
-                })
-                .await?;
-        } else {
-            bot.send_message(
-                msg.chat.id,
-                "Please attach a PNG with transparent background as the icon now.",
+        backend.icon_map(config, bot.inner().client(), branch).await
+    };
+    let (icon_map, merge_requests, releases) = tokio::join!(
+        tokio::time::timeout(STATS_TIMEOUT, icon_map),
+        tokio::time::timeout(STATS_TIMEOUT, open_icon_merge_requests(bot, config)),
+        tokio::time::timeout(
+            STATS_TIMEOUT,
+            releases.get(
+                bot.inner().client(),
+                limiter,
+                metrics,
+                &config.release_channels
             )
-            .await?;
+        ),
+    );
 
-            dialogue
-                .update(State::ReceiveIconFile {
-                    app_path: app_path.to_owned(),
-                })
-                .await?;
+    let mut sections = Vec::new();
+
+    if let Some(icon_map) = stats_source("the icon map", icon_map) {
+        sections.push(lang.text(Text::StatsIcons {
+            branch,
+            count: icon_map.icons.len(),
+        }));
+    }
+    sections.push(lang.text(Text::StatsSubmitted {
+        week: tracked.completed_within(7 * 24 * 60 * 60),
+        month: tracked.completed_within(30 * 24 * 60 * 60),
+    }));
+    if let Some(open) = stats_source("the open merge requests", merge_requests) {
+        sections.push(lang.text(Text::StatsOpenMergeRequests { count: open }));
+    }
+    if let Some(releases) = stats_source("the releases", releases) {
+        let lines = releases
+            .channels()
+            .filter_map(|(channel, release)| {
+                Some(format!(
+                    "{}: {}",
+                    channel.display_name,
+                    format_release_time(release?.datetime).ok()?
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        if !lines.is_empty() {
+            sections.push(lang.text(Text::StatsLatestReleases {
+                releases: &lines.join("\n"),
+            }));
         }
-    } else {
-        bot.send_message(msg.chat.id, "Please send an app path.")
+    }
+
+    sections.join("\n\n")
+}
+
+/// The value of a `/stats` source, or `None` after logging why it is
+/// unavailable.
+fn stats_source<T, E: fmt::Display>(
+    what: &str,
+    result: Result<Result<T, E>, tokio::time::error::Elapsed>,
+) -> Option<T> {
+    match result {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(e)) => {
+            log::warn!("Failed to get {what} for /stats: {e}");
+
+            None
+        }
+        Err(_) => {
+            log::warn!("Getting {what} for /stats timed out");
+
+            None
+        }
+    }
+}
+
+/// Counts the open merge requests the bot opened for icon submissions.
+async fn open_icon_merge_requests(
+    bot: &LeonardoBot,
+    config: &Config,
+) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let mut count = 0;
+    let mut page = 1;
+
+    loop {
+        let response = bot
+            .inner()
+            .client()
+            .get(format!(
+                "https://gitlab.com/api/v4/projects/{}/merge_requests",
+                config.gitlab_project_id
+            ))
+            .query(&[
+                ("state", "opened"),
+                ("per_page", &GITLAB_PAGE_SIZE.to_string()),
+                ("page", &page.to_string()),
+            ])
+            .header("PRIVATE-TOKEN", &config.gitlab_token)
+            .send()
             .await?;
+        let merge_requests = gitlab_error_for_status(response)
+            .await?
+            .json::<Vec<MergeRequest>>()
+            .await?;
+
+        count += merge_requests
+            .iter()
+            .filter(|merge_request| is_icon_branch(&merge_request.source_branch))
+            .count();
+
+        if merge_requests.len() < GITLAB_PAGE_SIZE {
+            return Ok(count);
+        }
+        page += 1;
+    }
+}
+
+/// How long Telegram may cache the answer to an inline query, the same as
+/// the release cache.
+const INLINE_CACHE_TIME_SECS: u32 = 5 * 60;
+
+/// Offers the release line of every channel matching the query, e.g.
+/// `@LeonardoBot dcosx pre`, to be sent into any chat.
+async fn answer_inline_query(
+    bot: LeonardoBot,
+    q: InlineQuery,
+    config: Arc<Config>,
+    limiter: Arc<RateLimiter>,
+    releases: Arc<ReleaseCache>,
+    metrics: Arc<Metrics>,
+) -> Result<(), BotError> {
+    // Inline queries don't come from a chat, so only the client's language
+    // counts.
+    let lang = q
+        .from
+        .language_code
+        .as_deref()
+        .and_then(Language::from_code)
+        .unwrap_or_default();
+    let mut results = match releases
+        .get(
+            bot.inner().client(),
+            &limiter,
+            &metrics,
+            &config.release_channels,
+        )
+        .await
+    {
+        Ok(releases) => releases
+            .channels()
+            .filter(|(channel, _)| matches_query(&channel.key, &q.query))
+            .filter_map(|(channel, release)| {
+                let release = release?;
+                let name = channel.display_name.as_str();
+                let text = format_release(lang, name, Some(release)).ok()?;
+                let content = InputMessageContent::Text(
+                    InputMessageContentText::new(text).parse_mode(ParseMode::MarkdownV2),
+                );
+                let article = InlineQueryResultArticle::new(&channel.key, name, content)
+                    .description(lang.text(Text::ReleaseUpdated {
+                        time: &format_release_time(release.datetime).ok()?,
+                    }));
+
+                Some(InlineQueryResult::Article(
+                    match download_button(name, release) {
+                        Some(button) => article.reply_markup(InlineKeyboardMarkup::new([[button]])),
+                        None => article,
+                    },
+                ))
+            })
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            log::warn!("Failed to fetch the releases for an inline query: {e}");
+
+            Vec::new()
+        }
+    };
+
+    if results.is_empty() {
+        results.push(InlineQueryResult::Article(
+            InlineQueryResultArticle::new(
+                "none",
+                lang.text(Text::NoReleasesTitle),
+                InputMessageContent::Text(InputMessageContentText::new(
+                    lang.text(Text::NoReleasesText),
+                )),
+            )
+            .description(lang.text(Text::NoReleasesHint)),
+        ));
     }
 
+    bot.answer_inline_query(q.id, results)
+        .cache_time(INLINE_CACHE_TIME_SECS)
+        .await?;
+
     Ok(())
 }
 
-async fn receive_app_path_confirmation(
+async fn receive_target_branch(
     bot: LeonardoBot,
     q: CallbackQuery,
     dialogue: AppIconDialogue,
-    app_path: String,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
+    config: Arc<Config>,
+    lang: Language,
+) -> Result<(), BotError> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    remove_reply_markup(&bot, &q).await?;
+
     if let Some(answer) = &q.data {
         if let Some(chat_id) = q.chat_id() {
-            if answer == "Yes, this is correct" {
-                bot.send_message(
-                    chat_id,
-                    "Please attach a PNG with transparent background as the icon now.",
-                )
-                .await?;
+            let target_branches = selected_branches(&config, answer);
 
-                dialogue.update(State::ReceiveIconFile { app_path }).await?;
-            } else {
-                bot.send_message(chat_id, "Aborting.").await?;
+            if target_branches.is_empty() {
+                bot.send_message(chat_id, lang.text(Text::BranchClosed))
+                    .await?;
 
                 dialogue.exit().await?;
+            } else {
+                bot.send_message(chat_id, lang.text(Text::AskAppPath))
+                    .await?;
+
+                dialogue
+                    .update(State::ReceiveAppPath {
+                        target_branches,
+                        batch: Vec::new(),
+                    })
+                    .await?;
             }
         }
     }
@@ -308,269 +1296,423 @@ async fn receive_app_path_confirmation(
     Ok(())
 }
 
-async fn receive_icon_file(
+/// The branches picked with the `answer` to the branch question, every
+/// branch for "all". Empty if the branch isn't open for submissions anymore.
+fn selected_branches(config: &Config, answer: &str) -> Vec<String> {
+    config
+        .overlay_branches
+        .iter()
+        .filter(|branch| answer == "all" || branch.name == answer)
+        .map(|branch| branch.name.clone())
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn receive_app_path(
     bot: LeonardoBot,
     msg: Message,
     dialogue: AppIconDialogue,
-    app_path: String,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    if let Some(document) = msg.document() {
-        bot.send_message(
-            msg.chat.id,
-            "Provide a name for this icon, for example youtube_music or whatsapp. Must be lowercase and contain no special characters or spaces.",
-        )
-        .await?;
+    config: Arc<Config>,
+    limiter: Arc<RateLimiter>,
+    stores: Arc<AppStores>,
+    lang: Language,
+    (target_branches, batch): (Vec<String>, Vec<BatchedIcon>),
+) -> Result<(), BotError> {
+    if let Some(text) = msg.text() {
+        let app_path = match parse_app_path(text) {
+            Some(app_path) => app_path,
+            None => {
+                bot.send_message(msg.chat.id, lang.text(Text::InvalidAppPath))
+                    .await?;
 
-        dialogue
-            .update(State::ReceiveIconName {
-                app_path,
-                file_id: document.file_id.clone(),
-            })
-            .await?;
-    } else {
-        bot.send_message(msg.chat.id, "Please attach an image.")
+                return Ok(());
+            }
+        };
+
+        if let Some(conflict) = app_path_conflict(&batch, &app_path) {
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "{} {}",
+                    lang.text(conflict),
+                    lang.text(Text::AnotherAppPath)
+                ),
+            )
             .await?;
-    }
 
-    Ok(())
-}
+            return Ok(());
+        }
 
-async fn receive_icon_name(
-    bot: LeonardoBot,
-    msg: Message,
-    dialogue: AppIconDialogue,
-    (app_path, file_id): (String, String),
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    if let Some(name) = msg.text() {
-        bot.send_message(
-            msg.chat.id,
-            "Finally, provide a short description for this request.",
-        )
-        .await?;
+        let check = stores.check(&limiter, &app_path).await;
 
-        dialogue
-            .update(State::ReceiveDescription {
+        if check == StoreCheck::NotFound {
+            let answers = InlineKeyboardMarkup::default().append_row(
+                [Button::YesCorrect, Button::NoWrong]
+                    .into_iter()
+                    .map(|answer| answer.callback(lang)),
+            );
+
+            bot.send_message(msg.chat.id, lang.text(Text::AppNotFound))
+                .reply_markup(answers)
+                .await?;
+
+            dialogue
+                .update(State::ConfirmingAppPath {
+                    app_path,
+                    target_branches,
+                    batch,
+                })
+                .await?;
+        } else {
+            let note = match check {
+                StoreCheck::Found(store) => {
+                    let title = stores.details(&app_path).and_then(|details| details.title);
+
+                    lang.text(Text::FoundApp {
+                        title: title.as_deref(),
+                        app_path: &app_path,
+                        store,
+                    })
+                }
+                _ => lang.text(Text::StoresUnavailable),
+            };
+
+            bot.send_message(msg.chat.id, note).await?;
+
+            ask_for_icon(
+                &bot,
+                &dialogue,
+                &config,
+                lang,
                 app_path,
-                file_id,
-                icon_name: name.to_owned(),
-            })
+                target_branches,
+                batch,
+            )
             .await?;
+        }
     } else {
-        bot.send_message(msg.chat.id, "Please provide a name.")
+        bot.send_message(msg.chat.id, lang.text(Text::SendAppPath))
             .await?;
     }
 
     Ok(())
 }
 
-async fn receive_description(
+async fn receive_app_path_confirmation(
     bot: LeonardoBot,
-    msg: Message,
+    q: CallbackQuery,
     dialogue: AppIconDialogue,
-    (app_path, file_id, icon_name): (String, String, String),
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let description = msg.text().unwrap_or_default();
+    config: Arc<Config>,
+    lang: Language,
+    (app_path, target_branches, batch): (String, Vec<String>, Vec<BatchedIcon>),
+) -> Result<(), BotError> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    remove_reply_markup(&bot, &q).await?;
 
-    let bot_msg = bot
-        .send_message(msg.chat.id, "Downloading image...")
-        .await?;
+    if let Some(answer) = &q.data {
+        if let Some(chat_id) = q.chat_id() {
+            if answer == Button::YesCorrect.data() {
+                ask_for_icon(
+                    &bot,
+                    &dialogue,
+                    &config,
+                    lang,
+                    app_path,
+                    target_branches,
+                    batch,
+                )
+                .await?;
+            } else {
+                bot.send_message(chat_id, lang.text(Text::Aborting)).await?;
 
-    let file = bot.get_file(file_id).await?;
-    let extension = Path::new(&file.file_path)
-        .extension()
-        .and_then(|e| e.to_str());
+                dialogue.exit().await?;
+            }
+        }
+    }
 
-    let mut file_bytes = Vec::new();
-    bot.download_file(&file.file_path, &mut file_bytes).await?;
+    Ok(())
+}
 
-    match extension {
-        Some("png") => {
-            bot.edit_message_text(msg.chat.id, bot_msg.id, "Converting PNG to black PNM...")
-                .await?;
-
-            let svg = tokio::task::spawn_blocking(move || {
-                let mut img = load_from_memory(&file_bytes)?.into_rgba8();
-
-                for y in 0..img.height() {
-                    for x in 0..img.width() {
-                        // Convert any pixels that are not transparent to black
-                        let mut pixel = img.get_pixel_mut(x, y);
-
-                        if pixel.0[3] > 0 {
-                            // Make it black but keep transparency
-                            pixel.0[0] = 0;
-                            pixel.0[1] = 0;
-                            pixel.0[2] = 0;
-                        } else {
-                            // Make it white
-                            pixel.0[0] = 255;
-                            pixel.0[1] = 255;
-                            pixel.0[2] = 255;
-                            pixel.0[3] = 255;
-                        }
-                    }
-                }
+/// Asks for the icon of `app_path`, or whether to update it if the overlay
+/// already has one. Submissions for the app that are still pending review are
+/// pointed out first.
+async fn ask_for_icon(
+    bot: &LeonardoBot,
+    dialogue: &AppIconDialogue,
+    config: &Arc<Config>,
+    lang: Language,
+    app_path: String,
+    target_branches: Vec<String>,
+    batch: Vec<BatchedIcon>,
+) -> Result<(), BotError> {
+    let chat_id = dialogue.chat_id();
+    let status = icon_status(bot, config, &target_branches, &app_path).await?;
+
+    if let Some(pending) = pending_note(lang, &status.pending) {
+        bot.send_message(chat_id, pending).await?;
+    }
+
+    if let Some(drawable) = status.drawables.into_iter().flatten().next() {
+        let answers = InlineKeyboardMarkup::default().append_row(
+            [Button::UpdateExisting, Button::Abort]
+                .into_iter()
+                .map(|answer| answer.callback(lang)),
+        );
 
-                let svg = convert_image_to_svg(Config::from_preset(Preset::Bw), img)?;
+        bot.send_message(
+            chat_id,
+            lang.text(Text::AlreadyHasIcon {
+                app_path: &app_path,
+                drawable: &drawable,
+            }),
+        )
+        .reply_markup(answers)
+        .await?;
 
-                Ok::<_, Box<dyn Error + Send + Sync>>(svg)
+        dialogue
+            .update(State::ConfirmingUpdate {
+                app_path,
+                target_branches,
+                batch,
             })
-            .await??;
+            .await?;
 
-            bot.edit_message_text(msg.chat.id, bot_msg.id, "Converting SVG to VD...")
-                .await?;
+        return Ok(());
+    }
 
-            let mut vd_proc = TokioCommand::new("svg2vd");
-            vd_proc.args(&["-i", "-", "-o", "-"]);
-            vd_proc.stdout(Stdio::piped());
-            vd_proc.stdin(Stdio::piped());
+    bot.send_message(chat_id, lang.text(Text::AttachIcon))
+        .await?;
 
-            let mut child = vd_proc.spawn()?;
-            let mut stdin = child.stdin.take().unwrap();
+    dialogue
+        .update(State::ReceiveIconFile {
+            app_path,
+            icon_name: None,
+            target_branches,
+            batch,
+        })
+        .await?;
 
-            stdin.write_all(svg.as_bytes()).await?;
-            drop(stdin);
+    Ok(())
+}
 
-            let op = child.wait_with_output().await?;
+async fn receive_update_confirmation(
+    bot: LeonardoBot,
+    q: CallbackQuery,
+    dialogue: AppIconDialogue,
+    lang: Language,
+    (app_path, target_branches, batch): (String, Vec<String>, Vec<BatchedIcon>),
+) -> Result<(), BotError> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    remove_reply_markup(&bot, &q).await?;
 
-            if !op.status.success() {
-                bot.edit_message_text(msg.chat.id, bot_msg.id, "Failed to convert SVG to VD.")
+    if let Some(answer) = &q.data {
+        if let Some(chat_id) = q.chat_id() {
+            if answer == Button::UpdateExisting.data() {
+                bot.send_message(chat_id, lang.text(Text::AttachNewIcon))
                     .await?;
 
-                return Ok(());
+                dialogue
+                    .update(State::ReceiveIconFile {
+                        app_path,
+                        icon_name: None,
+                        target_branches,
+                        batch,
+                    })
+                    .await?;
             } else {
-                bot.edit_message_text(
-                    msg.chat.id,
-                    bot_msg.id,
-                    "Done with conversion. Here's a preview of the SVG:",
-                )
-                .await?;
+                bot.send_message(chat_id, lang.text(Text::Aborting)).await?;
+
+                dialogue.exit().await?;
             }
+        }
+    }
 
-            let answers = InlineKeyboardMarkup::default().append_row(
-                vec!["Yes, create my request", "No, abort"]
-                    .into_iter()
-                    .map(|answer| {
-                        InlineKeyboardButton::callback(answer.to_owned(), answer.to_owned())
-                    }),
-            );
+    Ok(())
+}
 
-            bot.send_document(msg.chat.id, InputFile::memory(svg).file_name("icon.svg"))
-                .caption("Please review the SVG file and if it is good, proceed!")
-                .reply_markup(answers)
-                .await?;
+async fn receive_icon_list_page(
+    bot: LeonardoBot,
+    q: CallbackQuery,
+    config: Arc<Config>,
+    lang: Language,
+) -> Result<(), BotError> {
+    bot.answer_callback_query(q.id.clone()).await?;
 
-            dialogue
-                .update(State::ConfirmingCreation {
-                    vd_bytes: op.stdout,
-                    app_path,
-                    description: description.to_owned(),
-                    icon_name,
-                })
-                .await?;
-        }
-        Some("svg") => {
-            bot.edit_message_text(msg.chat.id, bot_msg.id, "Converting SVG to VD...")
-                .await?;
+    let (page, filter) = match q.data.as_deref().and_then(icon_list::parse_callback_data) {
+        Some(page) => page,
+        None => return Ok(()),
+    };
+    let message = match &q.message {
+        Some(message) => message,
+        None => return Ok(()),
+    };
 
-            let mut vd_proc = TokioCommand::new("svg2vd");
-            vd_proc.args(&["-i", "-", "-o", "-"]);
-            vd_proc.stdout(Stdio::piped());
-            vd_proc.stdin(Stdio::piped());
+    let branch = config.default_overlay_branch();
+    let icon_map = config
+        .submission_backend
+        .icon_map(&config, bot.inner().client(), branch)
+        .await?;
+    let (text, keyboard) = icon_list::render_page(lang, &icon_map, branch, filter, page);
 
-            let mut child = vd_proc.spawn()?;
-            let mut stdin = child.stdin.take().unwrap();
+    let request = bot.edit_message_text(message.chat.id, message.id, text);
+    match keyboard {
+        Some(keyboard) => request.reply_markup(keyboard).await?,
+        None => request.await?,
+    };
 
-            stdin.write_all(&file_bytes).await?;
-            drop(stdin);
+    Ok(())
+}
 
-            let op = child.wait_with_output().await?;
+async fn receive_removal_target(
+    bot: LeonardoBot,
+    msg: Message,
+    dialogue: AppIconDialogue,
+    config: Arc<Config>,
+    git_lock: Arc<GitLock>,
+    lang: Language,
+) -> Result<(), BotError> {
+    let name = match msg.text() {
+        Some(name) => name.trim().to_owned(),
+        None => {
+            bot.send_message(msg.chat.id, lang.text(Text::RemovalTargetAsText))
+                .await?;
 
-            if !op.status.success() {
-                bot.edit_message_text(msg.chat.id, bot_msg.id, "Failed to convert SVG to VD.")
-                    .await?;
+            return Ok(());
+        }
+    };
 
-                return Ok(());
-            }
+    let backend = config.submission_backend;
+    let branches = config
+        .overlay_branches
+        .iter()
+        .map(|branch| branch.name.clone())
+        .collect::<Vec<_>>();
 
-            bot.edit_message_text(
-                msg.chat.id,
-                bot_msg.id,
-                "SVG converted to XML, uploading directly!",
-            )
-            .await?;
+    let guard = git_lock.lock().await;
+    let refreshed = backend.refresh(&config, &branches).await;
+    drop(guard);
+    refreshed?;
 
-            create_icon(
-                &bot,
-                dialogue,
-                icon_name,
-                op.stdout,
-                app_path,
-                description.to_owned(),
-            )
+    // (branch, package, drawable) of every matching entry.
+    let mut mappings = Vec::new();
+    for branch in &branches {
+        let icon_map = backend
+            .icon_map(&config, bot.inner().client(), branch)
             .await?;
 
-            bot.edit_message_text(
-                msg.chat.id,
-                bot_msg.id,
-                "Upload complete, thanks for your submission!",
-            )
-            .await?;
+        for icon in icon_map.lookup(&name) {
+            mappings.push((branch.clone(), icon.package.clone(), icon.drawable.clone()));
         }
-        Some("xml") => {
-            bot.edit_message_text(
-                msg.chat.id,
-                bot_msg.id,
-                "Android icon XML detected, uploading directly!",
-            )
-            .await?;
+    }
 
-            create_icon(
-                &bot,
-                dialogue,
-                icon_name,
-                file_bytes,
-                app_path,
-                description.to_owned(),
-            )
-            .await?;
+    let mut packages = mappings
+        .iter()
+        .map(|(_, package, _)| package.as_str())
+        .collect::<Vec<_>>();
+    packages.sort_unstable();
+    packages.dedup();
+
+    let app_path = match packages[..] {
+        [] => {
+            bot.send_message(msg.chat.id, lang.text(Text::NotInIconMap { name: &name }))
+                .await?;
 
-            bot.edit_message_text(
-                msg.chat.id,
-                bot_msg.id,
-                "Upload complete, thanks for your submission!",
-            )
-            .await?;
-        }
-        _ => {
             dialogue.exit().await?;
 
-            bot.edit_message_text(
+            return Ok(());
+        }
+        [package] => package.to_owned(),
+        _ => {
+            bot.send_message(
                 msg.chat.id,
-                bot_msg.id,
-                "File format unsupported, try again!",
+                lang.text(Text::UsedBySeveral {
+                    name: &name,
+                    packages: &packages.join(", "),
+                }),
             )
             .await?;
+
+            return Ok(());
         }
-    }
+    };
+
+    let mappings = mappings
+        .into_iter()
+        .filter(|(_, package, _)| *package == app_path)
+        .collect::<Vec<_>>();
+    let drawable = &mappings[0].2;
+    let icon_name = drawable
+        .strip_prefix("@drawable/themed_icon_")
+        .or_else(|| drawable.strip_prefix("@drawable/"))
+        .unwrap_or(drawable)
+        .to_owned();
+
+    let answers = InlineKeyboardMarkup::default().append_row(
+        [Button::YesRemove, Button::NoAbort]
+            .into_iter()
+            .map(|answer| answer.callback(lang)),
+    );
+
+    bot.send_message(
+        msg.chat.id,
+        lang.text(Text::ConfirmRemoval {
+            app_path: &app_path,
+            mappings: &mappings
+                .iter()
+                .map(|(branch, _, drawable)| lang.text(Text::MappedOn { drawable, branch }))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }),
+    )
+    .reply_markup(answers)
+    .await?;
+
+    dialogue
+        .update(State::ConfirmingRemoval {
+            app_path,
+            icon_name,
+            target_branches: mappings.into_iter().map(|(branch, _, _)| branch).collect(),
+        })
+        .await?;
 
     Ok(())
 }
 
-async fn receive_creation_confirmation(
+#[allow(clippy::too_many_arguments)]
+async fn receive_removal_confirmation(
     bot: LeonardoBot,
     q: CallbackQuery,
     dialogue: AppIconDialogue,
-    (vd_bytes, icon_name, app_path, description): (Vec<u8>, String, String, String),
-) -> Result<(), Box<dyn Error + Send + Sync>> {
+    config: Arc<Config>,
+    notifier: Arc<MaintainerNotifier>,
+    in_flight: Arc<InFlight>,
+    git_lock: Arc<GitLock>,
+    lang: Language,
+    (app_path, icon_name, target_branches): (String, String, Vec<String>),
+) -> Result<(), BotError> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    remove_reply_markup(&bot, &q).await?;
+
     if let Some(answer) = &q.data {
         if let Some(chat_id) = q.chat_id() {
-            if answer == "Yes, create my request" {
-                create_icon(&bot, dialogue, icon_name, vd_bytes, app_path, description).await?;
-
-                bot.send_message(chat_id, "Created.").await?;
+            if answer == Button::YesRemove.data() {
+                remove_icon(
+                    &bot,
+                    &dialogue,
+                    &config,
+                    &notifier,
+                    &in_flight,
+                    &git_lock,
+                    lang,
+                    &app_path,
+                    &icon_name,
+                    &target_branches,
+                )
+                .await?;
             } else {
-                bot.send_message(chat_id, "Aborting.").await?;
+                bot.send_message(chat_id, lang.text(Text::Aborting)).await?;
+
+                dialogue.exit().await?;
             }
         }
     }
@@ -578,158 +1720,4007 @@ async fn receive_creation_confirmation(
     Ok(())
 }
 
-async fn create_icon(
-    bot: &LeonardoBot,
+async fn receive_icon_file(
+    bot: LeonardoBot,
+    msg: Message,
     dialogue: AppIconDialogue,
-    icon_name: String,
-    vd_bytes: Vec<u8>,
-    app_path: String,
-    description: String,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let base = env::var("PATH_TO_ICONS_OVERLAY")?;
-    let branch_name = format!("bot/icon_{icon_name}");
-    let branch_refspec = format!("refs/heads/{branch_name}");
-    let vd_file_name = format!("themed_icon_{icon_name}.xml");
-    let commit_msg = format!("overlay: Add icon for {icon_name}");
-
-    let commit_msg_clone = commit_msg.clone();
-    let branch_name_clone = branch_name.clone();
-
-    let vd_file_path: PathBuf = [
-        &base,
-        "PixelLauncherIconsOverlay",
-        "res",
-        "drawable",
-        &vd_file_name,
-    ]
-    .iter()
-    .collect();
-    let xml_file_path: PathBuf = [
-        &base,
-        "PixelLauncherIconsOverlay",
-        "res",
-        "xml",
-        "grayscale_icon_map.xml",
-    ]
-    .iter()
-    .collect();
-
-    tokio::task::spawn_blocking(move || {
-        let prev_xml = fs::read_to_string(&xml_file_path)?;
-
-        // For whatever reason, none of the XML parsers for Rust have proper
-        // support for serde + pretty serialization.
-        // So for now, we add the line where it is needed.
-        let mut lines: Vec<String> = prev_xml.lines().map(ToString::to_string).collect();
-        let line = format!(
-            "    <icon drawable=\"@drawable/themed_icon_{icon_name}\" package=\"{app_path}\" />"
-        );
-        lines.insert(2, line);
-        let line_count = lines.len();
-        lines[2..line_count - 1].sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
+    config: Arc<Config>,
+    lang: Language,
+    (app_path, icon_name, target_branches, batch): (
+        String,
+        Option<String>,
+        Vec<String>,
+        Vec<BatchedIcon>,
+    ),
+) -> Result<(), BotError> {
+    let (file_id, is_svg) = match icon_file(msg.document(), msg.sticker()) {
+        Ok(icon_file) => icon_file,
+        Err(text) => {
+            bot.send_message(msg.chat.id, lang.text(text)).await?;
 
-        let new_xml = lines.join("\n");
+            return Ok(());
+        }
+    };
 
-        fs::write(vd_file_path, vd_bytes)?;
-        fs::write(xml_file_path, new_xml)?;
+    // The name was already picked for the previous image.
+    if let Some(icon_name) = icon_name {
+        bot.send_message(msg.chat.id, lang.text(Text::AskDescription))
+            .await?;
 
-        let repo = Repository::open(base)?;
+        dialogue
+            .update(State::ReceiveDescription {
+                app_path,
+                file_id,
+                is_svg,
+                icon_name,
+                target_branches,
+                batch,
+            })
+            .await?;
 
-        let head = repo.head()?.peel_to_commit()?;
-        let branch = repo.branch(&branch_name, &head, true)?;
-        repo.set_head(branch.into_reference().name().unwrap())?;
+        return Ok(());
+    }
 
-        let tree_id = {
-            let mut index = repo.index()?;
-            index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
-            index.write_tree()?
-        };
-        let tree = repo.find_tree(tree_id)?;
-
-        let signature = repo.signature()?;
-        let head = repo.head()?.peel_to_commit()?;
-        repo.commit(
-            Some("HEAD"),
-            &signature,
-            &signature,
-            &commit_msg,
-            &tree,
-            &[&head],
-        )?;
-        repo.checkout_head(None)?;
-
-        let mut push_opts = PushOptions::new();
-        let mut callbacks = RemoteCallbacks::new();
-        let mut remote = repo.find_remote("origin")?;
-        callbacks.credentials(|_url, username_from_url, _allowed_type| {
-            Cred::ssh_key(
-                username_from_url.unwrap_or("git"),
-                None,
-                Path::new(&env::var("SSH_KEY").unwrap()),
-                None,
-            )
-        });
-        push_opts.remote_callbacks(callbacks);
-        remote.push(&[&branch_refspec], Some(&mut push_opts))?;
+    let status = icon_status(&bot, &config, &target_branches, &app_path).await?;
+    let existing_name = status
+        .drawables
+        .iter()
+        .flatten()
+        .find_map(|drawable| drawable.strip_prefix("themed_icon_"));
 
-        let main_ref = repo.revparse_single("12.1")?;
-        repo.checkout_tree(&main_ref, None)?;
-        repo.set_head("refs/heads/12.1")?;
+    if let Some(pending) = pending_note(lang, &status.pending) {
+        bot.send_message(msg.chat.id, pending).await?;
+    }
 
-        Ok::<(), Box<dyn Error + Send + Sync>>(())
-    })
-    .await??;
+    let prompt = lang.text(match existing_name {
+        Some(name) => Text::AskIconNameKeep { name },
+        None => Text::AskIconName,
+    });
 
-    let params = MergeRequestParams {
-        id: OVERLAY_GITLAB_PROJECT_ID,
-        title: commit_msg_clone,
-        description,
-        source_branch: branch_name_clone,
-        target_branch: String::from("12.1"),
-        remove_source_branch: true,
-    };
+    bot.send_message(msg.chat.id, prompt).await?;
 
-    bot.inner()
-        .client()
-        .post(format!(
-            "https://gitlab.com/api/v4/projects/{OVERLAY_GITLAB_PROJECT_ID}/merge_requests"
-        ))
-        .header("PRIVATE-TOKEN", env::var("GITLAB_TOKEN")?)
-        .json(&params)
-        .send()
+    dialogue
+        .update(State::ReceiveIconName {
+            app_path,
+            file_id,
+            is_svg,
+            target_branches,
+            batch,
+        })
         .await?;
 
-    dialogue.exit().await?;
-
     Ok(())
 }
 
-async fn get_release(
-    client: &reqwest::Client,
-    url: &str,
-) -> Result<Option<OtaData>, reqwest::Error> {
-    Ok(client.get(url).send().await?.json::<OtaData>().await.ok())
+/// The file id of the icon a user sent and whether it is an SVG, or the
+/// message telling them why it can't be used.
+fn icon_file(
+    document: Option<&Document>,
+    sticker: Option<&Sticker>,
+) -> Result<(String, bool), Text<'static>> {
+    match (document, sticker) {
+        (Some(document), _) => Ok((document.file_id.clone(), is_svg_document(document))),
+        (None, Some(sticker)) if sticker.is_animated || sticker.is_video => {
+            Err(Text::AnimatedSticker)
+        }
+        // Static stickers are WebP images with transparency.
+        (None, Some(sticker)) => Ok((sticker.file_id.clone(), false)),
+        (None, None) => Err(Text::AttachImage),
+    }
 }
 
-async fn get_latest_releases(client: &reqwest::Client) -> Result<AllReleases, reqwest::Error> {
-    let dcos = get_release(client, OTA_DCOS).await?;
-    let dcos_pre = get_release(client, OTA_DCOS_PRE).await?;
-    let dcosx = get_release(client, OTA_DCOSX).await?;
-    let dcosx_pre = get_release(client, OTA_DCOSX_PRE).await?;
-
-    Ok(AllReleases {
-        dcos,
-        dcos_pre,
-        dcosx,
-        dcosx_pre,
-    })
+/// Whether an uploaded document is an SVG, judging by its MIME type or file
+/// name.
+fn is_svg_document(document: &Document) -> bool {
+    document
+        .mime_type
+        .as_ref()
+        .map_or(false, |mime| mime.essence_str() == "image/svg+xml")
+        || document
+            .file_name
+            .as_deref()
+            .map_or(false, |name| name.to_lowercase().ends_with(".svg"))
 }
 
-async fn playstore_app_exists(
-    client: &reqwest::Client,
-    app_path: &str,
-) -> Result<bool, reqwest::Error> {
-    let app_url = format!("https://play.google.com/store/apps/details?id={app_path}&gl=US");
+async fn receive_icon_name(
+    bot: LeonardoBot,
+    msg: Message,
+    dialogue: AppIconDialogue,
+    config: Arc<Config>,
+    lang: Language,
+    (app_path, file_id, is_svg, target_branches, batch): (
+        String,
+        String,
+        bool,
+        Vec<String>,
+        Vec<BatchedIcon>,
+    ),
+) -> Result<(), BotError> {
+    if let Some(name) = msg.text() {
+        let icon_name = name.to_owned();
 
-    Ok(client.head(app_url).send().await?.status() == 200)
+        if let Some(conflict) = icon_name_conflict(&batch, &app_path, &icon_name) {
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "{} {}",
+                    lang.text(conflict),
+                    lang.text(Text::PickAnotherName)
+                ),
+            )
+            .await?;
+
+            return Ok(());
+        }
+
+        if let Some(taken) =
+            name_taken(&bot, &config, lang, &target_branches, &app_path, &icon_name).await?
+        {
+            let answers = InlineKeyboardMarkup::default().append_row(
+                [Button::ReplaceIt, Button::PickAnotherName]
+                    .into_iter()
+                    .map(|answer| answer.callback(lang)),
+            );
+
+            bot.send_message(
+                msg.chat.id,
+                format!("{taken} {}", lang.text(Text::ReplaceOrPickAnother)),
+            )
+            .reply_markup(answers)
+            .await?;
+
+            dialogue
+                .update(State::ConfirmingIconName {
+                    app_path,
+                    file_id,
+                    is_svg,
+                    icon_name,
+                    target_branches,
+                    batch,
+                })
+                .await?;
+
+            return Ok(());
+        }
+
+        bot.send_message(msg.chat.id, lang.text(Text::AskDescription))
+            .await?;
+
+        dialogue
+            .update(State::ReceiveDescription {
+                app_path,
+                file_id,
+                is_svg,
+                icon_name,
+                target_branches,
+                batch,
+            })
+            .await?;
+    } else {
+        bot.send_message(msg.chat.id, lang.text(Text::ProvideName))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Says which icon already uses the drawable `icon_name` on one of
+/// `target_branches`, unless it is the icon of `app_path` itself.
+async fn name_taken(
+    bot: &LeonardoBot,
+    config: &Arc<Config>,
+    lang: Language,
+    target_branches: &[String],
+    app_path: &str,
+    icon_name: &str,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    let existing = config
+        .submission_backend
+        .find_existing_drawable(config, bot.inner().client(), target_branches, icon_name)
+        .await?;
+
+    Ok(existing
+        .filter(|existing| existing.package.as_deref() != Some(app_path))
+        .map(|existing| {
+            lang.text(Text::NameTaken {
+                icon_name,
+                package: existing.package.as_deref(),
+                branch: &existing.branch,
+            })
+        }))
+}
+
+async fn receive_icon_name_confirmation(
+    bot: LeonardoBot,
+    q: CallbackQuery,
+    dialogue: AppIconDialogue,
+    lang: Language,
+    (app_path, file_id, is_svg, icon_name, target_branches, batch): (
+        String,
+        String,
+        bool,
+        String,
+        Vec<String>,
+        Vec<BatchedIcon>,
+    ),
+) -> Result<(), BotError> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    remove_reply_markup(&bot, &q).await?;
+
+    if let Some(answer) = &q.data {
+        if let Some(chat_id) = q.chat_id() {
+            if answer == Button::ReplaceIt.data() {
+                bot.send_message(chat_id, lang.text(Text::AskDescription))
+                    .await?;
+
+                dialogue
+                    .update(State::ReceiveDescription {
+                        app_path,
+                        file_id,
+                        is_svg,
+                        icon_name,
+                        target_branches,
+                        batch,
+                    })
+                    .await?;
+            } else {
+                bot.send_message(chat_id, lang.text(Text::ProvideDifferentName))
+                    .await?;
+
+                dialogue
+                    .update(State::ReceiveIconName {
+                        app_path,
+                        file_id,
+                        is_svg,
+                        target_branches,
+                        batch,
+                    })
+                    .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn receive_description(
+    bot: LeonardoBot,
+    msg: Message,
+    dialogue: AppIconDialogue,
+    config: Arc<Config>,
+    notifier: Arc<MaintainerNotifier>,
+    metrics: Arc<Metrics>,
+    in_flight: Arc<InFlight>,
+    tracked: Arc<TrackedMergeRequests>,
+    limits: Arc<SubmissionLimits>,
+    access: Arc<AccessList>,
+    moderator: Arc<Moderator>,
+    git_lock: Arc<GitLock>,
+    reviews: Arc<PendingReviews>,
+    languages: Arc<ChatLanguages>,
+    stores: Arc<AppStores>,
+    lang: Language,
+    (app_path, file_id, is_svg, icon_name, target_branches, batch): (
+        String,
+        String,
+        bool,
+        String,
+        Vec<String>,
+        Vec<BatchedIcon>,
+    ),
+) -> Result<(), BotError> {
+    let description = msg.text().unwrap_or_default().to_owned();
+    let user_id = match msg.from() {
+        Some(user) => user.id,
+        None => return Ok(()),
+    };
+
+    let bot_msg = bot
+        .send_message(msg.chat.id, lang.text(Text::DownloadingImage))
+        .await?;
+
+    let deadline = Deadline::after(config.submission_timeout);
+    let result = process_icon(
+        &bot,
+        dialogue.clone(),
+        &config,
+        &notifier,
+        &metrics,
+        &in_flight,
+        &tracked,
+        &limits,
+        &access,
+        &moderator,
+        &git_lock,
+        &reviews,
+        &languages,
+        &stores,
+        lang,
+        user_id,
+        bot_msg.id,
+        deadline,
+        description,
+        (
+            app_path.clone(),
+            file_id,
+            is_svg,
+            icon_name.clone(),
+            target_branches.clone(),
+            batch.clone(),
+        ),
+    )
+    .await;
+
+    match result {
+        Err(e) if e.is::<DeadlineExceeded>() => {
+            count_exceeded_deadline(&metrics, &*e);
+
+            bot.edit_message_text(
+                msg.chat.id,
+                bot_msg.id,
+                lang.text(Text::TooSlowAttachAgain {
+                    error: &e.to_string(),
+                    request: deadline.request_id(),
+                }),
+            )
+            .await?;
+
+            dialogue
+                .update(State::ReceiveIconFile {
+                    app_path,
+                    icon_name: Some(icon_name),
+                    target_branches,
+                    batch,
+                })
+                .await?;
+
+            Ok(())
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Counts the stage a submission ran out of time in, if `e` is a
+/// [`DeadlineExceeded`].
+fn count_exceeded_deadline(metrics: &Metrics, e: &(dyn Error + Send + Sync + 'static)) {
+    if let Some(exceeded) = e.downcast_ref::<DeadlineExceeded>() {
+        metrics.deadline_exceeded(exceeded.0);
+    }
+}
+
+/// Downloads and converts the submitted icon. Stops with [`DeadlineExceeded`]
+/// if this takes longer than `deadline` allows.
+#[allow(clippy::too_many_arguments)]
+async fn process_icon(
+    bot: &LeonardoBot,
+    dialogue: AppIconDialogue,
+    config: &Arc<Config>,
+    notifier: &MaintainerNotifier,
+    metrics: &Metrics,
+    in_flight: &InFlight,
+    tracked: &TrackedMergeRequests,
+    limits: &SubmissionLimits,
+    access: &AccessList,
+    moderator: &Moderator,
+    git_lock: &GitLock,
+    reviews: &PendingReviews,
+    languages: &ChatLanguages,
+    stores: &AppStores,
+    lang: Language,
+    user_id: i64,
+    bot_msg_id: i32,
+    deadline: Deadline,
+    description: String,
+    (app_path, file_id, is_svg, icon_name, target_branches, batch): (
+        String,
+        String,
+        bool,
+        String,
+        Vec<String>,
+        Vec<BatchedIcon>,
+    ),
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = dialogue.chat_id();
+    let file = deadline
+        .run(Stage::Download, async {
+            bot.get_file(file_id)
+                .await
+                .map_err(|e| BotError::Download(e.into()))
+        })
+        .await?;
+
+    if file.file_size > config.max_icon_file_size {
+        bot.edit_message_text(chat_id, bot_msg_id, lang.text(Text::FileTooLarge))
+            .await?;
+
+        dialogue
+            .update(State::ReceiveIconFile {
+                app_path,
+                icon_name: Some(icon_name),
+                target_branches,
+                batch,
+            })
+            .await?;
+
+        return Ok(());
+    }
+
+    let extension = Path::new(&file.file_path)
+        .extension()
+        .and_then(|e| e.to_str());
+
+    let file_bytes = deadline
+        .run(
+            Stage::Download,
+            download_complete(&file.file_path, file.file_size, || async {
+                let mut file_bytes = Vec::new();
+                bot.download_file(&file.file_path, &mut file_bytes).await?;
+
+                Ok(file_bytes)
+            }),
+        )
+        .await?;
+
+    if let Some(rejection) = moderator.screen(access, user_id, &file_bytes).await {
+        // Only the size of the image is audited, it is dropped right away.
+        drop(file_bytes);
+        post_rejection(bot, config.audit_chat_id, &rejection).await;
+
+        bot.edit_message_text(chat_id, bot_msg_id, lang.text(Text::ImageRejected))
+            .await?;
+
+        dialogue.exit().await?;
+
+        return Ok(());
+    }
+
+    // Telegram does not always keep the file extension of uploaded SVGs.
+    let extension = if is_svg { Some("svg") } else { extension };
+
+    match extension {
+        // Stickers are downloaded as WebP, which is traced like a PNG.
+        Some("png" | "webp") => {
+            convert_png(
+                bot,
+                dialogue,
+                config,
+                notifier,
+                lang,
+                bot_msg_id,
+                deadline,
+                file_bytes,
+                (app_path, icon_name, description, target_branches, batch),
+            )
+            .await?;
+        }
+        Some("svg") => {
+            let svg = match check_svg(file_bytes) {
+                Ok(svg) => svg,
+                Err(e) => {
+                    bot.edit_message_text(
+                        chat_id,
+                        bot_msg_id,
+                        lang.text(Text::SvgUnusable {
+                            error: &e.to_string(),
+                        }),
+                    )
+                    .await?;
+
+                    dialogue
+                        .update(State::ReceiveIconFile {
+                            app_path,
+                            icon_name: Some(icon_name),
+                            target_branches,
+                            batch,
+                        })
+                        .await?;
+
+                    return Ok(());
+                }
+            };
+
+            bot.edit_message_text(chat_id, bot_msg_id, lang.text(Text::ConvertingSvg))
+                .await?;
+
+            let vd_bytes = match deadline
+                .run(
+                    Stage::Convert,
+                    svg_to_vd(&config.svg2vd_bin, svg.as_bytes()),
+                )
+                .await
+            {
+                Ok(vd_bytes) => vd_bytes,
+                Err(e) if e.is::<CommandFailed>() || e.is::<InvalidVectorDrawable>() => {
+                    notifier
+                        .pipeline_failed(
+                            bot,
+                            chat_id,
+                            Some(Stage::Convert),
+                            &app_path,
+                            Some(&icon_name),
+                            &e,
+                        )
+                        .await;
+
+                    let error = e.to_string();
+                    let text = lang.text(if e.is::<CommandFailed>() {
+                        Text::ConvertFailed { error: &error }
+                    } else {
+                        Text::UnusableVd { error: &error }
+                    });
+
+                    bot.edit_message_text(chat_id, bot_msg_id, text).await?;
+
+                    // Keep the name so only the image has to be sent again.
+                    dialogue
+                        .update(State::ReceiveIconFile {
+                            app_path,
+                            icon_name: Some(icon_name),
+                            target_branches,
+                            batch,
+                        })
+                        .await?;
+
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            };
+
+            bot.edit_message_text(chat_id, bot_msg_id, lang.text(Text::ConversionDone))
+                .await?;
+
+            send_svg_preview(
+                bot,
+                chat_id,
+                lang,
+                svg.clone(),
+                &vd_bytes,
+                &icon_name,
+                false,
+            )
+            .await?;
+
+            dialogue
+                .update(State::ConfirmingCreation {
+                    vd_bytes,
+                    svg,
+                    app_path,
+                    description,
+                    icon_name,
+                    png_bytes: None,
+                    trace_options: TraceOptions::from_config(config),
+                    target_branches,
+                    batch,
+                })
+                .await?;
+        }
+        Some("xml") => {
+            let vd_bytes = match check_vector_drawable(file_bytes) {
+                Ok(vd_bytes) => vd_bytes,
+                Err(e) => {
+                    bot.edit_message_text(
+                        chat_id,
+                        bot_msg_id,
+                        lang.text(Text::XmlUnusable {
+                            error: &e.to_string(),
+                        }),
+                    )
+                    .await?;
+
+                    dialogue
+                        .update(State::ReceiveIconFile {
+                            app_path,
+                            icon_name: Some(icon_name),
+                            target_branches,
+                            batch,
+                        })
+                        .await?;
+
+                    return Ok(());
+                }
+            };
+
+            bot.edit_message_text(chat_id, bot_msg_id, lang.text(Text::XmlDetected))
+                .await?;
+
+            // XML icons skip the confirmation and go right into the request.
+            add_to_batch(
+                bot,
+                dialogue,
+                config,
+                notifier,
+                metrics,
+                in_flight,
+                tracked,
+                limits,
+                access,
+                git_lock,
+                reviews,
+                languages,
+                lang,
+                user_id,
+                batch,
+                BatchedIcon {
+                    app: stores.details(&app_path),
+                    app_path,
+                    icon_name,
+                    description,
+                    vd_bytes,
+                    svg: None,
+                },
+                target_branches,
+            )
+            .await?;
+        }
+        _ => {
+            dialogue.exit().await?;
+
+            bot.edit_message_text(chat_id, bot_msg_id, lang.text(Text::UnsupportedFormat))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Traces a PNG icon, converts it to a VectorDrawable and asks the user to
+/// confirm the result.
+#[allow(clippy::too_many_arguments)]
+async fn convert_png(
+    bot: &LeonardoBot,
+    dialogue: AppIconDialogue,
+    config: &Arc<Config>,
+    notifier: &MaintainerNotifier,
+    lang: Language,
+    bot_msg_id: i32,
+    deadline: Deadline,
+    png_bytes: Vec<u8>,
+    (app_path, icon_name, description, target_branches, batch): (
+        String,
+        String,
+        String,
+        Vec<String>,
+        Vec<BatchedIcon>,
+    ),
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = dialogue.chat_id();
+
+    bot.edit_message_text(chat_id, bot_msg_id, lang.text(Text::ConvertingPng))
+        .await?;
+
+    let trace_options = TraceOptions::from_config(config);
+    let traced = match deadline
+        .run(
+            Stage::Trace,
+            trace_png(config, png_bytes.clone(), trace_options),
+        )
+        .await
+    {
+        Ok(traced) => traced,
+        Err(e) if e.is::<ImageTooSmall>() => {
+            bot.edit_message_text(
+                chat_id,
+                bot_msg_id,
+                lang.text(Text::ImageTooSmall {
+                    error: &e.to_string(),
+                }),
+            )
+            .await?;
+
+            dialogue
+                .update(State::ReceiveIconFile {
+                    app_path,
+                    icon_name: Some(icon_name),
+                    target_branches,
+                    batch,
+                })
+                .await?;
+
+            return Ok(());
+        }
+        Err(e) if e.is::<ExtremeAspectRatio>() => {
+            bot.edit_message_text(
+                chat_id,
+                bot_msg_id,
+                lang.text(Text::ExtremeAspectRatio {
+                    error: &e.to_string(),
+                }),
+            )
+            .await?;
+
+            dialogue
+                .update(State::ReceiveIconFile {
+                    app_path,
+                    icon_name: Some(icon_name),
+                    target_branches,
+                    batch,
+                })
+                .await?;
+
+            return Ok(());
+        }
+        Err(e) if e.is::<EmptyImage>() => {
+            bot.edit_message_text(chat_id, bot_msg_id, lang.text(Text::FullyTransparent))
+                .await?;
+
+            dialogue
+                .update(State::ReceiveIconFile {
+                    app_path,
+                    icon_name: Some(icon_name),
+                    target_branches,
+                    batch,
+                })
+                .await?;
+
+            return Ok(());
+        }
+        Err(e) if e.is::<NoTransparency>() => {
+            let background = e
+                .downcast_ref::<NoTransparency>()
+                .and_then(|e| e.background);
+
+            if let Some(background) = background {
+                let answers = InlineKeyboardMarkup::default().append_row(
+                    [Button::RemoveBackground, Button::SendAnotherImage]
+                        .into_iter()
+                        .map(|answer| answer.callback(lang)),
+                );
+
+                bot.edit_message_text(chat_id, bot_msg_id, lang.text(Text::SolidBackground))
+                    .reply_markup(answers)
+                    .await?;
+
+                dialogue
+                    .update(State::ConfirmingBackgroundRemoval {
+                        app_path,
+                        icon_name,
+                        description,
+                        png_bytes,
+                        background: background.0,
+                        target_branches,
+                        batch,
+                        deadline: deadline.pause(),
+                    })
+                    .await?;
+            } else {
+                bot.edit_message_text(chat_id, bot_msg_id, lang.text(Text::NoTransparency))
+                    .await?;
+
+                dialogue
+                    .update(State::ReceiveIconFile {
+                        app_path,
+                        icon_name: Some(icon_name),
+                        target_branches,
+                        batch,
+                    })
+                    .await?;
+            }
+
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+    let svg = traced.svg;
+
+    let status = lang.text(if traced.padded {
+        Text::PaddedConvertingSvg
+    } else {
+        Text::ConvertingSvg
+    });
+    bot.edit_message_text(chat_id, bot_msg_id, status).await?;
+
+    let vd_bytes = match deadline
+        .run(
+            Stage::Convert,
+            svg_to_vd(&config.svg2vd_bin, svg.as_bytes()),
+        )
+        .await
+    {
+        Ok(vd_bytes) => vd_bytes,
+        Err(e) if e.is::<CommandFailed>() || e.is::<InvalidVectorDrawable>() => {
+            notifier
+                .pipeline_failed(
+                    bot,
+                    chat_id,
+                    Some(Stage::Convert),
+                    &app_path,
+                    Some(&icon_name),
+                    &e,
+                )
+                .await;
+
+            let error = e.to_string();
+            let text = lang.text(if e.is::<CommandFailed>() {
+                Text::ConvertFailed { error: &error }
+            } else {
+                Text::UnusableVd { error: &error }
+            });
+
+            bot.edit_message_text(chat_id, bot_msg_id, text).await?;
+
+            // Keep the name so only the image has to be sent again.
+            dialogue
+                .update(State::ReceiveIconFile {
+                    app_path,
+                    icon_name: Some(icon_name),
+                    target_branches,
+                    batch,
+                })
+                .await?;
+
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+
+    // The note ends up in the description so reviewers see it too.
+    let (status, description) = match traced.low_resolution {
+        Some((width, height)) => (
+            lang.text(Text::ConversionDoneLowResolution { width, height }),
+            format!("{description}\n\nNote: the source image was only {width}×{height} pixels."),
+        ),
+        None => (lang.text(Text::ConversionDone), description),
+    };
+
+    bot.edit_message_text(chat_id, bot_msg_id, status).await?;
+
+    send_svg_preview(bot, chat_id, lang, svg.clone(), &vd_bytes, &icon_name, true).await?;
+
+    dialogue
+        .update(State::ConfirmingCreation {
+            vd_bytes,
+            svg,
+            app_path,
+            description,
+            icon_name,
+            png_bytes: Some(png_bytes),
+            trace_options,
+            target_branches,
+            batch,
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn receive_background_removal_confirmation(
+    bot: LeonardoBot,
+    q: CallbackQuery,
+    dialogue: AppIconDialogue,
+    config: Arc<Config>,
+    notifier: Arc<MaintainerNotifier>,
+    metrics: Arc<Metrics>,
+    lang: Language,
+    (app_path, icon_name, description, png_bytes, background, target_branches, batch, deadline): (
+        String,
+        String,
+        String,
+        Vec<u8>,
+        [u8; 4],
+        Vec<String>,
+        Vec<BatchedIcon>,
+        PausedDeadline,
+    ),
+) -> Result<(), BotError> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    remove_reply_markup(&bot, &q).await?;
+
+    if let Some(answer) = &q.data {
+        if let Some(chat_id) = q.chat_id() {
+            if answer == Button::RemoveBackground.data() {
+                let bot_msg = bot
+                    .send_message(chat_id, lang.text(Text::RemovingBackground))
+                    .await?;
+
+                let deadline = deadline.resume();
+                let result = async {
+                    let png_bytes = deadline
+                        .run(
+                            Stage::Trace,
+                            remove_png_background(config.max_icon_dimension, png_bytes, background),
+                        )
+                        .await?;
+
+                    convert_png(
+                        &bot,
+                        dialogue.clone(),
+                        &config,
+                        &notifier,
+                        lang,
+                        bot_msg.id,
+                        deadline,
+                        png_bytes,
+                        (
+                            app_path.clone(),
+                            icon_name.clone(),
+                            description,
+                            target_branches.clone(),
+                            batch.clone(),
+                        ),
+                    )
+                    .await
+                }
+                .await;
+
+                match result {
+                    Err(e) if e.is::<DeadlineExceeded>() => {
+                        count_exceeded_deadline(&metrics, &*e);
+
+                        bot.edit_message_text(
+                            chat_id,
+                            bot_msg.id,
+                            lang.text(Text::TooSlowAttachAgain {
+                                error: &e.to_string(),
+                                request: deadline.request_id(),
+                            }),
+                        )
+                        .await?;
+
+                        dialogue
+                            .update(State::ReceiveIconFile {
+                                app_path,
+                                icon_name: Some(icon_name),
+                                target_branches,
+                                batch,
+                            })
+                            .await?;
+                    }
+                    result => result?,
+                }
+            } else {
+                bot.send_message(chat_id, lang.text(Text::AttachIcon))
+                    .await?;
+
+                dialogue
+                    .update(State::ReceiveIconFile {
+                        app_path,
+                        icon_name: Some(icon_name),
+                        target_branches,
+                        batch,
+                    })
+                    .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn receive_creation_confirmation(
+    bot: LeonardoBot,
+    q: CallbackQuery,
+    dialogue: AppIconDialogue,
+    config: Arc<Config>,
+    notifier: Arc<MaintainerNotifier>,
+    metrics: Arc<Metrics>,
+    in_flight: Arc<InFlight>,
+    tracked: Arc<TrackedMergeRequests>,
+    limits: Arc<SubmissionLimits>,
+    access: Arc<AccessList>,
+    git_lock: Arc<GitLock>,
+    reviews: Arc<PendingReviews>,
+    languages: Arc<ChatLanguages>,
+    stores: Arc<AppStores>,
+    lang: Language,
+    (
+        vd_bytes,
+        svg,
+        icon_name,
+        app_path,
+        description,
+        png_bytes,
+        trace_options,
+        target_branches,
+        batch,
+    ): (
+        Vec<u8>,
+        String,
+        String,
+        String,
+        String,
+        Option<Vec<u8>>,
+        TraceOptions,
+        Vec<String>,
+        Vec<BatchedIcon>,
+    ),
+) -> Result<(), BotError> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    remove_reply_markup(&bot, &q).await?;
+
+    if let Some(answer) = &q.data {
+        if let Some(chat_id) = q.chat_id() {
+            if answer == Button::YesCreate.data() {
+                add_to_batch(
+                    &bot,
+                    dialogue,
+                    &config,
+                    &notifier,
+                    &metrics,
+                    &in_flight,
+                    &tracked,
+                    &limits,
+                    &access,
+                    &git_lock,
+                    &reviews,
+                    &languages,
+                    lang,
+                    q.from.id,
+                    batch,
+                    BatchedIcon {
+                        app: stores.details(&app_path),
+                        app_path,
+                        icon_name,
+                        description,
+                        vd_bytes,
+                        svg: Some(svg),
+                    },
+                    target_branches,
+                )
+                .await?;
+            } else if answer == Button::PreviewMergeRequest.data() {
+                let mut icons = batch.clone();
+                icons.push(BatchedIcon {
+                    app: stores.details(&app_path),
+                    app_path: app_path.clone(),
+                    icon_name: icon_name.clone(),
+                    description: description.clone(),
+                    vd_bytes: vd_bytes.clone(),
+                    svg: Some(svg.clone()),
+                });
+
+                // Previewing must not upload anything to GitLab.
+                let inputs =
+                    merge_request_inputs(&bot, &config, chat_id, &target_branches, &icons, false)
+                        .await?;
+                let preview = build_merge_requests(&config, &icons, &target_branches, &inputs)
+                    .iter()
+                    .map(merge_request_preview)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                bot.send_message(chat_id, preview)
+                    .parse_mode(ParseMode::MarkdownV2)
+                    .reply_markup(confirmation_keyboard(lang, png_bytes.is_some()))
+                    .await?;
+            } else if answer == Button::ChangeName.data() {
+                bot.send_message(chat_id, lang.text(Text::ProvideNewName))
+                    .await?;
+
+                dialogue
+                    .update(State::ReceiveNewIconName {
+                        vd_bytes,
+                        svg,
+                        app_path,
+                        icon_name,
+                        description,
+                        png_bytes,
+                        trace_options,
+                        target_branches,
+                        batch,
+                    })
+                    .await?;
+            } else if answer == Button::DifferentImage.data() {
+                bot.send_message(chat_id, lang.text(Text::AttachNewImage))
+                    .await?;
+
+                dialogue
+                    .update(State::ReceiveIconFile {
+                        app_path,
+                        icon_name: Some(icon_name),
+                        target_branches,
+                        batch,
+                    })
+                    .await?;
+            } else if let (Some(png_bytes), Some(trace_options)) =
+                (png_bytes, trace_options.adjust(answer))
+            {
+                let bot_msg = bot
+                    .send_message(
+                        chat_id,
+                        lang.text(Text::Retracing {
+                            options: &trace_options.to_string(),
+                        }),
+                    )
+                    .await?;
+
+                let deadline = Deadline::after(config.submission_timeout);
+                let svg = match deadline
+                    .run(
+                        Stage::Trace,
+                        trace_png(&config, png_bytes.clone(), trace_options),
+                    )
+                    .await
+                {
+                    Ok(traced) => traced.svg,
+                    Err(e) if e.is::<ExtremeAspectRatio>() => {
+                        bot.edit_message_text(
+                            chat_id,
+                            bot_msg.id,
+                            lang.text(Text::RetraceUnusable {
+                                error: &e.to_string(),
+                            }),
+                        )
+                        .reply_markup(confirmation_keyboard(lang, true))
+                        .await?;
+
+                        return Ok(());
+                    }
+                    Err(e) if e.is::<EmptyImage>() => {
+                        bot.edit_message_text(chat_id, bot_msg.id, lang.text(Text::NothingLeft))
+                            .reply_markup(confirmation_keyboard(lang, true))
+                            .await?;
+
+                        return Ok(());
+                    }
+                    Err(e) if e.is::<DeadlineExceeded>() => {
+                        count_exceeded_deadline(&metrics, &*e);
+
+                        bot.edit_message_text(
+                            chat_id,
+                            bot_msg.id,
+                            lang.text(Text::TooSlowKeepingPrevious {
+                                error: &e.to_string(),
+                                request: deadline.request_id(),
+                            }),
+                        )
+                        .reply_markup(confirmation_keyboard(lang, true))
+                        .await?;
+
+                        return Ok(());
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+
+                let vd_bytes = match deadline
+                    .run(
+                        Stage::Convert,
+                        svg_to_vd(&config.svg2vd_bin, svg.as_bytes()),
+                    )
+                    .await
+                {
+                    Ok(vd_bytes) => vd_bytes,
+                    Err(e) if e.is::<DeadlineExceeded>() => {
+                        count_exceeded_deadline(&metrics, &*e);
+
+                        bot.edit_message_text(
+                            chat_id,
+                            bot_msg.id,
+                            lang.text(Text::TooSlowKeepingPrevious {
+                                error: &e.to_string(),
+                                request: deadline.request_id(),
+                            }),
+                        )
+                        .reply_markup(confirmation_keyboard(lang, true))
+                        .await?;
+
+                        return Ok(());
+                    }
+                    Err(e) if e.is::<CommandFailed>() || e.is::<InvalidVectorDrawable>() => {
+                        notifier
+                            .pipeline_failed(
+                                &bot,
+                                chat_id,
+                                Some(Stage::Convert),
+                                &app_path,
+                                Some(&icon_name),
+                                &e,
+                            )
+                            .await;
+
+                        bot.edit_message_text(
+                            chat_id,
+                            bot_msg.id,
+                            lang.text(Text::RetraceConvertFailed {
+                                error: &e.to_string(),
+                            }),
+                        )
+                        .reply_markup(confirmation_keyboard(lang, true))
+                        .await?;
+
+                        return Ok(());
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+
+                bot.edit_message_text(chat_id, bot_msg.id, lang.text(Text::NewPreview))
+                    .await?;
+
+                send_svg_preview(
+                    &bot,
+                    chat_id,
+                    lang,
+                    svg.clone(),
+                    &vd_bytes,
+                    &icon_name,
+                    true,
+                )
+                .await?;
+
+                dialogue
+                    .update(State::ConfirmingCreation {
+                        vd_bytes,
+                        svg,
+                        app_path,
+                        icon_name,
+                        description,
+                        png_bytes: Some(png_bytes),
+                        trace_options,
+                        target_branches,
+                        batch,
+                    })
+                    .await?;
+            } else {
+                bot.send_message(chat_id, lang.text(Text::Aborting)).await?;
+
+                dialogue.exit().await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Renames the converted icon and asks for confirmation again.
+async fn receive_new_icon_name(
+    bot: LeonardoBot,
+    msg: Message,
+    dialogue: AppIconDialogue,
+    config: Arc<Config>,
+    lang: Language,
+    (
+        vd_bytes,
+        svg,
+        old_name,
+        app_path,
+        description,
+        png_bytes,
+        trace_options,
+        target_branches,
+        batch,
+    ): (
+        Vec<u8>,
+        String,
+        String,
+        String,
+        String,
+        Option<Vec<u8>>,
+        TraceOptions,
+        Vec<String>,
+        Vec<BatchedIcon>,
+    ),
+) -> Result<(), BotError> {
+    let icon_name = match msg.text() {
+        Some(name) => name.to_owned(),
+        None => {
+            bot.send_message(msg.chat.id, lang.text(Text::ProvideName))
+                .await?;
+
+            return Ok(());
+        }
+    };
+
+    if let Some(conflict) = icon_name_conflict(&batch, &app_path, &icon_name) {
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "{} {}",
+                lang.text(conflict),
+                lang.text(Text::PickAnotherName)
+            ),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    // Replacing another icon is only offered when the name is first picked,
+    // so it can't happen by accident here.
+    if icon_name != old_name {
+        if let Some(taken) =
+            name_taken(&bot, &config, lang, &target_branches, &app_path, &icon_name).await?
+        {
+            bot.send_message(
+                msg.chat.id,
+                format!("{taken} {}", lang.text(Text::PickAnotherName)),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    }
+
+    send_svg_preview(
+        &bot,
+        msg.chat.id,
+        lang,
+        svg.clone(),
+        &vd_bytes,
+        &icon_name,
+        png_bytes.is_some(),
+    )
+    .await?;
+
+    dialogue
+        .update(State::ConfirmingCreation {
+            vd_bytes,
+            svg,
+            app_path,
+            icon_name,
+            description,
+            png_bytes,
+            trace_options,
+            target_branches,
+            batch,
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Adds the confirmed `icon` to the icons of the request in `batch` and asks
+/// whether to add another one. Requests with [`MAX_BATCH_SIZE`] icons are
+/// submitted right away.
+#[allow(clippy::too_many_arguments)]
+async fn add_to_batch(
+    bot: &LeonardoBot,
+    dialogue: AppIconDialogue,
+    config: &Arc<Config>,
+    notifier: &MaintainerNotifier,
+    metrics: &Metrics,
+    in_flight: &InFlight,
+    tracked: &TrackedMergeRequests,
+    limits: &SubmissionLimits,
+    access: &AccessList,
+    git_lock: &GitLock,
+    reviews: &PendingReviews,
+    languages: &ChatLanguages,
+    lang: Language,
+    user_id: i64,
+    mut batch: Vec<BatchedIcon>,
+    icon: BatchedIcon,
+    target_branches: Vec<String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = dialogue.chat_id();
+    batch.push(icon);
+
+    // Kept in the dialogue so a failed submission can be retried.
+    dialogue
+        .update(State::ConfirmingBatch {
+            icons: batch.clone(),
+            target_branches: target_branches.clone(),
+        })
+        .await?;
+
+    if batch.len() >= MAX_BATCH_SIZE {
+        bot.send_message(
+            chat_id,
+            lang.text(Text::BatchFull {
+                max: MAX_BATCH_SIZE,
+            }),
+        )
+        .await?;
+
+        return submit_batch(
+            bot,
+            dialogue,
+            config,
+            notifier,
+            metrics,
+            in_flight,
+            tracked,
+            limits,
+            access,
+            git_lock,
+            reviews,
+            languages,
+            lang,
+            user_id,
+            batch,
+            target_branches,
+        )
+        .await;
+    }
+
+    let answers = InlineKeyboardMarkup::default().append_row(
+        [Button::AddAnother, Button::Submit]
+            .into_iter()
+            .map(|answer| answer.callback(lang)),
+    );
+
+    bot.send_message(chat_id, lang.text(Text::AddAnother))
+        .reply_markup(answers)
+        .await?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn receive_batch_confirmation(
+    bot: LeonardoBot,
+    q: CallbackQuery,
+    dialogue: AppIconDialogue,
+    config: Arc<Config>,
+    notifier: Arc<MaintainerNotifier>,
+    metrics: Arc<Metrics>,
+    in_flight: Arc<InFlight>,
+    tracked: Arc<TrackedMergeRequests>,
+    limits: Arc<SubmissionLimits>,
+    access: Arc<AccessList>,
+    git_lock: Arc<GitLock>,
+    reviews: Arc<PendingReviews>,
+    languages: Arc<ChatLanguages>,
+    lang: Language,
+    (icons, target_branches): (Vec<BatchedIcon>, Vec<String>),
+) -> Result<(), BotError> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    remove_reply_markup(&bot, &q).await?;
+
+    if let Some(answer) = &q.data {
+        if let Some(chat_id) = q.chat_id() {
+            if answer == Button::AddAnother.data() {
+                bot.send_message(chat_id, lang.text(Text::AskNextAppPath))
+                    .await?;
+
+                dialogue
+                    .update(State::ReceiveAppPath {
+                        target_branches,
+                        batch: icons,
+                    })
+                    .await?;
+            } else {
+                submit_batch(
+                    &bot,
+                    dialogue,
+                    &config,
+                    &notifier,
+                    &metrics,
+                    &in_flight,
+                    &tracked,
+                    &limits,
+                    &access,
+                    &git_lock,
+                    &reviews,
+                    &languages,
+                    lang,
+                    q.from.id,
+                    icons,
+                    target_branches,
+                )
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends the icons of a request to review, or submits them in one merge
+/// request per target branch if there is no review chat.
+#[allow(clippy::too_many_arguments)]
+async fn submit_batch(
+    bot: &LeonardoBot,
+    dialogue: AppIconDialogue,
+    config: &Arc<Config>,
+    notifier: &MaintainerNotifier,
+    metrics: &Metrics,
+    in_flight: &InFlight,
+    tracked: &TrackedMergeRequests,
+    limits: &SubmissionLimits,
+    access: &AccessList,
+    git_lock: &GitLock,
+    reviews: &PendingReviews,
+    languages: &ChatLanguages,
+    lang: Language,
+    user_id: i64,
+    icons: Vec<BatchedIcon>,
+    target_branches: Vec<String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = dialogue.chat_id();
+
+    // Checked again in case the dialogue was started before the limit was
+    // reached.
+    if submission_refused(bot, limits, access, lang, chat_id, user_id).await? {
+        dialogue.exit().await?;
+
+        return Ok(());
+    }
+
+    if let Some(review_chat_id) = config.review_chat_id {
+        request_review(
+            bot,
+            dialogue,
+            reviews,
+            languages,
+            lang,
+            user_id,
+            review_chat_id,
+            icons,
+            target_branches,
+        )
+        .await?;
+    } else if create_icon(
+        bot,
+        dialogue,
+        config,
+        notifier,
+        metrics,
+        in_flight,
+        tracked,
+        limits,
+        git_lock,
+        lang,
+        user_id,
+        Deadline::after(config.submission_timeout),
+        icons.clone(),
+        target_branches,
+        false,
+    )
+    .await?
+    {
+        send_drawables(bot, chat_id, &icons, &lang.text(Text::Created)).await?;
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn receive_retry_confirmation(
+    bot: LeonardoBot,
+    q: CallbackQuery,
+    dialogue: AppIconDialogue,
+    config: Arc<Config>,
+    notifier: Arc<MaintainerNotifier>,
+    metrics: Arc<Metrics>,
+    in_flight: Arc<InFlight>,
+    tracked: Arc<TrackedMergeRequests>,
+    limits: Arc<SubmissionLimits>,
+    access: Arc<AccessList>,
+    git_lock: Arc<GitLock>,
+    lang: Language,
+    (icons, target_branches): (Vec<BatchedIcon>, Vec<String>),
+) -> Result<(), BotError> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    remove_reply_markup(&bot, &q).await?;
+
+    if let Some(answer) = &q.data {
+        if let Some(chat_id) = q.chat_id() {
+            if answer == Button::TryAgain.data() {
+                // The user may have been banned or reached the limit since.
+                if submission_refused(&bot, &limits, &access, lang, chat_id, q.from.id).await? {
+                    dialogue.exit().await?;
+
+                    return Ok(());
+                }
+
+                if create_icon(
+                    &bot,
+                    dialogue,
+                    &config,
+                    &notifier,
+                    &metrics,
+                    &in_flight,
+                    &tracked,
+                    &limits,
+                    &git_lock,
+                    lang,
+                    q.from.id,
+                    // A retry is a new attempt and gets the full time again.
+                    Deadline::after(config.submission_timeout),
+                    icons.clone(),
+                    target_branches,
+                    false,
+                )
+                .await?
+                {
+                    send_drawables(&bot, chat_id, &icons, &lang.text(Text::Created)).await?;
+                }
+            } else {
+                bot.send_message(chat_id, lang.text(Text::Aborting)).await?;
+
+                dialogue.exit().await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn receive_merge_request_update_confirmation(
+    bot: LeonardoBot,
+    q: CallbackQuery,
+    dialogue: AppIconDialogue,
+    config: Arc<Config>,
+    notifier: Arc<MaintainerNotifier>,
+    metrics: Arc<Metrics>,
+    in_flight: Arc<InFlight>,
+    tracked: Arc<TrackedMergeRequests>,
+    limits: Arc<SubmissionLimits>,
+    access: Arc<AccessList>,
+    git_lock: Arc<GitLock>,
+    lang: Language,
+    (icons, target_branches, deadline): (Vec<BatchedIcon>, Vec<String>, PausedDeadline),
+) -> Result<(), BotError> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    remove_reply_markup(&bot, &q).await?;
+
+    if let Some(answer) = &q.data {
+        if let Some(chat_id) = q.chat_id() {
+            if answer == Button::UpdateIt.data() {
+                // The user may have been banned or reached the limit since.
+                if submission_refused(&bot, &limits, &access, lang, chat_id, q.from.id).await? {
+                    dialogue.exit().await?;
+
+                    return Ok(());
+                }
+
+                if create_icon(
+                    &bot,
+                    dialogue,
+                    &config,
+                    &notifier,
+                    &metrics,
+                    &in_flight,
+                    &tracked,
+                    &limits,
+                    &git_lock,
+                    lang,
+                    q.from.id,
+                    deadline.resume(),
+                    icons.clone(),
+                    target_branches,
+                    true,
+                )
+                .await?
+                {
+                    send_drawables(&bot, chat_id, &icons, &lang.text(Text::Updated)).await?;
+                }
+            } else {
+                bot.send_message(chat_id, lang.text(Text::Aborting)).await?;
+
+                dialogue.exit().await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends a submission to the reviewers chat instead of submitting it right
+/// away. It is only submitted once a reviewer approves it, see
+/// [`receive_review`].
+#[allow(clippy::too_many_arguments)]
+async fn request_review(
+    bot: &LeonardoBot,
+    dialogue: AppIconDialogue,
+    reviews: &PendingReviews,
+    languages: &ChatLanguages,
+    lang: Language,
+    user_id: i64,
+    review_chat_id: ChatId,
+    icons: Vec<BatchedIcon>,
+    target_branches: Vec<String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let submitter = dialogue.chat_id();
+    let id = reviews.next_id();
+    let review_lang = languages.for_chat(review_chat_id);
+
+    for icon in &icons {
+        bot.send_document(review_chat_id, vd_document(&icon.vd_bytes, &icon.icon_name))
+            .await?;
+
+        if let Some(svg) = &icon.svg {
+            let svg_bytes = svg.clone().into_bytes();
+
+            match tokio::task::spawn_blocking(move || render_png(&svg_bytes, PREVIEW_SIZE)).await? {
+                Ok(png) => {
+                    bot.send_photo(
+                        review_chat_id,
+                        InputFile::memory(png).file_name("preview.png"),
+                    )
+                    .await?;
+                }
+                Err(e) => log::warn!("Failed to render preview for review: {e}"),
+            }
+        }
+    }
+
+    let username = match username(bot, submitter).await {
+        Some(username) => format!(" (@{username})"),
+        None => String::new(),
+    };
+    let buttons = InlineKeyboardMarkup::default().append_row(vec![
+        InlineKeyboardButton::callback(
+            review_lang.text(Text::ApproveButton),
+            review::callback_data(true, id),
+        ),
+        InlineKeyboardButton::callback(
+            review_lang.text(Text::RejectButton),
+            review::callback_data(false, id),
+        ),
+    ]);
+    let details = icons
+        .iter()
+        .map(|icon| {
+            let app = icon.app.as_ref().map(|details| {
+                format!(
+                    "{} ({})",
+                    details.title.as_deref().unwrap_or(&icon.app_path),
+                    details.store.page_url(&icon.app_path)
+                )
+            });
+
+            review_lang.text(Text::SubmissionDetails {
+                app_path: &icon.app_path,
+                app: app.as_deref(),
+                icon_name: &icon.icon_name,
+                description: &icon.description,
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let message = bot
+        .send_message(
+            review_chat_id,
+            review_lang.text(Text::NewSubmission {
+                user: &format!("{}{username}", submitter.0),
+                branches: &target_branches.join(", "),
+                details: &details,
+            }),
+        )
+        .reply_markup(buttons)
+        .await?;
+
+    reviews.insert(
+        id,
+        Review {
+            submitter,
+            submitter_id: user_id,
+            icons,
+            target_branches,
+            message_id: message.id,
+        },
+    );
+
+    bot.send_message(submitter, lang.text(Text::SentForReview))
+        .await?;
+
+    dialogue.update(State::AwaitingReview).await?;
+
+    Ok(())
+}
+
+/// Handles the Approve and Reject buttons in the reviewers chat. Approved
+/// submissions go through the usual commit, push and merge request steps in
+/// the submitter's dialogue.
+#[allow(clippy::too_many_arguments)]
+async fn receive_review(
+    bot: LeonardoBot,
+    q: CallbackQuery,
+    config: Arc<Config>,
+    notifier: Arc<MaintainerNotifier>,
+    metrics: Arc<Metrics>,
+    in_flight: Arc<InFlight>,
+    tracked: Arc<TrackedMergeRequests>,
+    limits: Arc<SubmissionLimits>,
+    git_lock: Arc<GitLock>,
+    storage: Arc<InMemStorage<State>>,
+    reviews: Arc<PendingReviews>,
+    languages: Arc<ChatLanguages>,
+    review_lang: Language,
+) -> Result<(), BotError> {
+    let (approve, id) = match q.data.as_deref().and_then(review::parse_callback_data) {
+        Some(answer) => answer,
+        None => return Ok(()),
+    };
+
+    if !config.reviewer_ids.contains(&q.from.id) {
+        bot.answer_callback_query(q.id.clone())
+            .text(review_lang.text(Text::OnlyReviewers))
+            .await?;
+
+        return Ok(());
+    }
+
+    bot.answer_callback_query(q.id.clone()).await?;
+    remove_reply_markup(&bot, &q).await?;
+
+    // Another reviewer was faster, or the review expired.
+    let review = match reviews.take(id) {
+        Some(review) => review,
+        None => return Ok(()),
+    };
+
+    if let Some(message) = &q.message {
+        let reviewer = match &q.from.username {
+            Some(username) => format!("@{username}"),
+            None => q.from.full_name(),
+        };
+
+        bot.send_message(
+            message.chat.id,
+            review_lang.text(Text::ReviewVerdict {
+                reviewer: &reviewer,
+                approved: approve,
+                icon_names: &icon_names(&review.icons),
+                app_paths: &app_paths(&review.icons),
+            }),
+        )
+        .await?;
+    }
+
+    let dialogue = AppIconDialogue::new(storage, review.submitter);
+    // Everything from here on goes to the submitter, not the reviewer.
+    let lang = languages.for_chat(review.submitter);
+
+    if !approve {
+        bot.send_message(
+            review.submitter,
+            lang.text(Text::SubmissionRejected {
+                app_paths: &app_paths(&review.icons),
+            }),
+        )
+        .await?;
+
+        dialogue.exit().await?;
+
+        return Ok(());
+    }
+
+    bot.send_message(
+        review.submitter,
+        lang.text(Text::SubmissionApproved {
+            app_paths: &app_paths(&review.icons),
+        }),
+    )
+    .await?;
+
+    let result = create_icon(
+        &bot,
+        dialogue.clone(),
+        &config,
+        &notifier,
+        &metrics,
+        &in_flight,
+        &tracked,
+        &limits,
+        &git_lock,
+        lang,
+        review.submitter_id,
+        // The time waiting for the review is not counted.
+        Deadline::after(config.submission_timeout),
+        review.icons.clone(),
+        review.target_branches,
+        false,
+    )
+    .await;
+
+    match result {
+        Ok(true) => {
+            send_drawables(
+                &bot,
+                review.submitter,
+                &review.icons,
+                &lang.text(Text::Created),
+            )
+            .await?;
+        }
+        Ok(false) => {}
+        Err(e) => {
+            // The error itself is reported in the reviewers chat.
+            bot.send_message(review.submitter, lang.text(Text::ApprovedSubmissionFailed))
+                .await?;
+
+            dialogue.exit().await?;
+
+            return Err(e.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Stops fetching updates on Ctrl+C or SIGTERM and gives the running
+/// handlers `grace` to finish, so no submission is cut off in the middle of
+/// pushing. Exits right away if they take longer.
+async fn shutdown_on_signal(token: ShutdownToken, in_flight: Arc<InFlight>, grace: Duration) {
+    shutdown::signal().await;
+    log::info!(
+        "Shutting down, waiting up to {}s for running handlers",
+        grace.as_secs()
+    );
+
+    match token.shutdown() {
+        Ok(stopped) => {
+            if tokio::time::timeout(grace, stopped).await.is_ok() {
+                return;
+            }
+        }
+        // The dispatcher is not running, there is nothing to wait for.
+        Err(_) => return,
+    }
+
+    let running = in_flight.running();
+    if running.is_empty() {
+        log::warn!("Handlers were still running after the grace period");
+    }
+    for work in running {
+        log::warn!("Exiting while still {work}");
+    }
+
+    std::process::exit(1);
+}
+
+/// Asks Telegram who the bot is every [`TELEGRAM_PROBE_INTERVAL`], so
+/// `/healthz` notices when Telegram can't be reached anymore.
+async fn probe_telegram(bot: LeonardoBot, metrics: Arc<Metrics>) {
+    let mut interval = tokio::time::interval(TELEGRAM_PROBE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match bot.get_me().await {
+            Ok(_) => metrics.telegram_reached(),
+            Err(e) => log::warn!("Telegram could not be reached: {e}"),
+        }
+    }
+}
+
+/// Checks the tracked merge requests every `MERGE_REQUEST_POLL_SECS` and
+/// tells their submitters once they are merged or closed.
+async fn watch_merge_requests(
+    bot: LeonardoBot,
+    config: Arc<Config>,
+    tracked: Arc<TrackedMergeRequests>,
+    languages: Arc<ChatLanguages>,
+) {
+    let mut interval = tokio::time::interval(config.merge_request_poll_interval);
+
+    loop {
+        interval.tick().await;
+
+        for merge_request in tracked.all() {
+            let lang = languages.for_chat(merge_request.chat_id);
+
+            match merge_request_outcome(&bot, &config, lang, &merge_request).await {
+                Ok(Some(text)) => {
+                    // Users who never opened a private chat with the bot
+                    // can't be messaged, there is no point in trying again.
+                    if let Err(e) = bot.send_message(merge_request.chat_id, text).await {
+                        log::warn!(
+                            "Failed to tell {} about merge request !{}: {e}",
+                            merge_request.chat_id,
+                            merge_request.iid
+                        );
+                    }
+
+                    tracked.forget(merge_request.iid);
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("Failed to check merge request !{}: {e}", merge_request.iid),
+            }
+        }
+    }
+}
+
+/// What to tell the submitter about `tracked`, `None` while it is still
+/// open.
+async fn merge_request_outcome(
+    bot: &LeonardoBot,
+    config: &Config,
+    lang: Language,
+    tracked: &TrackedMergeRequest,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    let url = format!(
+        "https://gitlab.com/api/v4/projects/{}/merge_requests/{}",
+        config.gitlab_project_id, tracked.iid
+    );
+    let app_paths = tracked.app_path.replace(',', ", ");
+    let branch = &tracked.target_branch;
+
+    let response = bot
+        .inner()
+        .client()
+        .get(&url)
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .send()
+        .await?;
+    let merge_request = gitlab_error_for_status(response)
+        .await?
+        .json::<MergeRequest>()
+        .await?;
+
+    match merge_request.state.as_str() {
+        "merged" => Ok(Some(lang.text(Text::Merged {
+            app_paths: &app_paths,
+            branch,
+        }))),
+        "closed" => {
+            let response = bot
+                .inner()
+                .client()
+                .get(format!("{url}/notes"))
+                .query(&[("sort", "desc"), ("order_by", "created_at")])
+                .header("PRIVATE-TOKEN", &config.gitlab_token)
+                .send()
+                .await?;
+            let notes = gitlab_error_for_status(response)
+                .await?
+                .json::<Vec<MergeRequestNote>>()
+                .await?;
+
+            let note = notes.into_iter().find(|note| !note.system);
+
+            Ok(Some(lang.text(match &note {
+                Some(note) => Text::ClosedWithNote {
+                    app_paths: &app_paths,
+                    branch,
+                    note: &note.body,
+                },
+                None => Text::Closed {
+                    app_paths: &app_paths,
+                    branch,
+                    url: &merge_request.web_url,
+                },
+            })))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Drops submissions nobody reviewed within `REVIEW_TIMEOUT_SECS` and tells
+/// their submitters.
+async fn expire_reviews(
+    bot: LeonardoBot,
+    review_chat_id: ChatId,
+    storage: Arc<InMemStorage<State>>,
+    reviews: Arc<PendingReviews>,
+    languages: Arc<ChatLanguages>,
+) {
+    let mut interval = tokio::time::interval(REVIEW_EXPIRY_CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        for review in reviews.take_expired() {
+            let result: Result<(), BotError> = async {
+                bot.edit_message_reply_markup(review_chat_id, review.message_id)
+                    .await?;
+                bot.send_message(
+                    review_chat_id,
+                    languages
+                        .for_chat(review_chat_id)
+                        .text(Text::ReviewDropped {
+                            icon_names: &icon_names(&review.icons),
+                            app_paths: &app_paths(&review.icons),
+                        }),
+                )
+                .await?;
+                bot.send_message(
+                    review.submitter,
+                    languages
+                        .for_chat(review.submitter)
+                        .text(Text::ReviewExpired {
+                            app_paths: &app_paths(&review.icons),
+                        }),
+                )
+                .await?;
+
+                let dialogue = AppIconDialogue::new(storage.clone(), review.submitter);
+
+                if matches!(dialogue.get().await?, Some(State::AwaitingReview)) {
+                    dialogue.exit().await?;
+                }
+
+                Ok(())
+            }
+            .await;
+
+            if let Err(e) = result {
+                log::warn!(
+                    "Failed to expire the review of {} for {}: {}",
+                    icon_names(&review.icons),
+                    app_paths(&review.icons),
+                    e.chain()
+                );
+            }
+        }
+    }
+}
+
+/// Answers callbacks from keyboards whose dialogue step is already over, e.g.
+/// when a confirmation button is tapped a second time.
+async fn receive_stale_callback(
+    bot: LeonardoBot,
+    q: CallbackQuery,
+    lang: Language,
+) -> Result<(), BotError> {
+    bot.answer_callback_query(q.id.clone())
+        .text(lang.text(Text::AlreadyProcessed))
+        .await?;
+    remove_reply_markup(&bot, &q).await?;
+
+    Ok(())
+}
+
+/// Tells the user what went wrong when a handler failed and moves the
+/// dialogue to a step they can continue from: image errors ask for the icon
+/// again, repository errors offer to retry the submission. Anything else
+/// leaves the dialogue where it was.
+async fn report_error(
+    bot: &LeonardoBot,
+    dialogue: &AppIconDialogue,
+    notifier: &MaintainerNotifier,
+    lang: Language,
+    error: BotError,
+) -> Result<(), BotError> {
+    let chat_id = dialogue.chat_id();
+    log::error!("Failed to handle an update in {chat_id}: {}", error.chain());
+
+    let state = dialogue.get().await?.unwrap_or_default();
+    let icon_name = state.icon_name().map(str::to_owned);
+
+    if let Some((app_path, _, _)) = state.clone().submission() {
+        notifier
+            .pipeline_failed(
+                bot,
+                chat_id,
+                error.stage(),
+                &app_path,
+                icon_name.as_deref(),
+                &error.chain(),
+            )
+            .await;
+    }
+    let text = error.user_message(lang);
+
+    match (&error, state) {
+        (
+            BotError::Download(_) | BotError::ImageDecode(_) | BotError::Trace(_) | BotError::Vd(_),
+            state,
+        ) => match state.submission() {
+            Some((app_path, target_branches, batch)) => {
+                bot.send_message(
+                    chat_id,
+                    format!("{text} {}", lang.text(Text::SendDifferentImage)),
+                )
+                .await?;
+
+                dialogue
+                    .update(State::ReceiveIconFile {
+                        app_path,
+                        icon_name,
+                        target_branches,
+                        batch,
+                    })
+                    .await?;
+            }
+            None => {
+                bot.send_message(chat_id, text).await?;
+            }
+        },
+        (
+            BotError::Git(_) | BotError::GitLabApi { .. },
+            State::ConfirmingBatch {
+                icons,
+                target_branches,
+            }
+            | State::ConfirmingMergeRequestUpdate {
+                icons,
+                target_branches,
+                ..
+            }
+            | State::RetryingCreation {
+                icons,
+                target_branches,
+            },
+        ) => {
+            bot.send_message(
+                chat_id,
+                format!("{text} {}", lang.text(Text::TryAgainQuestion)),
+            )
+            .reply_markup(retry_keyboard(lang))
+            .await?;
+
+            dialogue
+                .update(State::RetryingCreation {
+                    icons,
+                    target_branches,
+                })
+                .await?;
+        }
+        _ => {
+            bot.send_message(chat_id, text).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Drops the inline keyboard from the message a callback originated from so
+/// it cannot be tapped again.
+async fn remove_reply_markup(
+    bot: &LeonardoBot,
+    q: &CallbackQuery,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(message) = &q.message {
+        bot.edit_message_reply_markup(message.chat.id, message.id)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Builds the keyboard to confirm the conversion result. The buttons to
+/// adjust the tracing are only shown if `can_retrace` is set, i.e. the
+/// icon was traced from a PNG.
+fn confirmation_keyboard(lang: Language, can_retrace: bool) -> InlineKeyboardMarkup {
+    let row = |buttons: &[Button]| {
+        buttons
+            .iter()
+            .map(|button| button.callback(lang))
+            .collect::<Vec<_>>()
+    };
+    let keyboard =
+        InlineKeyboardMarkup::default().append_row(row(&[Button::YesCreate, Button::NoAbort]));
+
+    let keyboard = if can_retrace {
+        keyboard
+            .append_row(row(&[Button::Thinner, Button::Thicker]))
+            .append_row(row(&[
+                Button::RemoveSpeckles,
+                Button::MoreDetail,
+                Button::SmootherCurves,
+            ]))
+    } else {
+        keyboard
+    };
+
+    keyboard
+        .append_row(row(&[Button::ChangeName, Button::DifferentImage]))
+        .append_row(row(&[Button::PreviewMergeRequest]))
+}
+
+/// Escapes text for use inside a MarkdownV2 code block.
+fn escape_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+fn retry_keyboard(lang: Language) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::default().append_row(
+        [Button::TryAgain, Button::NoAbort]
+            .into_iter()
+            .map(|answer| answer.callback(lang)),
+    )
+}
+
+/// Wraps the generated VectorDrawable in a file named like it will be in the
+/// overlay.
+fn vd_document(vd_bytes: &[u8], icon_name: &str) -> InputFile {
+    InputFile::memory(vd_bytes.to_vec()).file_name(format!("themed_icon_{icon_name}.xml"))
+}
+
+/// Sends the drawables of `icons`, the last one with `caption`.
+async fn send_drawables(
+    bot: &LeonardoBot,
+    chat_id: ChatId,
+    icons: &[BatchedIcon],
+    caption: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for (index, icon) in icons.iter().enumerate() {
+        let document = bot.send_document(chat_id, vd_document(&icon.vd_bytes, &icon.icon_name));
+
+        if index + 1 == icons.len() {
+            document.caption(caption).await?;
+        } else {
+            document.await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_svg_preview(
+    bot: &LeonardoBot,
+    chat_id: ChatId,
+    lang: Language,
+    svg: String,
+    vd_bytes: &[u8],
+    icon_name: &str,
+    can_retrace: bool,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let caption = lang.text(if can_retrace {
+        Text::ReviewTracedIcon
+    } else {
+        Text::ReviewIcon
+    });
+
+    bot.send_document(chat_id, vd_document(vd_bytes, icon_name))
+        .await?;
+
+    let svg_bytes = svg.clone().into_bytes();
+    let rendered =
+        tokio::task::spawn_blocking(move || render_png(&svg_bytes, PREVIEW_SIZE)).await?;
+
+    match rendered {
+        Ok(png) => {
+            bot.send_document(chat_id, InputFile::memory(svg).file_name("icon.svg"))
+                .await?;
+            bot.send_photo(chat_id, InputFile::memory(png).file_name("preview.png"))
+                .caption(caption)
+                .reply_markup(confirmation_keyboard(lang, can_retrace))
+                .await?;
+        }
+        Err(e) => {
+            // Telegram can't show SVGs inline, but the file is still enough
+            // to review the icon.
+            log::warn!("Failed to render preview, only sending the SVG: {e}");
+
+            bot.send_document(chat_id, InputFile::memory(svg).file_name("icon.svg"))
+                .caption(caption)
+                .reply_markup(confirmation_keyboard(lang, can_retrace))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Commits the icons to every branch in `target_branches` and opens one merge
+/// request for all of them on each. Returns `false` if `deadline` passed in
+/// between, in which case the user is asked whether to retry the remaining
+/// branches. Each branch is prepared in its own temporary clone, only
+/// refreshing the shared overlay cache waits for `git_lock`.
+///
+/// Open merge requests left from an earlier submission of the same icons are
+/// only force-pushed to if `update_open_merge_requests` is set, otherwise the
+/// user is asked first.
+///
+/// In dry-run mode the commits are only made locally, the user is sent what
+/// would have been submitted and `false` is returned.
+#[allow(clippy::too_many_arguments)]
+async fn create_icon(
+    bot: &LeonardoBot,
+    dialogue: AppIconDialogue,
+    config: &Arc<Config>,
+    notifier: &MaintainerNotifier,
+    metrics: &Metrics,
+    in_flight: &InFlight,
+    tracked: &TrackedMergeRequests,
+    limits: &SubmissionLimits,
+    git_lock: &GitLock,
+    lang: Language,
+    user_id: i64,
+    deadline: Deadline,
+    icons: Vec<BatchedIcon>,
+    target_branches: Vec<String>,
+    update_open_merge_requests: bool,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let work = in_flight.start(format!(
+        "submitting {} for {} in chat {}",
+        icon_names(&icons),
+        app_paths(&icons),
+        dialogue.chat_id()
+    ));
+
+    let icon_count = icons.len();
+    let result = try_create_icon(
+        bot,
+        dialogue,
+        config,
+        notifier,
+        metrics,
+        &work,
+        tracked,
+        git_lock,
+        lang,
+        deadline,
+        icons,
+        target_branches,
+        update_open_merge_requests,
+    )
+    .await;
+
+    match &result {
+        Ok(true) => {
+            metrics.submission_completed();
+            tracked.completed(icon_count);
+            limits.record(user_id);
+        }
+        Ok(false) => {}
+        Err(_) => metrics.submission_failed(),
+    }
+
+    result
+}
+
+/// Does the work of [`create_icon`]. Failures the user is offered a retry
+/// for are counted here, since they return `Ok(false)`.
+#[allow(clippy::too_many_arguments)]
+async fn try_create_icon(
+    bot: &LeonardoBot,
+    dialogue: AppIconDialogue,
+    config: &Arc<Config>,
+    notifier: &MaintainerNotifier,
+    metrics: &Metrics,
+    work: &Work<'_>,
+    tracked: &TrackedMergeRequests,
+    git_lock: &GitLock,
+    lang: Language,
+    deadline: Deadline,
+    icons: Vec<BatchedIcon>,
+    target_branches: Vec<String>,
+    update_open_merge_requests: bool,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let backend = config.submission_backend;
+
+    let guard = match tokio::time::timeout(GIT_LOCK_NOTICE_DELAY, git_lock.lock()).await {
+        Ok(guard) => guard,
+        Err(_) => {
+            bot.send_message(dialogue.chat_id(), lang.text(Text::Queued))
+                .await?;
+
+            git_lock.lock().await
+        }
+    };
+
+    let fetched: Result<(), Box<dyn Error + Send + Sync>> = async {
+        work.stage(Stage::Fetch);
+        deadline.check(Stage::Fetch)?;
+        backend.refresh(config, &target_branches).await
+    }
+    .await;
+    drop(guard);
+
+    if let Err(e) = fetched {
+        // Never commit on top of a stale checkout.
+        log::error!("Failed to fetch the overlay repository: {e}");
+        metrics.submission_failed();
+        notifier
+            .pipeline_failed(
+                bot,
+                dialogue.chat_id(),
+                Some(Stage::Fetch),
+                &app_paths(&icons),
+                Some(&icon_names(&icons)),
+                &e,
+            )
+            .await;
+
+        bot.send_message(
+            dialogue.chat_id(),
+            lang.text(Text::RefreshFailed {
+                error: &e.to_string(),
+            }),
+        )
+        .reply_markup(retry_keyboard(lang))
+        .await?;
+
+        dialogue
+            .update(State::RetryingCreation {
+                icons,
+                target_branches,
+            })
+            .await?;
+
+        return Ok(false);
+    }
+
+    let inputs = merge_request_inputs(
+        bot,
+        config,
+        dialogue.chat_id(),
+        &target_branches,
+        &icons,
+        true,
+    )
+    .await?;
+    let all_params = build_merge_requests(config, &icons, &target_branches, &inputs);
+    let MergeRequestInputs {
+        submitter,
+        previews,
+        ..
+    } = inputs;
+
+    if config.dry_run {
+        let mut summaries = Vec::with_capacity(all_params.len());
+
+        for (target_branch, params) in target_branches.iter().zip(&all_params) {
+            let icon = IconCommit {
+                target_branch: target_branch.clone(),
+                branch_name: params.source_branch.clone(),
+                icons: icons.iter().map(BatchedIcon::to_new_icon).collect(),
+                commit_msg: commit_message(&params.title, submitter.as_deref()),
+                force: false,
+            };
+
+            work.stage(Stage::Push);
+            let diffstat = backend
+                .push_icon(config, bot.inner().client(), icon)
+                .await?
+                .unwrap_or_default();
+            summaries.push(dry_run_summary(target_branch, params, &diffstat));
+        }
+
+        bot.send_message(dialogue.chat_id(), summaries.join("\n\n"))
+            .await?;
+        dialogue.exit().await?;
+
+        return Ok(false);
+    }
+
+    let mut remote_branches = Vec::with_capacity(all_params.len());
+    for params in &all_params {
+        remote_branches.push(remote_branch(bot, config, &params.source_branch).await?);
+    }
+
+    let open = remote_branches
+        .iter()
+        .filter_map(|remote| match remote {
+            RemoteBranch::Open(merge_request) => Some(merge_request.web_url.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if !open.is_empty() && !update_open_merge_requests {
+        let answers = InlineKeyboardMarkup::default().append_row(
+            [Button::UpdateIt, Button::NoAbort]
+                .into_iter()
+                .map(|answer| answer.callback(lang)),
+        );
+
+        bot.send_message(
+            dialogue.chat_id(),
+            lang.text(Text::AlreadyOpen {
+                urls: &open.join(" "),
+            }),
+        )
+        .reply_markup(answers)
+        .await?;
+
+        dialogue
+            .update(State::ConfirmingMergeRequestUpdate {
+                icons,
+                target_branches,
+                deadline: deadline.pause(),
+            })
+            .await?;
+
+        return Ok(false);
+    }
+
+    let mut merge_requests = Vec::with_capacity(target_branches.len());
+    let mut notes = Vec::new();
+
+    for (index, (target_branch, (params, remote))) in target_branches
+        .iter()
+        .zip(all_params.into_iter().zip(remote_branches))
+        .enumerate()
+    {
+        let branch_name = params.source_branch.clone();
+        let commit_msg = commit_message(&params.title, submitter.as_deref());
+        let force = !matches!(remote, RemoteBranch::Missing);
+        let updated = matches!(remote, RemoteBranch::Open(_));
+
+        let icon = IconCommit {
+            target_branch: target_branch.clone(),
+            branch_name: branch_name.clone(),
+            icons: icons.iter().map(BatchedIcon::to_new_icon).collect(),
+            commit_msg,
+            force,
+        };
+
+        let result: Result<MergeRequest, Box<dyn Error + Send + Sync>> = async {
+            // A running push is not interrupted so no half-updated branch is
+            // left behind on the remote.
+            work.stage(Stage::Push);
+            deadline.check(Stage::Push)?;
+            backend
+                .push_icon(config, bot.inner().client(), icon)
+                .await?;
+
+            match remote {
+                RemoteBranch::Open(merge_request) => {
+                    notes.push(lang.text(Text::UpdatedExisting {
+                        url: &merge_request.web_url,
+                    }));
+
+                    return Ok(merge_request);
+                }
+                RemoteBranch::Stale => notes.push(lang.text(Text::ReplacedStale {
+                    branch: &branch_name,
+                })),
+                RemoteBranch::Missing => {}
+            }
+
+            work.stage(Stage::MergeRequest);
+            open_merge_request(bot, config, deadline, &params).await
+        }
+        .await;
+
+        let merge_request = match result {
+            Ok(merge_request) => merge_request,
+            Err(e) if e.is::<DeadlineExceeded>() || e.is::<PushFailed>() => {
+                metrics.submission_failed();
+
+                let error = e.to_string();
+                let text = if e.is::<PushFailed>() {
+                    notifier
+                        .pipeline_failed(
+                            bot,
+                            dialogue.chat_id(),
+                            Some(Stage::Push),
+                            &app_paths(&icons),
+                            Some(&icon_names(&icons)),
+                            &e,
+                        )
+                        .await;
+
+                    lang.text(Text::SubmissionFailed { error: &error })
+                } else {
+                    count_exceeded_deadline(metrics, &*e);
+
+                    lang.text(Text::TooSlowTryAgain {
+                        error: &error,
+                        request: deadline.request_id(),
+                    })
+                };
+
+                // Keep the converted icon around so the remaining branches
+                // can be retried without going through the dialogue again.
+                bot.send_message(dialogue.chat_id(), text)
+                    .reply_markup(retry_keyboard(lang))
+                    .await?;
+
+                dialogue
+                    .update(State::RetryingCreation {
+                        icons,
+                        target_branches: target_branches[index..].to_vec(),
+                    })
+                    .await?;
+
+                return Ok(false);
+            }
+            Err(e) => return Err(e),
+        };
+
+        for icon in &icons {
+            post_audit_entry(
+                bot,
+                config.audit_chat_id,
+                AuditEntry {
+                    user: dialogue.chat_id(),
+                    package: &icon.app_path,
+                    icon_name: &icon.icon_name,
+                    target_branch,
+                    merge_request_url: &merge_request.web_url,
+                    updated,
+                },
+                vd_document(&icon.vd_bytes, &icon.icon_name),
+            )
+            .await;
+        }
+
+        // The tracking file is whitespace separated, so the packages are
+        // joined without spaces.
+        tracked.track(TrackedMergeRequest {
+            chat_id: dialogue.chat_id(),
+            iid: merge_request.iid,
+            target_branch: target_branch.clone(),
+            app_path: icons
+                .iter()
+                .map(|icon| icon.app_path.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+        });
+        merge_requests.push((target_branch, merge_request));
+    }
+
+    if !notes.is_empty() {
+        bot.send_message(dialogue.chat_id(), notes.join("\n"))
+            .await?;
+    }
+
+    // Link the merge requests for the same icons on different branches to
+    // each other so reviewers can handle them together.
+    if merge_requests.len() > 1 {
+        let description = merge_request_description(&icons, submitter.as_deref(), &previews);
+
+        for (target_branch, merge_request) in &merge_requests {
+            let params = MergeRequestUpdateParams {
+                description: cross_linked_description(
+                    &description,
+                    &merge_requests,
+                    merge_request.iid,
+                ),
+            };
+
+            let response = bot
+                .inner()
+                .client()
+                .put(format!(
+                    "https://gitlab.com/api/v4/projects/{}/merge_requests/{}",
+                    config.gitlab_project_id, merge_request.iid
+                ))
+                .header("PRIVATE-TOKEN", &config.gitlab_token)
+                .json(&params)
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                log::warn!(
+                    "Failed to link merge request !{} for {target_branch}: {}",
+                    merge_request.iid,
+                    response.status()
+                );
+            }
+        }
+    }
+
+    dialogue.exit().await?;
+
+    Ok(true)
+}
+
+/// `description` with links to the merge requests in `merge_requests`, by
+/// target branch, other than the one numbered `iid`.
+fn cross_linked_description(
+    description: &str,
+    merge_requests: &[(&String, MergeRequest)],
+    iid: u64,
+) -> String {
+    let related = merge_requests
+        .iter()
+        .filter(|(_, other)| other.iid != iid)
+        .map(|(other_branch, other)| format!("!{} ({other_branch})", other.iid))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!("{description}\n\nThis submission was also made for other branches: {related}")
+}
+
+/// Opens a merge request removing the icon of `app_path` from each of
+/// `target_branches`.
+#[allow(clippy::too_many_arguments)]
+async fn remove_icon(
+    bot: &LeonardoBot,
+    dialogue: &AppIconDialogue,
+    config: &Arc<Config>,
+    notifier: &MaintainerNotifier,
+    in_flight: &InFlight,
+    git_lock: &GitLock,
+    lang: Language,
+    app_path: &str,
+    icon_name: &str,
+    target_branches: &[String],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let backend = config.submission_backend;
+    let deadline = Deadline::after(config.submission_timeout);
+    let work = in_flight.start(format!(
+        "removing the icon of {app_path} in chat {}",
+        dialogue.chat_id()
+    ));
+
+    // Never commit on top of a stale checkout.
+    work.stage(Stage::Fetch);
+    let guard = git_lock.lock().await;
+    let refreshed = backend.refresh(config, target_branches).await;
+    drop(guard);
+    refreshed?;
+
+    let mut results = Vec::with_capacity(target_branches.len());
+    let submitter = username(bot, dialogue.chat_id()).await;
+
+    for target_branch in target_branches {
+        let params = build_removal_merge_request(config, icon_name, app_path, target_branch);
+        let remote = remote_branch(bot, config, &params.source_branch).await?;
+
+        if let RemoteBranch::Open(merge_request) = remote {
+            results.push(lang.text(Text::RemovalAlreadyOpen {
+                branch: target_branch,
+                url: &merge_request.web_url,
+            }));
+
+            continue;
+        }
+
+        let removal = IconRemoval {
+            target_branch: target_branch.clone(),
+            branch_name: params.source_branch.clone(),
+            app_path: app_path.to_owned(),
+            commit_msg: commit_message(&params.title, submitter.as_deref()),
+            force: matches!(remote, RemoteBranch::Stale),
+        };
+
+        work.stage(Stage::Push);
+        let diffstat = match backend
+            .push_removal(config, bot.inner().client(), removal)
+            .await
+        {
+            Ok(diffstat) => diffstat,
+            Err(e) if !e.is::<PushFailed>() => return Err(e),
+            Err(e) => {
+                notifier
+                    .pipeline_failed(
+                        bot,
+                        dialogue.chat_id(),
+                        Some(Stage::Push),
+                        app_path,
+                        Some(icon_name),
+                        &e,
+                    )
+                    .await;
+
+                results.push(lang.text(Text::RemovalFailed {
+                    branch: target_branch,
+                    error: &e.to_string(),
+                }));
+
+                break;
+            }
+        };
+
+        if let Some(diffstat) = diffstat {
+            results.push(dry_run_summary(target_branch, &params, &diffstat));
+
+            continue;
+        }
+
+        work.stage(Stage::MergeRequest);
+        let merge_request = open_merge_request(bot, config, deadline, &params).await?;
+        results.push(lang.text(Text::RemovalOpened {
+            url: &merge_request.web_url,
+            branch: target_branch,
+        }));
+    }
+
+    bot.send_message(dialogue.chat_id(), results.join("\n"))
+        .await?;
+
+    dialogue.exit().await?;
+
+    Ok(())
+}
+
+/// Describes the merge request a dry run would have opened for
+/// `target_branch` and the changes it would have pushed.
+fn dry_run_summary(target_branch: &str, params: &MergeRequestParams, diffstat: &str) -> String {
+    format!(
+        "[dry run] no merge request was created for {target_branch}. It would have been:\n\n{}\n\n{}\n\n{}",
+        params.title,
+        params.description,
+        diffstat.trim_end()
+    )
+}
+
+/// Opens the merge request described by `params`.
+async fn open_merge_request(
+    bot: &LeonardoBot,
+    config: &Config,
+    deadline: Deadline,
+    params: &MergeRequestParams,
+) -> Result<MergeRequest, Box<dyn Error + Send + Sync>> {
+    let request = with_retry(
+        "Creating a merge request",
+        DEFAULT_ATTEMPTS,
+        DEFAULT_BASE_DELAY,
+        || async {
+            let response = bot
+                .inner()
+                .client()
+                .post(format!(
+                    "https://gitlab.com/api/v4/projects/{}/merge_requests",
+                    config.gitlab_project_id
+                ))
+                .header("PRIVATE-TOKEN", &config.gitlab_token)
+                .json(params)
+                .send()
+                .await?;
+
+            gitlab_error_for_status(response).await
+        },
+    );
+
+    let response = deadline.run(Stage::MergeRequest, request).await?;
+
+    Ok(response.json::<MergeRequest>().await?)
+}
+
+/// Looks up whether `branch_name` already exists on GitLab and has an open
+/// merge request.
+async fn remote_branch(
+    bot: &LeonardoBot,
+    config: &Config,
+    branch_name: &str,
+) -> Result<RemoteBranch, Box<dyn Error + Send + Sync>> {
+    let response = bot
+        .inner()
+        .client()
+        .get(format!(
+            "https://gitlab.com/api/v4/projects/{}/repository/branches/{}",
+            config.gitlab_project_id,
+            branch_name.replace('/', "%2F")
+        ))
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(RemoteBranch::Missing);
+    }
+    gitlab_error_for_status(response).await?;
+
+    let response = bot
+        .inner()
+        .client()
+        .get(format!(
+            "https://gitlab.com/api/v4/projects/{}/merge_requests",
+            config.gitlab_project_id
+        ))
+        .query(&[("state", "opened"), ("source_branch", branch_name)])
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .send()
+        .await?;
+    let open = gitlab_error_for_status(response)
+        .await?
+        .json::<Vec<MergeRequest>>()
+        .await?;
+
+    Ok(match open.into_iter().next() {
+        Some(merge_request) => RemoteBranch::Open(merge_request),
+        None => RemoteBranch::Stale,
+    })
+}
+
+/// Everything besides the icons the merge requests of a submission are
+/// built from. The Preview MR button and the submission both gather it with
+/// [`merge_request_inputs`], so the preview shows what is submitted.
+struct MergeRequestInputs {
+    submitter: Option<String>,
+    previews: Vec<Option<String>>,
+    /// Whether each target branch already has an icon for the apps.
+    updates: Vec<bool>,
+}
+
+/// Gathers the [`MergeRequestInputs`] of `icons`. Unless `upload` is set, the
+/// previews are not uploaded but replaced by [`preview_placeholders`].
+async fn merge_request_inputs(
+    bot: &LeonardoBot,
+    config: &Arc<Config>,
+    chat_id: ChatId,
+    target_branches: &[String],
+    icons: &[BatchedIcon],
+    upload: bool,
+) -> Result<MergeRequestInputs, Box<dyn Error + Send + Sync>> {
+    let updates = updates_existing(bot, config, target_branches, icons).await?;
+    let submitter = username(bot, chat_id).await;
+    // Nothing is uploaded in a dry run, the description only shows the text.
+    let previews = if config.dry_run {
+        Vec::new()
+    } else if upload {
+        upload_previews(bot, config, icons).await
+    } else {
+        preview_placeholders(icons)
+    };
+
+    Ok(MergeRequestInputs {
+        submitter,
+        previews,
+        updates,
+    })
+}
+
+/// Builds the merge request for each of `target_branches`, in the same order.
+fn build_merge_requests(
+    config: &Config,
+    icons: &[BatchedIcon],
+    target_branches: &[String],
+    inputs: &MergeRequestInputs,
+) -> Vec<MergeRequestParams> {
+    target_branches
+        .iter()
+        .zip(&inputs.updates)
+        .map(|(target_branch, update)| {
+            build_merge_request(
+                config,
+                icons,
+                inputs.submitter.as_deref(),
+                &inputs.previews,
+                target_branch,
+                *update,
+            )
+        })
+        .collect()
+}
+
+/// Shows `params` in a MarkdownV2 code block, for the Preview MR button.
+fn merge_request_preview(params: &MergeRequestParams) -> String {
+    format!(
+        "```\nBranch: {} -> {}\nTitle: {}\n\n{}\n```",
+        escape_code(&params.source_branch),
+        escape_code(&params.target_branch),
+        escape_code(&params.title),
+        escape_code(&params.description)
+    )
+}
+
+/// Builds the merge request for adding `icons` to `target_branch`, or
+/// updating them if `update` is set. Its title doubles as the commit
+/// message.
+fn build_merge_request(
+    config: &Config,
+    icons: &[BatchedIcon],
+    submitter: Option<&str>,
+    previews: &[Option<String>],
+    target_branch: &str,
+    update: bool,
+) -> MergeRequestParams {
+    let verb = if update { "Update" } else { "Add" };
+
+    let (source_branch, title) = match icons {
+        [icon] => (
+            source_branch(config, "icon", &icon.icon_name, target_branch),
+            format!("overlay: {verb} icon for {}", icon.icon_name),
+        ),
+        _ => (
+            source_branch(
+                config,
+                "icons",
+                &format!("{}_and_{}_more", icons[0].icon_name, icons.len() - 1),
+                target_branch,
+            ),
+            format!("overlay: {verb} icons for {}", icon_names(icons)),
+        ),
+    };
+
+    MergeRequestParams {
+        id: config.gitlab_project_id,
+        source_branch,
+        target_branch: target_branch.to_owned(),
+        remove_source_branch: true,
+        title,
+        description: merge_request_description(icons, submitter, previews),
+        labels: config.gitlab_mr_labels.clone(),
+        assignee_id: config.gitlab_mr_assignee_id,
+        reviewer_ids: config.gitlab_mr_reviewer_ids.clone(),
+    }
+}
+
+/// The commit message for a merge request titled `title`, crediting the
+/// submitter in a trailer if they have a Telegram username.
+fn commit_message(title: &str, submitter: Option<&str>) -> String {
+    match submitter {
+        Some(username) => format!("{title}\n\nSuggested-by: @{username} (Telegram)"),
+        None => title.to_owned(),
+    }
+}
+
+/// The section of a merge request description about `icon`: its uploaded
+/// `preview`, what is known about the app and the submitter, followed by
+/// the description the user gave.
+fn describe_icon(icon: &BatchedIcon, submitter: Option<&str>, preview: Option<&str>) -> String {
+    let mut lines = vec![
+        format!("- Package: `{}`", icon.app_path),
+        format!("- Icon: `{}`", icon.icon_name),
+    ];
+
+    if let Some(details) = &icon.app {
+        let name = details.title.as_deref().unwrap_or(&icon.app_path);
+
+        lines.push(format!(
+            "- App: [{name}]({})",
+            details.store.page_url(&icon.app_path)
+        ));
+    }
+    if let Some(submitter) = submitter {
+        lines.push(format!("- Submitted by: @{submitter} (Telegram)"));
+    }
+
+    let details = lines.join("\n");
+
+    match preview {
+        Some(preview) => format!("{preview}\n\n{details}\n\n{}", icon.description),
+        None => format!("{details}\n\n{}", icon.description),
+    }
+}
+
+/// The description of the merge request for `icons`, with the uploaded
+/// `previews` in the same order. Ends with their packages so pending
+/// submissions for them can be found, see [`icon_status`].
+fn merge_request_description(
+    icons: &[BatchedIcon],
+    submitter: Option<&str>,
+    previews: &[Option<String>],
+) -> String {
+    let trailers = icons
+        .iter()
+        .map(|icon| format!("{PACKAGE_TRAILER}{}", icon.app_path))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let sections = icons
+        .iter()
+        .enumerate()
+        .map(|(i, icon)| {
+            let preview = previews.get(i).and_then(Option::as_deref);
+
+            (icon, describe_icon(icon, submitter, preview))
+        })
+        .collect::<Vec<_>>();
+
+    match sections.as_slice() {
+        [(_, section)] => format!("{section}\n\n{trailers}"),
+        _ => {
+            let sections = sections
+                .iter()
+                .map(|(icon, section)| {
+                    format!("## {} ({})\n\n{section}", icon.icon_name, icon.app_path)
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n");
+
+            format!("Submits {} icons:\n\n{sections}\n\n{trailers}", icons.len())
+        }
+    }
+}
+
+/// Renders a preview of each of `icons` and uploads it to the project, so it
+/// can be shown in the merge request. Returns the Markdown embedding each
+/// preview, or `None` for icons without an SVG or whose upload failed, which
+/// never stops the submission.
+async fn upload_previews(
+    bot: &LeonardoBot,
+    config: &Config,
+    icons: &[BatchedIcon],
+) -> Vec<Option<String>> {
+    let mut previews = Vec::with_capacity(icons.len());
+
+    for icon in icons {
+        let preview = match &icon.svg {
+            Some(svg) => match upload_preview(bot, config, &icon.icon_name, svg).await {
+                Ok(markdown) => Some(markdown),
+                Err(e) => {
+                    log::warn!("Failed to upload the preview of {}: {e}", icon.icon_name);
+
+                    None
+                }
+            },
+            None => None,
+        };
+        previews.push(preview);
+    }
+
+    previews
+}
+
+/// Stands in for the previews [`upload_previews`] would upload for `icons`,
+/// so they can be shown without uploading anything.
+fn preview_placeholders(icons: &[BatchedIcon]) -> Vec<Option<String>> {
+    icons
+        .iter()
+        .map(|icon| {
+            icon.svg
+                .as_ref()
+                .map(|_| format!("![{0}]({0}.png)", icon.icon_name))
+        })
+        .collect()
+}
+
+/// Uploads a PNG rendering of `svg` and returns the Markdown embedding it.
+async fn upload_preview(
+    bot: &LeonardoBot,
+    config: &Config,
+    icon_name: &str,
+    svg: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let svg_bytes = svg.as_bytes().to_vec();
+    let png = tokio::task::spawn_blocking(move || render_png(&svg_bytes, PREVIEW_SIZE)).await??;
+
+    let file = Part::bytes(png)
+        .file_name(format!("{icon_name}.png"))
+        .mime_str("image/png")?;
+    let response = bot
+        .inner()
+        .client()
+        .post(format!(
+            "https://gitlab.com/api/v4/projects/{}/uploads",
+            config.gitlab_project_id
+        ))
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .multipart(Form::new().part("file", file))
+        .send()
+        .await?;
+
+    Ok(gitlab_error_for_status(response)
+        .await?
+        .json::<Upload>()
+        .await?
+        .markdown)
+}
+
+/// Builds the merge request for removing the icon of `app_path` from
+/// `target_branch`.
+fn build_removal_merge_request(
+    config: &Config,
+    icon_name: &str,
+    app_path: &str,
+    target_branch: &str,
+) -> MergeRequestParams {
+    MergeRequestParams {
+        id: config.gitlab_project_id,
+        source_branch: source_branch(config, "remove_icon", icon_name, target_branch),
+        target_branch: target_branch.to_owned(),
+        remove_source_branch: true,
+        title: format!("overlay: Remove icon for {icon_name}"),
+        description: format!("Removes the icon of {app_path} from the icon map."),
+        labels: config.gitlab_mr_labels.clone(),
+        assignee_id: config.gitlab_mr_assignee_id,
+        reviewer_ids: config.gitlab_mr_reviewer_ids.clone(),
+    }
+}
+
+/// Whether `branch` is the source branch of an icon submission, see
+/// [`source_branch`].
+fn is_icon_branch(branch: &str) -> bool {
+    branch.starts_with("bot/icon_") || branch.starts_with("bot/icons_")
+}
+
+/// The branch of a merge request for `icon_name`, `kind` tells apart
+/// additions and removals of the same icon.
+fn source_branch(config: &Config, kind: &str, icon_name: &str, target_branch: &str) -> String {
+    if target_branch == config.default_overlay_branch() {
+        format!("bot/{kind}_{icon_name}")
+    } else {
+        format!("bot/{kind}_{icon_name}-{target_branch}")
+    }
+}
+
+/// Reads the app path from `input`, which is either the package name or a
+/// store link with the package in its `id` parameter, like
+/// `https://play.google.com/store/apps/details?id=com.discord`. Returns
+/// `None` if it isn't a valid package name.
+fn parse_app_path(input: &str) -> Option<String> {
+    let input = input.trim();
+
+    let app_path = match reqwest::Url::parse(input) {
+        Ok(url) if url.has_host() || url.scheme() == "market" => url
+            .query_pairs()
+            .find(|(key, _)| key == "id")
+            .map(|(_, id)| id.into_owned())?,
+        _ => input.to_owned(),
+    };
+
+    Some(app_path).filter(|app_path| is_valid_app_path(app_path))
+}
+
+/// Whether `app_path` looks like an Android package name, i.e. at least two
+/// dot separated parts of letters, digits and underscores that each start
+/// with a letter.
+fn is_valid_app_path(app_path: &str) -> bool {
+    app_path.contains('.')
+        && app_path.split('.').all(|part| {
+            part.starts_with(|c: char| c.is_ascii_alphabetic())
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+/// Looks up the icon `app_path` has on each of `target_branches` and the
+/// submissions for it that are still open.
+async fn icon_status(
+    bot: &LeonardoBot,
+    config: &Arc<Config>,
+    target_branches: &[String],
+    app_path: &str,
+) -> Result<IconStatus, Box<dyn Error + Send + Sync>> {
+    let drawables = mapped_drawables(bot, config, target_branches, app_path).await?;
+
+    let response = bot
+        .inner()
+        .client()
+        .get(format!(
+            "https://gitlab.com/api/v4/projects/{}/merge_requests",
+            config.gitlab_project_id
+        ))
+        .query(&[
+            ("state", "opened"),
+            ("in", "description"),
+            ("search", app_path),
+        ])
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .send()
+        .await?;
+    let trailer = format!("{PACKAGE_TRAILER}{app_path}");
+    let pending = gitlab_error_for_status(response)
+        .await?
+        .json::<Vec<MergeRequest>>()
+        .await?
+        .into_iter()
+        // The search also matches other packages that contain this one.
+        .filter(|merge_request| {
+            is_icon_branch(&merge_request.source_branch)
+                && merge_request
+                    .description
+                    .as_deref()
+                    .map_or(false, |description| {
+                        description.lines().any(|line| line == trailer)
+                    })
+        })
+        .collect();
+
+    Ok(IconStatus { drawables, pending })
+}
+
+/// Tells the user about the open submissions in `pending`, if any.
+fn pending_note(lang: Language, pending: &[MergeRequest]) -> Option<String> {
+    if pending.is_empty() {
+        return None;
+    }
+
+    let merge_requests = pending
+        .iter()
+        .map(|merge_request| format!("!{} {}", merge_request.iid, merge_request.web_url))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Some(lang.text(Text::PendingReview {
+        merge_requests: &merge_requests,
+    }))
+}
+
+/// Whether each of `target_branches` already maps every app in `icons` to an
+/// icon, in which case its merge request updates them.
+async fn updates_existing(
+    bot: &LeonardoBot,
+    config: &Arc<Config>,
+    target_branches: &[String],
+    icons: &[BatchedIcon],
+) -> Result<Vec<bool>, Box<dyn Error + Send + Sync>> {
+    let mut updates = vec![true; target_branches.len()];
+
+    for icon in icons {
+        let drawables = mapped_drawables(bot, config, target_branches, &icon.app_path).await?;
+
+        for (update, drawable) in updates.iter_mut().zip(drawables) {
+            *update &= drawable.is_some();
+        }
+    }
+
+    Ok(updates)
+}
+
+/// Returns the drawable `package` is mapped to on each of `target_branches`,
+/// without the `@drawable/` prefix.
+async fn mapped_drawables(
+    bot: &LeonardoBot,
+    config: &Arc<Config>,
+    target_branches: &[String],
+    package: &str,
+) -> Result<Vec<Option<String>>, Box<dyn Error + Send + Sync>> {
+    config
+        .submission_backend
+        .mapped_drawables(config, bot.inner().client(), target_branches, package)
+        .await
+}
+
+/// Starts the dialogue of a new icon submission in `chat_id`.
+#[allow(clippy::too_many_arguments)]
+async fn start_submission(
+    bot: &LeonardoBot,
+    dialogue: &AppIconDialogue,
+    config: &Config,
+    limits: &SubmissionLimits,
+    access: &AccessList,
+    tools: &Tools,
+    metrics: &Metrics,
+    lang: Language,
+    chat_id: ChatId,
+    user_id: i64,
+) -> Result<(), BotError> {
+    if matches!(dialogue.get().await?, Some(State::AwaitingReview)) {
+        bot.send_message(chat_id, lang.text(Text::StillAwaitingReview))
+            .await?;
+
+        return Ok(());
+    }
+
+    if submission_refused(bot, limits, access, lang, chat_id, user_id).await? {
+        return Ok(());
+    }
+
+    if !tools.icon_submissions_available() {
+        bot.send_message(chat_id, lang.text(Text::SubmissionsUnavailable))
+            .await?;
+
+        return Ok(());
+    }
+
+    let mut branches = config.overlay_branches.clone();
+
+    if branches.len() > 1 {
+        let all = if branches.len() == 2 {
+            Text::BothBranches
+        } else {
+            Text::AllBranches
+        };
+        let answers = InlineKeyboardMarkup::default().append_row(
+            branches
+                .into_iter()
+                .map(|branch| {
+                    InlineKeyboardButton::callback(
+                        lang.text(Text::BranchButton {
+                            label: &branch.label,
+                        }),
+                        branch.name,
+                    )
+                })
+                .chain([InlineKeyboardButton::callback(
+                    lang.text(all),
+                    String::from("all"),
+                )]),
+        );
+
+        bot.send_message(chat_id, lang.text(Text::StartWithBranch))
+            .reply_markup(answers)
+            .await?;
+
+        dialogue.update(State::ReceiveTargetBranch).await?;
+    } else {
+        bot.send_message(chat_id, lang.text(Text::StartWithAppPath))
+            .await?;
+
+        dialogue
+            .update(State::ReceiveAppPath {
+                target_branches: vec![branches.remove(0).name],
+                batch: Vec::new(),
+            })
+            .await?;
+    }
+
+    metrics.submission_started();
+
+    Ok(())
+}
+
+fn sent_by_admin(message: &Message, access: &AccessList) -> bool {
+    message
+        .from()
+        .map_or(false, |user| access.is_admin(user.id))
+}
+
+/// Bans or unbans the user with the id `user_id`, or the sender of the
+/// message that is replied to if no id is given.
+async fn change_ban(
+    bot: &LeonardoBot,
+    message: &Message,
+    access: &AccessList,
+    lang: Language,
+    user_id: &str,
+    ban: bool,
+) -> Result<(), BotError> {
+    if !sent_by_admin(message, access) {
+        bot.send_message(message.chat.id, lang.text(Text::AdminsOnly))
+            .await?;
+
+        return Ok(());
+    }
+
+    let user_id = match user_id.trim().parse::<i64>().ok().or_else(|| {
+        message
+            .reply_to_message()
+            .and_then(Message::from)
+            .map(|user| user.id)
+    }) {
+        Some(user_id) => user_id,
+        None => {
+            bot.send_message(message.chat.id, lang.text(Text::BanUsage))
+                .await?;
+
+            return Ok(());
+        }
+    };
+
+    let changed = if ban {
+        access.ban(user_id)
+    } else {
+        access.unban(user_id)
+    };
+    let text = match (ban, changed) {
+        (true, true) => Text::UserBanned { user_id },
+        (true, false) => Text::UserAlreadyBanned { user_id },
+        (false, true) => Text::UserUnbanned { user_id },
+        (false, false) => Text::UserNotBanned { user_id },
+    };
+
+    bot.send_message(message.chat.id, lang.text(text)).await?;
+
+    Ok(())
+}
+
+/// Points to the private chat with the bot, where the submission dialogue
+/// doesn't mix up the answers of several users.
+async fn send_private_chat_link(
+    bot: &LeonardoBot,
+    lang: Language,
+    chat_id: ChatId,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let me = bot.get_me().await?;
+    let url = reqwest::Url::parse(&format!("https://t.me/{}?start=addicon", me.username()))?;
+
+    bot.send_message(chat_id, lang.text(Text::PrivateChatLink))
+        .reply_markup(
+            InlineKeyboardMarkup::default().append_row([InlineKeyboardButton::url(
+                lang.text(Text::SubmitIconButton),
+                url,
+            )]),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Tells the user in `chat_id` why they can't submit if `user_id` is banned,
+/// not allowlisted or reached their daily submission limit. Returns whether
+/// it can't.
+async fn submission_refused(
+    bot: &LeonardoBot,
+    limits: &SubmissionLimits,
+    access: &AccessList,
+    lang: Language,
+    chat_id: ChatId,
+    user_id: i64,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    if let Some(refusal) = access.refusal(user_id) {
+        bot.send_message(chat_id, lang.text(refusal)).await?;
+
+        return Ok(true);
+    }
+
+    let blocked_until = match limits.blocked_until(user_id) {
+        Some(blocked_until) => blocked_until,
+        None => return Ok(false),
+    };
+    let format = time::format_description::parse("[year]-[month]-[day] [hour]:[minute] UTC")?;
+
+    bot.send_message(
+        chat_id,
+        lang.text(Text::DailyLimit {
+            max: limits.max_per_day(),
+            until: &blocked_until.format(&format)?,
+        }),
+    )
+    .await?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::OverlayBranch,
+        store::{AppDetails, Store},
+    };
+
+    fn batched_icon(app_path: &str, icon_name: &str) -> BatchedIcon {
+        BatchedIcon {
+            app_path: app_path.to_owned(),
+            icon_name: icon_name.to_owned(),
+            app: None,
+            description: String::from("A flat icon"),
+            vd_bytes: b"<vector />".to_vec(),
+            svg: Some(String::from("<svg />")),
+        }
+    }
+
+    /// Reverses [`escape_code`].
+    fn unescape_code(text: &str) -> String {
+        let mut unescaped = String::with_capacity(text.len());
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => unescaped.extend(chars.next()),
+                c => unescaped.push(c),
+            }
+        }
+
+        unescaped
+    }
+
+    fn two_branches() -> Config {
+        let mut config = Config::for_tests();
+        config.overlay_branches = ["12.1", "13"]
+            .into_iter()
+            .map(|name| OverlayBranch {
+                name: name.to_owned(),
+                label: format!("Android {name}"),
+            })
+            .collect();
+
+        config
+    }
+
+    fn merge_request(iid: u64, source_branch: &str) -> MergeRequest {
+        MergeRequest {
+            iid,
+            web_url: format!("https://gitlab.com/example/-/merge_requests/{iid}"),
+            source_branch: source_branch.to_owned(),
+            description: None,
+            state: String::from("opened"),
+        }
+    }
+
+    #[test]
+    fn both_selects_every_branch() {
+        let config = two_branches();
+
+        assert_eq!(selected_branches(&config, "all"), ["12.1", "13"]);
+        assert_eq!(selected_branches(&config, "13"), ["13"]);
+        assert!(selected_branches(&config, "14").is_empty());
+    }
+
+    #[test]
+    fn both_fans_out_into_a_merge_request_per_branch() {
+        let config = two_branches();
+        let icons = vec![batched_icon("org.example.app", "example")];
+        let target_branches = selected_branches(&config, "all");
+        let inputs = MergeRequestInputs {
+            submitter: None,
+            previews: Vec::new(),
+            // Only the 13 branch already has an icon for the app.
+            updates: vec![false, true],
+        };
+
+        let all_params = build_merge_requests(&config, &icons, &target_branches, &inputs);
+
+        let branches = all_params
+            .iter()
+            .map(|params| {
+                (
+                    params.source_branch.as_str(),
+                    params.target_branch.as_str(),
+                    params.title.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            branches,
+            [
+                ("bot/icon_example", "12.1", "overlay: Add icon for example"),
+                (
+                    "bot/icon_example-13",
+                    "13",
+                    "overlay: Update icon for example"
+                ),
+            ]
+        );
+        assert_eq!(all_params[0].description, all_params[1].description);
+    }
+
+    #[test]
+    fn merge_requests_of_both_branches_link_each_other() {
+        let branches = [String::from("12.1"), String::from("13")];
+        let merge_requests = vec![
+            (&branches[0], merge_request(7, "bot/icon_example")),
+            (&branches[1], merge_request(8, "bot/icon_example-13")),
+        ];
+
+        assert_eq!(
+            cross_linked_description("Description", &merge_requests, 7),
+            "Description\n\nThis submission was also made for other branches: !8 (13)"
+        );
+        assert_eq!(
+            cross_linked_description("Description", &merge_requests, 8),
+            "Description\n\nThis submission was also made for other branches: !7 (12.1)"
+        );
+    }
+
+    #[test]
+    fn preview_shows_the_submitted_merge_requests_byte_for_byte() {
+        let config = Config::for_tests();
+        let icons = vec![BatchedIcon {
+            app: Some(AppDetails {
+                store: Store::FDroid,
+                title: Some(String::from("Example")),
+            }),
+            description: String::from("Uses `backticks` and a \\ backslash"),
+            ..batched_icon("org.example.app", "example")
+        }];
+        let target_branches = vec![String::from("12.1"), String::from("13")];
+        let inputs = MergeRequestInputs {
+            submitter: Some(String::from("someone")),
+            previews: vec![Some(String::from("![example](/uploads/1234/example.png)"))],
+            updates: vec![false, true],
+        };
+
+        let submitted = build_merge_requests(&config, &icons, &target_branches, &inputs);
+
+        let expected_description = "![example](/uploads/1234/example.png)\n\
+            \n\
+            - Package: `org.example.app`\n\
+            - Icon: `example`\n\
+            - App: [Example](https://f-droid.org/packages/org.example.app/)\n\
+            - Submitted by: @someone (Telegram)\n\
+            \n\
+            Uses `backticks` and a \\ backslash\n\
+            \n\
+            Package: org.example.app";
+        let expected = [
+            ("bot/icon_example", "12.1", "overlay: Add icon for example"),
+            (
+                "bot/icon_example-13",
+                "13",
+                "overlay: Update icon for example",
+            ),
+        ];
+
+        assert_eq!(submitted.len(), expected.len());
+        for (params, (source_branch, target_branch, title)) in submitted.iter().zip(expected) {
+            assert_eq!(params.source_branch, source_branch);
+            assert_eq!(params.target_branch, target_branch);
+            assert_eq!(params.title, title);
+            assert_eq!(
+                params.description.as_bytes(),
+                expected_description.as_bytes()
+            );
+
+            let preview = unescape_code(&merge_request_preview(params));
+            let shown = format!(
+                "```\nBranch: {source_branch} -> {target_branch}\nTitle: {title}\n\n{expected_description}\n```"
+            );
+            assert_eq!(preview.as_bytes(), shown.as_bytes());
+        }
+    }
+
+    #[test]
+    fn android_package_names_are_valid_app_paths() {
+        for app_path in ["com.discord", "org.example.app_2", "a.b", "Com.Discord"] {
+            assert!(is_valid_app_path(app_path), "{app_path}");
+        }
+    }
+
+    #[test]
+    fn malformed_package_names_are_invalid_app_paths() {
+        for app_path in [
+            "",
+            "discord",
+            "com.discord!",
+            "Com Discord",
+            "com.discord.",
+            ".com.discord",
+            "com..discord",
+            "com.1discord",
+            "com._discord",
+            "com.dïscord",
+        ] {
+            assert!(!is_valid_app_path(app_path), "{app_path}");
+        }
+    }
+
+    #[test]
+    fn app_paths_are_parsed_from_package_names_and_store_urls() {
+        for (input, app_path) in [
+            ("com.discord", "com.discord"),
+            ("  com.discord\n", "com.discord"),
+            (
+                "https://play.google.com/store/apps/details?id=com.discord",
+                "com.discord",
+            ),
+            (
+                "https://play.google.com/store/apps/details?hl=de&id=com.discord&gl=DE",
+                "com.discord",
+            ),
+            ("market://details?id=com.discord", "com.discord"),
+        ] {
+            assert_eq!(parse_app_path(input).as_deref(), Some(app_path), "{input}");
+        }
+    }
+
+    #[test]
+    fn unusable_app_paths_are_not_parsed() {
+        for input in [
+            "com.discord.",
+            "com.discord!",
+            "https://play.google.com/store/apps/details",
+            "https://play.google.com/store/apps/details?id=",
+            "https://play.google.com/store/apps/details?id=discord",
+            "https://play.google.com/store/apps/details?id=com.discord!",
+        ] {
+            assert_eq!(parse_app_path(input), None, "{input}");
+        }
+    }
+
+    fn document(file_name: &str, mime_type: &str) -> Document {
+        serde_json::from_value(serde_json::json!({
+            "file_id": "document",
+            "file_unique_id": "document",
+            "file_name": file_name,
+            "mime_type": mime_type,
+            "file_size": 1024,
+        }))
+        .unwrap()
+    }
+
+    fn sticker(is_animated: bool, is_video: bool) -> Sticker {
+        serde_json::from_value(serde_json::json!({
+            "file_id": "sticker",
+            "file_unique_id": "sticker",
+            "width": 512,
+            "height": 512,
+            "is_animated": is_animated,
+            "is_video": is_video,
+            "file_size": 1024,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn documents_are_icon_files() {
+        assert_eq!(
+            icon_file(Some(&document("icon.png", "image/png")), None).ok(),
+            Some((String::from("document"), false))
+        );
+        assert_eq!(
+            icon_file(Some(&document("icon.svg", "image/svg+xml")), None).ok(),
+            Some((String::from("document"), true))
+        );
+    }
+
+    #[test]
+    fn static_stickers_are_icon_files() {
+        assert_eq!(
+            icon_file(None, Some(&sticker(false, false))).ok(),
+            Some((String::from("sticker"), false))
+        );
+    }
+
+    #[test]
+    fn animated_and_video_stickers_are_refused() {
+        for sticker in [sticker(true, false), sticker(false, true)] {
+            assert!(matches!(
+                icon_file(None, Some(&sticker)),
+                Err(Text::AnimatedSticker)
+            ));
+        }
+    }
+
+    #[test]
+    fn nothing_attached_asks_for_an_image() {
+        assert!(matches!(icon_file(None, None), Err(Text::AttachImage)));
+    }
+
+    #[test]
+    fn single_icon_description_has_no_sections() {
+        let icon = BatchedIcon {
+            app: Some(AppDetails {
+                store: Store::PlayStore,
+                title: Some(String::from("Discord")),
+            }),
+            ..batched_icon("com.discord", "discord")
+        };
+        let preview = String::from("![discord](/uploads/0123/discord.png)");
+
+        assert_eq!(
+            merge_request_description(&[icon], Some("leonardo"), &[Some(preview)]),
+            "![discord](/uploads/0123/discord.png)\n\n\
+             - Package: `com.discord`\n\
+             - Icon: `discord`\n\
+             - App: [Discord](https://play.google.com/store/apps/details?id=com.discord)\n\
+             - Submitted by: @leonardo (Telegram)\n\n\
+             A flat icon\n\n\
+             Package: com.discord"
+        );
+    }
+
+    #[test]
+    fn several_icons_get_a_section_each() {
+        let icons = [
+            batched_icon("com.discord", "discord"),
+            BatchedIcon {
+                app: Some(AppDetails {
+                    store: Store::FDroid,
+                    title: None,
+                }),
+                description: String::from("The F-Droid logo"),
+                ..batched_icon("org.fdroid.fdroid", "fdroid")
+            },
+        ];
+        // The upload of the first preview failed.
+        let previews = [
+            None,
+            Some(String::from("![fdroid](/uploads/4567/fdroid.png)")),
+        ];
+
+        assert_eq!(
+            merge_request_description(&icons, None, &previews),
+            "Submits 2 icons:\n\n\
+             ## discord (com.discord)\n\n\
+             - Package: `com.discord`\n\
+             - Icon: `discord`\n\n\
+             A flat icon\n\n\
+             ## fdroid (org.fdroid.fdroid)\n\n\
+             ![fdroid](/uploads/4567/fdroid.png)\n\n\
+             - Package: `org.fdroid.fdroid`\n\
+             - Icon: `fdroid`\n\
+             - App: [org.fdroid.fdroid](https://f-droid.org/packages/org.fdroid.fdroid/)\n\n\
+             The F-Droid logo\n\n\
+             Package: com.discord\n\
+             Package: org.fdroid.fdroid"
+        );
+    }
+
+    #[test]
+    fn placeholders_stand_in_for_previews_of_icons_with_an_svg() {
+        let icons = [
+            batched_icon("com.discord", "discord"),
+            BatchedIcon {
+                svg: None,
+                ..batched_icon("org.fdroid.fdroid", "fdroid")
+            },
+        ];
+
+        assert_eq!(
+            preview_placeholders(&icons),
+            [Some(String::from("![discord](discord.png)")), None]
+        );
+    }
+
+    #[tokio::test]
+    async fn names_are_taken_by_other_apps_on_any_target_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Arc::new(overlay::two_branch_overlay(dir.path()));
+        let bot = Bot::new("token").auto_send();
+        let both = [String::from("13"), String::from("12.1")];
+
+        let taken = name_taken(
+            &bot,
+            &config,
+            Language::English,
+            &both,
+            "org.example.other",
+            "leonardo",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            taken.as_deref(),
+            Some(
+                "The name leonardo is already taken by the icon for org.example.leonardo on the 12.1 branch."
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn names_are_free_for_their_own_app_and_on_other_branches() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Arc::new(overlay::two_branch_overlay(dir.path()));
+        let bot = Bot::new("token").auto_send();
+
+        let own = name_taken(
+            &bot,
+            &config,
+            Language::English,
+            &[String::from("12.1"), String::from("13")],
+            "org.example.leonardo",
+            "leonardo",
+        )
+        .await
+        .unwrap();
+        let other_branch = name_taken(
+            &bot,
+            &config,
+            Language::English,
+            &[String::from("13")],
+            "org.example.other",
+            "leonardo",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(own, None);
+        assert_eq!(other_branch, None);
+    }
 }
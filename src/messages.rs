@@ -0,0 +1,1324 @@
+use reqwest::StatusCode;
+use teloxide::types::InlineKeyboardButton;
+
+use crate::store::Store;
+
+/// A language the bot has messages in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+}
+
+impl Default for Language {
+    fn default() -> Self {
+        Self::English
+    }
+}
+
+impl Language {
+    pub const ALL: [Self; 2] = [Self::English, Self::German];
+
+    /// The language of an IETF tag like `de` or `de-AT`, as Telegram sends
+    /// them. `None` if there are no messages in it.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let primary = code.split(|c| c == '-' || c == '_').next()?;
+
+        Self::ALL
+            .into_iter()
+            .find(|language| language.code().eq_ignore_ascii_case(primary))
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::English => "en",
+            Self::German => "de",
+        }
+    }
+
+    /// The name of the language in itself.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::English => "English",
+            Self::German => "Deutsch",
+        }
+    }
+
+    /// The codes of all languages, for messages listing them.
+    pub fn codes() -> String {
+        Self::ALL
+            .into_iter()
+            .map(Self::code)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn text(self, text: Text<'_>) -> String {
+        match self {
+            Self::English => english(text),
+            Self::German => german(text),
+        }
+    }
+}
+
+/// Every message the bot sends to users. Details that come from errors are
+/// passed in as they are and stay English.
+pub enum Text<'a> {
+    LatestRightHere,
+    DeleteIconPrivateOnly,
+    StillAwaitingReview,
+    AskRemovalTarget,
+    IconExistsUsage,
+    ThemedWith {
+        app_path: &'a str,
+        drawable: &'a str,
+        branch: &'a str,
+    },
+    NotThemed {
+        app_path: &'a str,
+    },
+    SearchTooLong,
+    LanguageCurrent {
+        name: &'a str,
+        codes: &'a str,
+    },
+    LanguagePicked {
+        name: &'a str,
+    },
+    UnknownLanguage {
+        code: &'a str,
+        codes: &'a str,
+    },
+    NoReleasesTitle,
+    NoReleasesText,
+    NoReleasesHint,
+    ReleaseUpdated {
+        time: &'a str,
+    },
+    ReleaseDownload,
+    NoRelease {
+        name: &'a str,
+    },
+    ReleaseUnavailable {
+        name: &'a str,
+    },
+    IconCount {
+        count: usize,
+        branch: &'a str,
+    },
+    IconMatches {
+        count: usize,
+        total: usize,
+        branch: &'a str,
+        filter: &'a str,
+    },
+    Page {
+        page: usize,
+        pages: usize,
+    },
+    PreviousPage,
+    NextPage,
+    StartWithBranch,
+    StartWithAppPath,
+    BranchButton {
+        label: &'a str,
+    },
+    BothBranches,
+    AllBranches,
+    BranchClosed,
+    AskAppPath,
+    InvalidAppPath,
+    AnotherAppPath,
+    AppPathInBatch {
+        app_path: &'a str,
+    },
+    IconNameInBatch {
+        icon_name: &'a str,
+        app_path: &'a str,
+    },
+    AppNotFound,
+    FoundApp {
+        title: Option<&'a str>,
+        app_path: &'a str,
+        store: Store,
+    },
+    StoresUnavailable,
+    AttachIcon,
+    AttachNewIcon,
+    SendAppPath,
+    AlreadyHasIcon {
+        app_path: &'a str,
+        drawable: &'a str,
+    },
+    Aborting,
+    RemovalTargetAsText,
+    NotInIconMap {
+        name: &'a str,
+    },
+    UsedBySeveral {
+        name: &'a str,
+        packages: &'a str,
+    },
+    MappedOn {
+        drawable: &'a str,
+        branch: &'a str,
+    },
+    ConfirmRemoval {
+        app_path: &'a str,
+        mappings: &'a str,
+    },
+    AnimatedSticker,
+    AttachImage,
+    AskDescription,
+    AskIconName,
+    AskIconNameKeep {
+        name: &'a str,
+    },
+    PickAnotherName,
+    NameTaken {
+        icon_name: &'a str,
+        package: Option<&'a str>,
+        branch: &'a str,
+    },
+    ReplaceOrPickAnother,
+    ProvideName,
+    ProvideDifferentName,
+    ProvideNewName,
+    AttachNewImage,
+    DownloadingImage,
+    TooSlowAttachAgain {
+        error: &'a str,
+        request: u64,
+    },
+    FileTooLarge,
+    ImageRejected,
+    SvgUnusable {
+        error: &'a str,
+    },
+    XmlUnusable {
+        error: &'a str,
+    },
+    ConvertingSvg,
+    PaddedConvertingSvg,
+    ConvertFailed {
+        error: &'a str,
+    },
+    UnusableVd {
+        error: &'a str,
+    },
+    ConversionDone,
+    ConversionDoneLowResolution {
+        width: u32,
+        height: u32,
+    },
+    XmlDetected,
+    UnsupportedFormat,
+    ConvertingPng,
+    ImageTooSmall {
+        error: &'a str,
+    },
+    ExtremeAspectRatio {
+        error: &'a str,
+    },
+    FullyTransparent,
+    SolidBackground,
+    NoTransparency,
+    RemovingBackground,
+    ReviewIcon,
+    ReviewTracedIcon,
+    Retracing {
+        options: &'a str,
+    },
+    RetraceUnusable {
+        error: &'a str,
+    },
+    NothingLeft,
+    TooSlowKeepingPrevious {
+        error: &'a str,
+        request: u64,
+    },
+    RetraceConvertFailed {
+        error: &'a str,
+    },
+    NewPreview,
+    BatchFull {
+        max: usize,
+    },
+    AddAnother,
+    AskNextAppPath,
+    Created,
+    Updated,
+    SentForReview,
+    SubmissionRejected {
+        app_paths: &'a str,
+    },
+    SubmissionApproved {
+        app_paths: &'a str,
+    },
+    ApprovedSubmissionFailed,
+    Merged {
+        app_paths: &'a str,
+        branch: &'a str,
+    },
+    ClosedWithNote {
+        app_paths: &'a str,
+        branch: &'a str,
+        note: &'a str,
+    },
+    Closed {
+        app_paths: &'a str,
+        branch: &'a str,
+        url: &'a str,
+    },
+    ReviewExpired {
+        app_paths: &'a str,
+    },
+    AlreadyProcessed,
+    TryAgainQuestion,
+    SendDifferentImage,
+    Queued,
+    RefreshFailed {
+        error: &'a str,
+    },
+    AlreadyOpen {
+        urls: &'a str,
+    },
+    SubmissionFailed {
+        error: &'a str,
+    },
+    TooSlowTryAgain {
+        error: &'a str,
+        request: u64,
+    },
+    UpdatedExisting {
+        url: &'a str,
+    },
+    ReplacedStale {
+        branch: &'a str,
+    },
+    RemovalAlreadyOpen {
+        branch: &'a str,
+        url: &'a str,
+    },
+    RemovalFailed {
+        branch: &'a str,
+        error: &'a str,
+    },
+    RemovalOpened {
+        url: &'a str,
+        branch: &'a str,
+    },
+    PendingReview {
+        merge_requests: &'a str,
+    },
+    SubmissionsUnavailable,
+    PrivateChatLink,
+    SubmitIconButton,
+    Banned,
+    TrustedOnly,
+    DailyLimit {
+        max: usize,
+        until: &'a str,
+    },
+    AdminsOnly,
+    BanUsage,
+    UserBanned {
+        user_id: i64,
+    },
+    UserAlreadyBanned {
+        user_id: i64,
+    },
+    UserUnbanned {
+        user_id: i64,
+    },
+    UserNotBanned {
+        user_id: i64,
+    },
+    NobodyBanned,
+    BannedUsers {
+        user_ids: &'a str,
+    },
+    About {
+        version: &'a str,
+        started: &'a str,
+        days: i64,
+        hours: i64,
+        minutes: i64,
+        backend: &'a str,
+        submissions_available: bool,
+        review: bool,
+        moderation: bool,
+        dry_run: bool,
+    },
+    StatsIcons {
+        branch: &'a str,
+        count: usize,
+    },
+    StatsSubmitted {
+        week: usize,
+        month: usize,
+    },
+    StatsOpenMergeRequests {
+        count: usize,
+    },
+    StatsLatestReleases {
+        releases: &'a str,
+    },
+    NewSubmission {
+        user: &'a str,
+        branches: &'a str,
+        details: &'a str,
+    },
+    SubmissionDetails {
+        app_path: &'a str,
+        /// The app's title and store page, if it was found in a store.
+        app: Option<&'a str>,
+        icon_name: &'a str,
+        description: &'a str,
+    },
+    ApproveButton,
+    RejectButton,
+    OnlyReviewers,
+    ReviewVerdict {
+        reviewer: &'a str,
+        approved: bool,
+        icon_names: &'a str,
+        app_paths: &'a str,
+    },
+    ReviewDropped {
+        icon_names: &'a str,
+        app_paths: &'a str,
+    },
+    DownloadFailed,
+    DecodeFailed,
+    TraceFailed,
+    VdFailed,
+    GitFailed,
+    GitLabRejected {
+        status: StatusCode,
+    },
+    OtaFailed,
+    ServiceUnavailable,
+    InternalError,
+}
+
+fn english(text: Text<'_>) -> String {
+    match text {
+        Text::LatestRightHere => String::from("The latest releases are right here."),
+        Text::DeleteIconPrivateOnly => {
+            String::from("Please send /deleteicon in a private chat with me.")
+        }
+        Text::StillAwaitingReview => String::from(
+            "Your last icon is still waiting for review, please wait for the maintainers to decide.",
+        ),
+        Text::AskRemovalTarget => String::from(
+            "Which icon do you want to remove? Send the app path of the app, for example com.discord, or the name of its drawable.",
+        ),
+        Text::IconExistsUsage => {
+            String::from("Please give a valid app path, for example /iconexists com.discord")
+        }
+        Text::ThemedWith {
+            app_path,
+            drawable,
+            branch,
+        } => format!("{app_path} is themed with {drawable} on {branch}."),
+        Text::NotThemed { app_path } => {
+            format!("{app_path} is not themed yet, use /addicon to submit an icon for it.")
+        }
+        Text::SearchTooLong => String::from("Please search for something shorter."),
+        Text::LanguageCurrent { name, codes } => format!(
+            "I speak {name} in this chat. Send /language followed by one of {codes} to change it."
+        ),
+        Text::LanguagePicked { name } => format!("I'll speak {name} in this chat from now on."),
+        Text::UnknownLanguage { code, codes } => {
+            format!("There are no messages in {code} yet, please pick one of {codes}.")
+        }
+        Text::NoReleasesTitle => String::from("No releases available"),
+        Text::NoReleasesText => String::from("No releases are available right now."),
+        Text::NoReleasesHint => String::from("Try again later or use /latest."),
+        Text::ReleaseUpdated { time } => format!("Updated {time}"),
+        Text::ReleaseDownload => String::from("download"),
+        Text::NoRelease { name } => format!("{name}: no release available"),
+        Text::ReleaseUnavailable { name } => {
+            format!("{name}: temporarily unavailable (fetch error)")
+        }
+        Text::IconCount { count, branch } => format!("{count} icons on {branch}"),
+        Text::IconMatches {
+            count,
+            total,
+            branch,
+            filter,
+        } => format!("{count} of {total} icons on {branch} match \"{filter}\""),
+        Text::Page { page, pages } => format!(", page {page}/{pages}"),
+        Text::PreviousPage => String::from("« Previous"),
+        Text::NextPage => String::from("Next »"),
+        Text::StartWithBranch => String::from(
+            "Let's start! Which branch of the overlay do you want to add an icon to?",
+        ),
+        Text::StartWithAppPath => String::from(
+            "Let's start! What is the app path of the app you want to add an icon for? For example com.discord or com.google.files",
+        ),
+        Text::BranchButton { label } => format!("{label} branch"),
+        Text::BothBranches => String::from("Both"),
+        Text::AllBranches => String::from("All"),
+        Text::BranchClosed => {
+            String::from("This branch is no longer accepting submissions. Aborting.")
+        }
+        Text::AskAppPath => String::from(
+            "What is the app path of the app you want to add an icon for? For example com.discord or com.google.files",
+        ),
+        Text::InvalidAppPath => String::from(
+            "That is not a valid app path. It consists of at least two parts separated by dots, each starting with a letter and containing only letters, digits and underscores, for example com.discord or com.google.files. You can also send the Play Store link of the app.",
+        ),
+        Text::AnotherAppPath => String::from("Please send the app path of another app."),
+        Text::AppPathInBatch { app_path } => {
+            format!("This request already contains an icon for {app_path}.")
+        }
+        Text::IconNameInBatch {
+            icon_name,
+            app_path,
+        } => format!("This request already uses the name {icon_name} for {app_path}."),
+        Text::AppNotFound => String::from(
+            "Could not find an app with this name in the Play Store or on F-Droid. Are you sure it is correct?",
+        ),
+        Text::FoundApp {
+            title: Some(title),
+            app_path,
+            store,
+        } => format!("Found {title} ({app_path}) in {store}."),
+        Text::FoundApp {
+            title: None,
+            app_path,
+            store,
+        } => format!("Found {app_path} in {store}."),
+        Text::StoresUnavailable => String::from(
+            "I couldn't check the app stores right now, so I'll assume the app path is correct.",
+        ),
+        Text::AttachIcon => String::from(
+            "Please attach a PNG with transparent background or a monochrome SVG as the icon now.",
+        ),
+        Text::AttachNewIcon => String::from(
+            "Please attach a PNG with transparent background or a monochrome SVG as the new icon now.",
+        ),
+        Text::SendAppPath => String::from("Please send an app path."),
+        Text::AlreadyHasIcon { app_path, drawable } => {
+            format!("{app_path} already has an icon ({drawable}). Do you want to update it?")
+        }
+        Text::Aborting => String::from("Aborting."),
+        Text::RemovalTargetAsText => {
+            String::from("Please send the app path or the drawable name as text.")
+        }
+        Text::NotInIconMap { name } => {
+            format!("{name} isn't in the icon map, there is nothing to remove.")
+        }
+        Text::UsedBySeveral { name, packages } => format!(
+            "{name} is used by several apps: {packages}. Please send the app path of the one to remove."
+        ),
+        Text::MappedOn { drawable, branch } => format!("{drawable} on {branch}"),
+        Text::ConfirmRemoval { app_path, mappings } => format!(
+            "{app_path} is mapped to:\n{mappings}\nDo you want to open a merge request removing it?"
+        ),
+        Text::AnimatedSticker => String::from(
+            "Animated and video stickers can't be used, please send a static sticker or an image.",
+        ),
+        Text::AttachImage => String::from("Please attach an image."),
+        Text::AskDescription => {
+            String::from("Finally, provide a short description for this request.")
+        }
+        Text::AskIconName => String::from(
+            "Provide a name for this icon, for example youtube_music or whatsapp. Must be lowercase and contain no special characters or spaces.",
+        ),
+        Text::AskIconNameKeep { name } => format!(
+            "Provide a name for this icon. Send {name} to keep the current name, or a new name to rename it. Must be lowercase and contain no special characters or spaces."
+        ),
+        Text::PickAnotherName => String::from("Please pick another name."),
+        Text::NameTaken {
+            icon_name,
+            package: Some(package),
+            branch,
+        } => format!(
+            "The name {icon_name} is already taken by the icon for {package} on the {branch} branch."
+        ),
+        Text::NameTaken {
+            icon_name,
+            package: None,
+            branch,
+        } => format!(
+            "The name {icon_name} is already taken by an icon that is not in the icon map on the {branch} branch."
+        ),
+        Text::ReplaceOrPickAnother => {
+            String::from("Do you want to replace it or pick another name?")
+        }
+        Text::ProvideName => String::from("Please provide a name."),
+        Text::ProvideDifferentName => String::from("Provide a different name for this icon."),
+        Text::ProvideNewName => String::from("Provide the new name for this icon."),
+        Text::AttachNewImage => String::from("Please attach the new image."),
+        Text::DownloadingImage => String::from("Downloading image..."),
+        Text::TooSlowAttachAgain { error, request } => format!(
+            "Sorry, {error} (request #{request}). Please attach the icon again, a smaller image might help."
+        ),
+        Text::FileTooLarge => String::from("This file is too large. Please attach a smaller image."),
+        Text::ImageRejected => String::from("Sorry, this image can't be accepted."),
+        Text::SvgUnusable { error } => {
+            format!("This SVG can't be used: {error}. Please attach a different file.")
+        }
+        Text::XmlUnusable { error } => {
+            format!("This XML can't be used: {error}. Please attach a different file.")
+        }
+        Text::ConvertingSvg => String::from("Converting SVG to VD..."),
+        Text::PaddedConvertingSvg => String::from(
+            "The icon wasn't square, so it was padded with transparent space. Converting SVG to VD...",
+        ),
+        Text::ConvertFailed { error } => format!(
+            "Failed to convert SVG to VD: {error}. {}",
+            english(Text::SendDifferentImage)
+        ),
+        Text::UnusableVd { error } => format!(
+            "svg2vd produced an unusable VectorDrawable, {error}. {}",
+            english(Text::SendDifferentImage)
+        ),
+        Text::ConversionDone => String::from("Done with conversion. Here's a preview of the SVG:"),
+        Text::ConversionDoneLowResolution { width, height } => format!(
+            "Done with conversion. Note that the image is only {width}×{height} pixels, so the icon may look rough. Here's a preview of the SVG:"
+        ),
+        Text::XmlDetected => String::from("Android icon XML detected!"),
+        Text::UnsupportedFormat => String::from("File format unsupported, try again!"),
+        Text::ConvertingPng => String::from("Converting PNG to black PNM..."),
+        Text::ImageTooSmall { error } => format!(
+            "This image can't be used, {error}. Small images trace into jagged icons, please look for a higher resolution version, e.g. on the app's store page or website."
+        ),
+        Text::ExtremeAspectRatio { error } => {
+            format!("This image can't be used, {error}. Please attach an image of just the icon.")
+        }
+        Text::FullyTransparent => String::from(
+            "This image is fully transparent, there is nothing to trace. Please attach a different PNG.",
+        ),
+        Text::SolidBackground => String::from(
+            "This image has no transparent background, but a solid background color. Should I remove it, or do you want to send a PNG with transparency instead?",
+        ),
+        Text::NoTransparency => String::from(
+            "This image has no transparent background, please send a PNG with transparency.",
+        ),
+        Text::RemovingBackground => String::from("Removing background..."),
+        Text::ReviewIcon => String::from("Please review the icon and if it is good, proceed!"),
+        Text::ReviewTracedIcon => String::from(
+            "Please review the icon and if it is good, proceed! If the lines are too thick or thin, or the shapes too rough or noisy, you can adjust them.",
+        ),
+        Text::Retracing { options } => format!("Tracing again with {options}..."),
+        Text::RetraceUnusable { error } => {
+            format!("With these settings {error}, keeping the previous result.")
+        }
+        Text::NothingLeft => String::from(
+            "Nothing is left of the icon with these settings, keeping the previous result.",
+        ),
+        Text::TooSlowKeepingPrevious { error, request } => {
+            format!("Sorry, {error} (request #{request}). Keeping the previous result.")
+        }
+        Text::RetraceConvertFailed { error } => {
+            format!("Failed to convert SVG to VD, keeping the previous result: {error}")
+        }
+        Text::NewPreview => String::from("Here's the new preview of the SVG:"),
+        Text::BatchFull { max } => {
+            format!("This request has reached the maximum of {max} icons, submitting it now.")
+        }
+        Text::AddAnother => String::from("Add another icon to this request?"),
+        Text::AskNextAppPath => String::from(
+            "What is the app path of the next app? For example com.discord or com.google.files",
+        ),
+        Text::Created => String::from("Created."),
+        Text::Updated => String::from("Updated."),
+        Text::SentForReview => String::from(
+            "Thanks! Your submission was sent to the maintainers for review, I'll let you know once they decide.",
+        ),
+        Text::SubmissionRejected { app_paths } => {
+            format!("Sorry, the maintainers rejected your submission for {app_paths}.")
+        }
+        Text::SubmissionApproved { app_paths } => format!(
+            "The maintainers approved your submission for {app_paths}, submitting it now..."
+        ),
+        Text::ApprovedSubmissionFailed => String::from(
+            "Sorry, your approved submission could not be submitted. The maintainers have been told.",
+        ),
+        Text::Merged { app_paths, branch } => format!(
+            "Your submission for {app_paths} ({branch}) was merged 🎉 It will ship in the next release."
+        ),
+        Text::ClosedWithNote {
+            app_paths,
+            branch,
+            note,
+        } => format!("Your submission for {app_paths} ({branch}) was closed: {note}"),
+        Text::Closed {
+            app_paths,
+            branch,
+            url,
+        } => format!("Your submission for {app_paths} ({branch}) was closed, see {url}"),
+        Text::ReviewExpired { app_paths } => format!(
+            "Sorry, nobody reviewed your submission for {app_paths} in time. Please submit it again later."
+        ),
+        Text::AlreadyProcessed => String::from("Already processed."),
+        Text::TryAgainQuestion => String::from("Do you want to try again?"),
+        Text::SendDifferentImage => String::from(
+            "Please send a different image, the previous one couldn't be processed.",
+        ),
+        Text::Queued => String::from("Another submission is being processed, yours is queued."),
+        Text::RefreshFailed { error } => format!(
+            "Sorry, the overlay repository could not be updated ({error}). Nothing was submitted. Do you want to try again?"
+        ),
+        Text::AlreadyOpen { urls } => format!(
+            "There already is an open merge request for this submission: {urls}\nDo you want to update it with the new icons?"
+        ),
+        Text::SubmissionFailed { error } => format!(
+            "Sorry, the submission failed ({error}). Nothing was changed, do you want to try again?"
+        ),
+        Text::TooSlowTryAgain { error, request } => {
+            format!("Sorry, {error} (request #{request}). Do you want to try again?")
+        }
+        Text::UpdatedExisting { url } => format!("Updated the existing merge request {url}"),
+        Text::ReplacedStale { branch } => {
+            format!("Replaced the leftover branch {branch} of a closed merge request.")
+        }
+        Text::RemovalAlreadyOpen { branch, url } => {
+            format!("There already is an open merge request for {branch}: {url}")
+        }
+        Text::RemovalFailed { branch, error } => format!(
+            "Sorry, the removal from {branch} failed ({error}). Nothing was changed there."
+        ),
+        Text::RemovalOpened { url, branch } => format!("Opened {url} for {branch}."),
+        Text::PendingReview { merge_requests } => {
+            format!("A submission for this app is already pending review: {merge_requests}")
+        }
+        Text::SubmissionsUnavailable => String::from(
+            "Icon submissions are temporarily unavailable, please try again later.",
+        ),
+        Text::PrivateChatLink => String::from(
+            "Icons are submitted in a private chat with me, tap the button to continue there.",
+        ),
+        Text::SubmitIconButton => String::from("Submit an icon"),
+        Text::Banned => String::from("Sorry, you can't submit icons."),
+        Text::TrustedOnly => String::from(
+            "Sorry, icon submissions are limited to trusted contributors at the moment.",
+        ),
+        Text::DailyLimit { max, until } => format!(
+            "You reached the limit of {max} icon submissions per day. You can submit again after {until}."
+        ),
+        Text::AdminsOnly => String::from("Only admins can do that."),
+        Text::BanUsage => {
+            String::from("Please give a user id or reply to a message of the user.")
+        }
+        Text::UserBanned { user_id } => format!("Banned {user_id} from submitting icons."),
+        Text::UserAlreadyBanned { user_id } => format!("{user_id} is already banned."),
+        Text::UserUnbanned { user_id } => format!("{user_id} can submit icons again."),
+        Text::UserNotBanned { user_id } => format!("{user_id} isn't banned."),
+        Text::NobodyBanned => String::from("Nobody is banned."),
+        Text::BannedUsers { user_ids } => format!("Banned users:\n{user_ids}"),
+        Text::About {
+            version,
+            started,
+            days,
+            hours,
+            minutes,
+            backend,
+            submissions_available,
+            review,
+            moderation,
+            dry_run,
+        } => {
+            let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+
+            format!(
+                "Leonardo {version}\n\
+                Running since {started} UTC, up {days}d {hours}h {minutes}m\n\
+                \n\
+                Submission backend: {backend}\n\
+                Icon submissions: {}\n\
+                Review mode: {}\n\
+                Image moderation: {}\n\
+                Dry run: {}",
+                if submissions_available {
+                    "available"
+                } else {
+                    "disabled, svg2vd is missing"
+                },
+                on_off(review),
+                on_off(moderation),
+                on_off(dry_run),
+            )
+        }
+        Text::StatsIcons { branch, count } => format!("Icons on {branch}: {count}"),
+        Text::StatsSubmitted { week, month } => format!(
+            "Icons submitted through the bot: {week} in the last 7 days, {month} in the last 30 days"
+        ),
+        Text::StatsOpenMergeRequests { count } => format!("Open icon merge requests: {count}"),
+        Text::StatsLatestReleases { releases } => format!("Latest releases:\n{releases}"),
+        Text::NewSubmission {
+            user,
+            branches,
+            details,
+        } => format!("New icon submission\nUser: {user}\nBranches: {branches}\n\n{details}"),
+        Text::SubmissionDetails {
+            app_path,
+            app,
+            icon_name,
+            description,
+        } => format!(
+            "Package: {app_path}{}\nIcon: {icon_name}\nDescription: {description}",
+            app.map(|app| format!("\nApp: {app}")).unwrap_or_default()
+        ),
+        Text::ApproveButton => String::from("Approve"),
+        Text::RejectButton => String::from("Reject"),
+        Text::OnlyReviewers => String::from("Only reviewers can do this."),
+        Text::ReviewVerdict {
+            reviewer,
+            approved,
+            icon_names,
+            app_paths,
+        } => format!(
+            "{reviewer} {} the submission of {icon_names} for {app_paths}.",
+            if approved { "approved" } else { "rejected" }
+        ),
+        Text::ReviewDropped {
+            icon_names,
+            app_paths,
+        } => format!(
+            "Nobody reviewed the submission of {icon_names} for {app_paths} in time, it was dropped."
+        ),
+        Text::DownloadFailed => String::from("I couldn't download your file from Telegram."),
+        Text::DecodeFailed => String::from("I couldn't decode that image, is it a valid PNG?"),
+        Text::TraceFailed => String::from("I couldn't trace that image into an icon."),
+        Text::VdFailed => String::from("I couldn't convert that image to a VectorDrawable."),
+        Text::GitFailed => String::from("Something went wrong with the overlay repository."),
+        Text::GitLabRejected { status } => format!("GitLab rejected the request ({status})."),
+        Text::OtaFailed => {
+            String::from("I couldn't fetch the latest releases, please try again later.")
+        }
+        Text::ServiceUnavailable => {
+            String::from("An external service didn't respond, please try again later.")
+        }
+        Text::InternalError => {
+            String::from("Sorry, something went wrong on my side. Please try again.")
+        }
+    }
+}
+
+fn german(text: Text<'_>) -> String {
+    match text {
+        Text::LatestRightHere => String::from("Die neuesten Releases findest du genau hier."),
+        Text::DeleteIconPrivateOnly => {
+            String::from("Bitte schick /deleteicon in einem privaten Chat mit mir.")
+        }
+        Text::StillAwaitingReview => String::from(
+            "Dein letztes Icon wartet noch auf die Prüfung, bitte warte, bis die Maintainer entschieden haben.",
+        ),
+        Text::AskRemovalTarget => String::from(
+            "Welches Icon möchtest du entfernen? Schick den App-Pfad der App, zum Beispiel com.discord, oder den Namen ihres Drawables.",
+        ),
+        Text::IconExistsUsage => String::from(
+            "Bitte gib einen gültigen App-Pfad an, zum Beispiel /iconexists com.discord",
+        ),
+        Text::ThemedWith {
+            app_path,
+            drawable,
+            branch,
+        } => format!("{app_path} hat auf {branch} das Icon {drawable}."),
+        Text::NotThemed { app_path } => format!(
+            "{app_path} hat noch kein Icon, mit /addicon kannst du eins dafür einreichen."
+        ),
+        Text::SearchTooLong => String::from("Bitte such nach etwas Kürzerem."),
+        Text::LanguageCurrent { name, codes } => format!(
+            "In diesem Chat spreche ich {name}. Schick /language gefolgt von einem von {codes}, um das zu ändern."
+        ),
+        Text::LanguagePicked { name } => {
+            format!("Ich spreche in diesem Chat ab jetzt {name}.")
+        }
+        Text::UnknownLanguage { code, codes } => {
+            format!("Für {code} gibt es noch keine Texte, bitte wähl eins von {codes}.")
+        }
+        Text::NoReleasesTitle => String::from("Keine Releases verfügbar"),
+        Text::NoReleasesText => String::from("Gerade sind keine Releases verfügbar."),
+        Text::NoReleasesHint => String::from("Versuch es später noch einmal oder nutze /latest."),
+        Text::ReleaseUpdated { time } => format!("Aktualisiert am {time}"),
+        Text::ReleaseDownload => String::from("Download"),
+        Text::NoRelease { name } => format!("{name}: kein Release verfügbar"),
+        Text::ReleaseUnavailable { name } => {
+            format!("{name}: vorübergehend nicht verfügbar (Abruffehler)")
+        }
+        Text::IconCount { count, branch } => format!("{count} Icons auf {branch}"),
+        Text::IconMatches {
+            count,
+            total,
+            branch,
+            filter,
+        } => format!("{count} von {total} Icons auf {branch} passen zu „{filter}“"),
+        Text::Page { page, pages } => format!(", Seite {page}/{pages}"),
+        Text::PreviousPage => String::from("« Zurück"),
+        Text::NextPage => String::from("Weiter »"),
+        Text::StartWithBranch => String::from(
+            "Los geht's! Zu welchem Branch des Overlays möchtest du ein Icon hinzufügen?",
+        ),
+        Text::StartWithAppPath => String::from(
+            "Los geht's! Wie lautet der App-Pfad der App, für die du ein Icon hinzufügen möchtest? Zum Beispiel com.discord oder com.google.files",
+        ),
+        Text::BranchButton { label } => format!("Branch {label}"),
+        Text::BothBranches => String::from("Beide"),
+        Text::AllBranches => String::from("Alle"),
+        Text::BranchClosed => String::from(
+            "Für diesen Branch werden keine Einreichungen mehr angenommen. Abgebrochen.",
+        ),
+        Text::AskAppPath => String::from(
+            "Wie lautet der App-Pfad der App, für die du ein Icon hinzufügen möchtest? Zum Beispiel com.discord oder com.google.files",
+        ),
+        Text::InvalidAppPath => String::from(
+            "Das ist kein gültiger App-Pfad. Er besteht aus mindestens zwei durch Punkte getrennten Teilen, die jeweils mit einem Buchstaben beginnen und nur Buchstaben, Ziffern und Unterstriche enthalten, zum Beispiel com.discord oder com.google.files. Du kannst auch den Play-Store-Link der App schicken.",
+        ),
+        Text::AnotherAppPath => String::from("Bitte schick den App-Pfad einer anderen App."),
+        Text::AppPathInBatch { app_path } => {
+            format!("Diese Anfrage enthält bereits ein Icon für {app_path}.")
+        }
+        Text::IconNameInBatch {
+            icon_name,
+            app_path,
+        } => format!("Diese Anfrage verwendet den Namen {icon_name} bereits für {app_path}."),
+        Text::AppNotFound => String::from(
+            "Ich konnte keine App mit diesem Namen im Play Store oder auf F-Droid finden. Bist du sicher, dass er stimmt?",
+        ),
+        Text::FoundApp {
+            title,
+            app_path,
+            store,
+        } => {
+            let app = match title {
+                Some(title) => format!("{title} ({app_path})"),
+                None => app_path.to_owned(),
+            };
+
+            match store {
+                Store::PlayStore => format!("{app} im Play Store gefunden."),
+                Store::FDroid => format!("{app} auf F-Droid gefunden."),
+            }
+        }
+        Text::StoresUnavailable => String::from(
+            "Ich konnte die App-Stores gerade nicht prüfen, also gehe ich davon aus, dass der App-Pfad stimmt.",
+        ),
+        Text::AttachIcon => String::from(
+            "Bitte häng jetzt ein PNG mit transparentem Hintergrund oder ein einfarbiges SVG als Icon an.",
+        ),
+        Text::AttachNewIcon => String::from(
+            "Bitte häng jetzt ein PNG mit transparentem Hintergrund oder ein einfarbiges SVG als neues Icon an.",
+        ),
+        Text::SendAppPath => String::from("Bitte schick einen App-Pfad."),
+        Text::AlreadyHasIcon { app_path, drawable } => format!(
+            "{app_path} hat bereits ein Icon ({drawable}). Möchtest du es aktualisieren?"
+        ),
+        Text::Aborting => String::from("Abgebrochen."),
+        Text::RemovalTargetAsText => {
+            String::from("Bitte schick den App-Pfad oder den Drawable-Namen als Text.")
+        }
+        Text::NotInIconMap { name } => {
+            format!("{name} ist nicht in der Icon-Map, es gibt nichts zu entfernen.")
+        }
+        Text::UsedBySeveral { name, packages } => format!(
+            "{name} wird von mehreren Apps verwendet: {packages}. Bitte schick den App-Pfad der App, deren Icon entfernt werden soll."
+        ),
+        Text::MappedOn { drawable, branch } => format!("{drawable} auf {branch}"),
+        Text::ConfirmRemoval { app_path, mappings } => format!(
+            "{app_path} ist zugeordnet zu:\n{mappings}\nMöchtest du einen Merge Request öffnen, der das Icon entfernt?"
+        ),
+        Text::AnimatedSticker => String::from(
+            "Animierte Sticker und Video-Sticker können nicht verwendet werden, bitte schick einen statischen Sticker oder ein Bild.",
+        ),
+        Text::AttachImage => String::from("Bitte häng ein Bild an."),
+        Text::AskDescription => {
+            String::from("Zum Schluss gib bitte eine kurze Beschreibung für diese Anfrage an.")
+        }
+        Text::AskIconName => String::from(
+            "Gib einen Namen für dieses Icon an, zum Beispiel youtube_music oder whatsapp. Er muss kleingeschrieben sein und darf keine Sonder- oder Leerzeichen enthalten.",
+        ),
+        Text::AskIconNameKeep { name } => format!(
+            "Gib einen Namen für dieses Icon an. Schick {name}, um den aktuellen Namen zu behalten, oder einen neuen Namen, um es umzubenennen. Er muss kleingeschrieben sein und darf keine Sonder- oder Leerzeichen enthalten."
+        ),
+        Text::PickAnotherName => String::from("Bitte wähl einen anderen Namen."),
+        Text::NameTaken {
+            icon_name,
+            package: Some(package),
+            branch,
+        } => format!(
+            "Der Name {icon_name} wird auf dem Branch {branch} bereits vom Icon für {package} verwendet."
+        ),
+        Text::NameTaken {
+            icon_name,
+            package: None,
+            branch,
+        } => format!(
+            "Der Name {icon_name} wird auf dem Branch {branch} bereits von einem Icon verwendet, das nicht in der Icon-Map ist."
+        ),
+        Text::ReplaceOrPickAnother => {
+            String::from("Möchtest du es ersetzen oder einen anderen Namen wählen?")
+        }
+        Text::ProvideName => String::from("Bitte gib einen Namen an."),
+        Text::ProvideDifferentName => String::from("Gib einen anderen Namen für dieses Icon an."),
+        Text::ProvideNewName => String::from("Gib den neuen Namen für dieses Icon an."),
+        Text::AttachNewImage => String::from("Bitte häng das neue Bild an."),
+        Text::DownloadingImage => String::from("Bild wird heruntergeladen..."),
+        Text::TooSlowAttachAgain { error, request } => format!(
+            "Entschuldigung, das hat zu lange gedauert ({error}, Anfrage #{request}). Bitte häng das Icon noch einmal an, ein kleineres Bild könnte helfen."
+        ),
+        Text::FileTooLarge => {
+            String::from("Diese Datei ist zu groß. Bitte häng ein kleineres Bild an.")
+        }
+        Text::ImageRejected => {
+            String::from("Entschuldigung, dieses Bild kann nicht angenommen werden.")
+        }
+        Text::SvgUnusable { error } => format!(
+            "Dieses SVG kann nicht verwendet werden ({error}). Bitte häng eine andere Datei an."
+        ),
+        Text::XmlUnusable { error } => format!(
+            "Diese XML-Datei kann nicht verwendet werden ({error}). Bitte häng eine andere Datei an."
+        ),
+        Text::ConvertingSvg => String::from("SVG wird in ein VD umgewandelt..."),
+        Text::PaddedConvertingSvg => String::from(
+            "Das Icon war nicht quadratisch und wurde mit transparentem Rand aufgefüllt. SVG wird in ein VD umgewandelt...",
+        ),
+        Text::ConvertFailed { error } => format!(
+            "Das SVG konnte nicht in ein VD umgewandelt werden ({error}). {}",
+            german(Text::SendDifferentImage)
+        ),
+        Text::UnusableVd { error } => format!(
+            "svg2vd hat ein unbrauchbares VectorDrawable erzeugt ({error}). {}",
+            german(Text::SendDifferentImage)
+        ),
+        Text::ConversionDone => {
+            String::from("Umwandlung abgeschlossen. Hier ist eine Vorschau des SVG:")
+        }
+        Text::ConversionDoneLowResolution { width, height } => format!(
+            "Umwandlung abgeschlossen. Das Bild hat nur {width}×{height} Pixel, das Icon könnte also grob aussehen. Hier ist eine Vorschau des SVG:"
+        ),
+        Text::XmlDetected => String::from("Android-Icon-XML erkannt!"),
+        Text::UnsupportedFormat => {
+            String::from("Dieses Dateiformat wird nicht unterstützt, versuch es noch einmal!")
+        }
+        Text::ConvertingPng => String::from("PNG wird in ein schwarzes PNM umgewandelt..."),
+        Text::ImageTooSmall { error } => format!(
+            "Dieses Bild kann nicht verwendet werden ({error}). Kleine Bilder ergeben gezackte Icons, bitte such nach einer Version mit höherer Auflösung, z. B. auf der Store-Seite oder Website der App."
+        ),
+        Text::ExtremeAspectRatio { error } => format!(
+            "Dieses Bild kann nicht verwendet werden ({error}). Bitte häng ein Bild an, das nur das Icon zeigt."
+        ),
+        Text::FullyTransparent => String::from(
+            "Dieses Bild ist vollständig transparent, es gibt nichts nachzuzeichnen. Bitte häng ein anderes PNG an.",
+        ),
+        Text::SolidBackground => String::from(
+            "Dieses Bild hat keinen transparenten Hintergrund, sondern eine einfarbige Hintergrundfarbe. Soll ich sie entfernen, oder möchtest du stattdessen ein PNG mit Transparenz schicken?",
+        ),
+        Text::NoTransparency => String::from(
+            "Dieses Bild hat keinen transparenten Hintergrund, bitte schick ein PNG mit Transparenz.",
+        ),
+        Text::RemovingBackground => String::from("Hintergrund wird entfernt..."),
+        Text::ReviewIcon => String::from("Bitte prüf das Icon und mach weiter, wenn es gut ist!"),
+        Text::ReviewTracedIcon => String::from(
+            "Bitte prüf das Icon und mach weiter, wenn es gut ist! Wenn die Linien zu dick oder zu dünn oder die Formen zu grob oder unruhig sind, kannst du sie anpassen.",
+        ),
+        Text::Retracing { options } => format!("Wird erneut nachgezeichnet ({options})..."),
+        Text::RetraceUnusable { error } => format!(
+            "Mit diesen Einstellungen ist das Ergebnis unbrauchbar ({error}), das vorherige Ergebnis bleibt."
+        ),
+        Text::NothingLeft => String::from(
+            "Mit diesen Einstellungen bleibt nichts vom Icon übrig, das vorherige Ergebnis bleibt.",
+        ),
+        Text::TooSlowKeepingPrevious { error, request } => format!(
+            "Entschuldigung, das hat zu lange gedauert ({error}, Anfrage #{request}). Das vorherige Ergebnis bleibt."
+        ),
+        Text::RetraceConvertFailed { error } => format!(
+            "Das SVG konnte nicht in ein VD umgewandelt werden ({error}), das vorherige Ergebnis bleibt."
+        ),
+        Text::NewPreview => String::from("Hier ist die neue Vorschau des SVG:"),
+        Text::BatchFull { max } => format!(
+            "Diese Anfrage hat das Maximum von {max} Icons erreicht und wird jetzt eingereicht."
+        ),
+        Text::AddAnother => String::from("Noch ein Icon zu dieser Anfrage hinzufügen?"),
+        Text::AskNextAppPath => String::from(
+            "Wie lautet der App-Pfad der nächsten App? Zum Beispiel com.discord oder com.google.files",
+        ),
+        Text::Created => String::from("Erstellt."),
+        Text::Updated => String::from("Aktualisiert."),
+        Text::SentForReview => String::from(
+            "Danke! Deine Einreichung wurde den Maintainern zur Prüfung geschickt, ich melde mich, sobald sie entschieden haben.",
+        ),
+        Text::SubmissionRejected { app_paths } => format!(
+            "Entschuldigung, die Maintainer haben deine Einreichung für {app_paths} abgelehnt."
+        ),
+        Text::SubmissionApproved { app_paths } => format!(
+            "Die Maintainer haben deine Einreichung für {app_paths} angenommen, sie wird jetzt eingereicht..."
+        ),
+        Text::ApprovedSubmissionFailed => String::from(
+            "Entschuldigung, deine angenommene Einreichung konnte nicht eingereicht werden. Die Maintainer wissen Bescheid.",
+        ),
+        Text::Merged { app_paths, branch } => format!(
+            "Deine Einreichung für {app_paths} ({branch}) wurde gemergt 🎉 Sie ist im nächsten Release dabei."
+        ),
+        Text::ClosedWithNote {
+            app_paths,
+            branch,
+            note,
+        } => format!("Deine Einreichung für {app_paths} ({branch}) wurde geschlossen: {note}"),
+        Text::Closed {
+            app_paths,
+            branch,
+            url,
+        } => format!(
+            "Deine Einreichung für {app_paths} ({branch}) wurde geschlossen, siehe {url}"
+        ),
+        Text::ReviewExpired { app_paths } => format!(
+            "Entschuldigung, niemand hat deine Einreichung für {app_paths} rechtzeitig geprüft. Bitte reich sie später noch einmal ein."
+        ),
+        Text::AlreadyProcessed => String::from("Bereits erledigt."),
+        Text::TryAgainQuestion => String::from("Möchtest du es noch einmal versuchen?"),
+        Text::SendDifferentImage => String::from(
+            "Bitte schick ein anderes Bild, das vorherige konnte nicht verarbeitet werden.",
+        ),
+        Text::Queued => String::from(
+            "Gerade wird eine andere Einreichung bearbeitet, deine ist in der Warteschlange.",
+        ),
+        Text::RefreshFailed { error } => format!(
+            "Entschuldigung, das Overlay-Repository konnte nicht aktualisiert werden ({error}). Es wurde nichts eingereicht. Möchtest du es noch einmal versuchen?"
+        ),
+        Text::AlreadyOpen { urls } => format!(
+            "Für diese Einreichung gibt es bereits einen offenen Merge Request: {urls}\nMöchtest du ihn mit den neuen Icons aktualisieren?"
+        ),
+        Text::SubmissionFailed { error } => format!(
+            "Entschuldigung, die Einreichung ist fehlgeschlagen ({error}). Es wurde nichts geändert, möchtest du es noch einmal versuchen?"
+        ),
+        Text::TooSlowTryAgain { error, request } => format!(
+            "Entschuldigung, das hat zu lange gedauert ({error}, Anfrage #{request}). Möchtest du es noch einmal versuchen?"
+        ),
+        Text::UpdatedExisting { url } => {
+            format!("Der bestehende Merge Request {url} wurde aktualisiert.")
+        }
+        Text::ReplacedStale { branch } => format!(
+            "Der übrig gebliebene Branch {branch} eines geschlossenen Merge Requests wurde ersetzt."
+        ),
+        Text::RemovalAlreadyOpen { branch, url } => {
+            format!("Für {branch} gibt es bereits einen offenen Merge Request: {url}")
+        }
+        Text::RemovalFailed { branch, error } => format!(
+            "Entschuldigung, das Entfernen aus {branch} ist fehlgeschlagen ({error}). Dort wurde nichts geändert."
+        ),
+        Text::RemovalOpened { url, branch } => format!("{url} für {branch} geöffnet."),
+        Text::PendingReview { merge_requests } => format!(
+            "Für diese App wartet bereits eine Einreichung auf die Prüfung: {merge_requests}"
+        ),
+        Text::SubmissionsUnavailable => String::from(
+            "Icon-Einreichungen sind vorübergehend nicht möglich, bitte versuch es später noch einmal.",
+        ),
+        Text::PrivateChatLink => String::from(
+            "Icons werden in einem privaten Chat mit mir eingereicht, tipp auf den Button, um dort weiterzumachen.",
+        ),
+        Text::SubmitIconButton => String::from("Icon einreichen"),
+        Text::Banned => String::from("Entschuldigung, du kannst keine Icons einreichen."),
+        Text::TrustedOnly => String::from(
+            "Entschuldigung, Icon-Einreichungen sind im Moment auf vertrauenswürdige Mitwirkende beschränkt.",
+        ),
+        Text::DailyLimit { max, until } => format!(
+            "Du hast das Limit von {max} Icon-Einreichungen pro Tag erreicht. Du kannst ab {until} wieder einreichen."
+        ),
+        Text::AdminsOnly => String::from("Das können nur Admins."),
+        Text::BanUsage => String::from(
+            "Bitte gib eine Nutzer-ID an oder antworte auf eine Nachricht des Nutzers.",
+        ),
+        Text::UserBanned { user_id } => format!("{user_id} kann keine Icons mehr einreichen."),
+        Text::UserAlreadyBanned { user_id } => format!("{user_id} ist bereits gesperrt."),
+        Text::UserUnbanned { user_id } => format!("{user_id} kann wieder Icons einreichen."),
+        Text::UserNotBanned { user_id } => format!("{user_id} ist nicht gesperrt."),
+        Text::NobodyBanned => String::from("Niemand ist gesperrt."),
+        Text::BannedUsers { user_ids } => format!("Gesperrte Nutzer:\n{user_ids}"),
+        Text::About {
+            version,
+            started,
+            days,
+            hours,
+            minutes,
+            backend,
+            submissions_available,
+            review,
+            moderation,
+            dry_run,
+        } => {
+            let on_off = |enabled: bool| if enabled { "an" } else { "aus" };
+
+            format!(
+                "Leonardo {version}\n\
+                Läuft seit {started} UTC, seit {days}d {hours}h {minutes}m\n\
+                \n\
+                Einreichungs-Backend: {backend}\n\
+                Icon-Einreichungen: {}\n\
+                Prüfmodus: {}\n\
+                Bildmoderation: {}\n\
+                Testlauf: {}",
+                if submissions_available {
+                    "verfügbar"
+                } else {
+                    "deaktiviert, svg2vd fehlt"
+                },
+                on_off(review),
+                on_off(moderation),
+                on_off(dry_run),
+            )
+        }
+        Text::StatsIcons { branch, count } => format!("Icons auf {branch}: {count}"),
+        Text::StatsSubmitted { week, month } => format!(
+            "Über den Bot eingereichte Icons: {week} in den letzten 7 Tagen, {month} in den letzten 30 Tagen"
+        ),
+        Text::StatsOpenMergeRequests { count } => {
+            format!("Offene Icon-Merge-Requests: {count}")
+        }
+        Text::StatsLatestReleases { releases } => format!("Neueste Releases:\n{releases}"),
+        Text::NewSubmission {
+            user,
+            branches,
+            details,
+        } => format!("Neue Icon-Einreichung\nNutzer: {user}\nBranches: {branches}\n\n{details}"),
+        Text::SubmissionDetails {
+            app_path,
+            app,
+            icon_name,
+            description,
+        } => format!(
+            "Paket: {app_path}{}\nIcon: {icon_name}\nBeschreibung: {description}",
+            app.map(|app| format!("\nApp: {app}")).unwrap_or_default()
+        ),
+        Text::ApproveButton => String::from("Annehmen"),
+        Text::RejectButton => String::from("Ablehnen"),
+        Text::OnlyReviewers => String::from("Das können nur Prüfer."),
+        Text::ReviewVerdict {
+            reviewer,
+            approved,
+            icon_names,
+            app_paths,
+        } => format!(
+            "{reviewer} hat die Einreichung von {icon_names} für {app_paths} {}.",
+            if approved { "angenommen" } else { "abgelehnt" }
+        ),
+        Text::ReviewDropped {
+            icon_names,
+            app_paths,
+        } => format!(
+            "Niemand hat die Einreichung von {icon_names} für {app_paths} rechtzeitig geprüft, sie wurde verworfen."
+        ),
+        Text::DownloadFailed => {
+            String::from("Ich konnte deine Datei nicht von Telegram herunterladen.")
+        }
+        Text::DecodeFailed => String::from("Ich konnte das Bild nicht lesen, ist es ein gültiges PNG?"),
+        Text::TraceFailed => String::from("Ich konnte das Bild nicht zu einem Icon nachzeichnen."),
+        Text::VdFailed => {
+            String::from("Ich konnte das Bild nicht in ein VectorDrawable umwandeln.")
+        }
+        Text::GitFailed => String::from("Beim Overlay-Repository ist etwas schiefgelaufen."),
+        Text::GitLabRejected { status } => format!("GitLab hat die Anfrage abgelehnt ({status})."),
+        Text::OtaFailed => String::from(
+            "Ich konnte die neuesten Releases nicht abrufen, bitte versuch es später noch einmal.",
+        ),
+        Text::ServiceUnavailable => String::from(
+            "Ein externer Dienst hat nicht geantwortet, bitte versuch es später noch einmal.",
+        ),
+        Text::InternalError => String::from(
+            "Entschuldigung, bei mir ist etwas schiefgelaufen. Bitte versuch es noch einmal.",
+        ),
+    }
+}
+
+/// The buttons of the submission dialogue. Their callback data stays the
+/// same in every language, so the handlers don't depend on the language a
+/// keyboard was sent in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    YesCorrect,
+    NoWrong,
+    UpdateExisting,
+    Abort,
+    ReplaceIt,
+    PickAnotherName,
+    RemoveBackground,
+    SendAnotherImage,
+    YesCreate,
+    NoAbort,
+    Thinner,
+    Thicker,
+    RemoveSpeckles,
+    MoreDetail,
+    SmootherCurves,
+    ChangeName,
+    DifferentImage,
+    PreviewMergeRequest,
+    AddAnother,
+    Submit,
+    TryAgain,
+    UpdateIt,
+    YesRemove,
+}
+
+impl Button {
+    /// The callback data of the button. These are the English labels the
+    /// buttons had before they were translated, so keyboards sent back then
+    /// still work.
+    pub fn data(self) -> &'static str {
+        match self {
+            Self::YesCorrect => "Yes, this is correct",
+            Self::NoWrong => "No, this is wrong",
+            Self::UpdateExisting => "Update the existing icon",
+            Self::Abort => "Abort",
+            Self::ReplaceIt => "Replace it",
+            Self::PickAnotherName => "Pick another name",
+            Self::RemoveBackground => "Remove background",
+            Self::SendAnotherImage => "Send another image",
+            Self::YesCreate => "Yes, create my request",
+            Self::NoAbort => "No, abort",
+            Self::Thinner => "Thinner",
+            Self::Thicker => "Thicker",
+            Self::RemoveSpeckles => "Remove speckles",
+            Self::MoreDetail => "More detail",
+            Self::SmootherCurves => "Smoother curves",
+            Self::ChangeName => "Change name",
+            Self::DifferentImage => "Use a different image",
+            Self::PreviewMergeRequest => "Preview MR",
+            Self::AddAnother => "Yes, add another",
+            Self::Submit => "No, submit",
+            Self::TryAgain => "Try again",
+            Self::UpdateIt => "Update it",
+            Self::YesRemove => "Yes, remove it",
+        }
+    }
+
+    pub fn label(self, language: Language) -> &'static str {
+        match language {
+            Language::English => self.data(),
+            Language::German => match self {
+                Self::YesCorrect => "Ja, das stimmt",
+                Self::NoWrong => "Nein, das ist falsch",
+                Self::UpdateExisting => "Bestehendes Icon aktualisieren",
+                Self::Abort => "Abbrechen",
+                Self::ReplaceIt => "Ersetzen",
+                Self::PickAnotherName => "Anderen Namen wählen",
+                Self::RemoveBackground => "Hintergrund entfernen",
+                Self::SendAnotherImage => "Anderes Bild schicken",
+                Self::YesCreate => "Ja, Anfrage erstellen",
+                Self::NoAbort => "Nein, abbrechen",
+                Self::Thinner => "Dünner",
+                Self::Thicker => "Dicker",
+                Self::RemoveSpeckles => "Flecken entfernen",
+                Self::MoreDetail => "Mehr Details",
+                Self::SmootherCurves => "Glattere Kurven",
+                Self::ChangeName => "Namen ändern",
+                Self::DifferentImage => "Anderes Bild verwenden",
+                Self::PreviewMergeRequest => "MR-Vorschau",
+                Self::AddAnother => "Ja, noch eins",
+                Self::Submit => "Nein, einreichen",
+                Self::TryAgain => "Erneut versuchen",
+                Self::UpdateIt => "Aktualisieren",
+                Self::YesRemove => "Ja, entfernen",
+            },
+        }
+    }
+
+    pub fn callback(self, language: Language) -> InlineKeyboardButton {
+        InlineKeyboardButton::callback(self.label(language).to_owned(), self.data().to_owned())
+    }
+}
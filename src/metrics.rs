@@ -0,0 +1,284 @@
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt::Write,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use time::OffsetDateTime;
+
+use crate::deadline::Stage;
+
+/// Counters exposed on `/metrics`, shared by all handlers.
+pub struct Metrics {
+    commands: BTreeMap<&'static str, AtomicU64>,
+    submissions_started: AtomicU64,
+    submissions_completed: AtomicU64,
+    submissions_failed: AtomicU64,
+    /// Submissions that ran out of time, by the stage they were in.
+    deadlines_exceeded: BTreeMap<&'static str, AtomicU64>,
+    ota_fetch_errors: AtomicU64,
+    rate_limit_waits: AtomicU64,
+    rate_limit_skips: AtomicU64,
+    /// Unix timestamps, 0 if it never happened.
+    last_release_poll: AtomicU64,
+    last_telegram_contact: AtomicU64,
+}
+
+impl Metrics {
+    /// Counts the commands named in `commands`, others are ignored.
+    pub fn new(commands: &[&'static str]) -> Self {
+        Self {
+            commands: commands
+                .iter()
+                .map(|command| (*command, AtomicU64::new(0)))
+                .collect(),
+            submissions_started: AtomicU64::new(0),
+            submissions_completed: AtomicU64::new(0),
+            submissions_failed: AtomicU64::new(0),
+            deadlines_exceeded: Stage::ALL
+                .iter()
+                .map(|stage| (stage.label(), AtomicU64::new(0)))
+                .collect(),
+            ota_fetch_errors: AtomicU64::new(0),
+            rate_limit_waits: AtomicU64::new(0),
+            rate_limit_skips: AtomicU64::new(0),
+            last_release_poll: AtomicU64::new(0),
+            last_telegram_contact: AtomicU64::new(0),
+        }
+    }
+
+    pub fn command_handled(&self, command: &str) {
+        if let Some(count) = self.commands.get(command) {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn submission_started(&self) {
+        self.submissions_started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn submission_completed(&self) {
+        self.submissions_completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn submission_failed(&self) {
+        self.submissions_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A submission ran out of time while in `stage`.
+    pub fn deadline_exceeded(&self, stage: Stage) {
+        if let Some(count) = self.deadlines_exceeded.get(stage.label()) {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn ota_fetch_failed(&self) {
+        self.ota_fetch_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A request had to wait for the rate limit of its host.
+    pub fn rate_limit_waited(&self) {
+        self.rate_limit_waits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A request was skipped because of the rate limit of its host.
+    pub fn rate_limit_skipped(&self) {
+        self.rate_limit_skips.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn releases_polled(&self) {
+        self.last_release_poll.store(now(), Ordering::Relaxed);
+    }
+
+    pub fn telegram_reached(&self) {
+        self.last_telegram_contact.store(now(), Ordering::Relaxed);
+    }
+
+    /// Whether Telegram answered within the last `max_age`.
+    pub fn is_healthy(&self, max_age: Duration) -> bool {
+        let last_contact = self.last_telegram_contact.load(Ordering::Relaxed);
+
+        last_contact > 0 && now().saturating_sub(last_contact) <= max_age.as_secs()
+    }
+
+    /// Renders the counters in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+
+        text.push_str("# HELP leonardo_commands_total Commands handled, by command.\n");
+        text.push_str("# TYPE leonardo_commands_total counter\n");
+        for (command, count) in &self.commands {
+            let _ = writeln!(
+                text,
+                "leonardo_commands_total{{command=\"{command}\"}} {}",
+                count.load(Ordering::Relaxed)
+            );
+        }
+
+        text.push_str(
+            "# HELP leonardo_deadlines_exceeded_total Submissions that ran out of time, by stage.\n",
+        );
+        text.push_str("# TYPE leonardo_deadlines_exceeded_total counter\n");
+        for (stage, count) in &self.deadlines_exceeded {
+            let _ = writeln!(
+                text,
+                "leonardo_deadlines_exceeded_total{{stage=\"{stage}\"}} {}",
+                count.load(Ordering::Relaxed)
+            );
+        }
+
+        for (name, kind, help, value) in [
+            (
+                "leonardo_submissions_started_total",
+                "counter",
+                "Icon submissions started.",
+                &self.submissions_started,
+            ),
+            (
+                "leonardo_submissions_completed_total",
+                "counter",
+                "Icon submissions that opened or updated their merge requests.",
+                &self.submissions_completed,
+            ),
+            (
+                "leonardo_submissions_failed_total",
+                "counter",
+                "Icon submissions that failed while being submitted.",
+                &self.submissions_failed,
+            ),
+            (
+                "leonardo_ota_fetch_errors_total",
+                "counter",
+                "Failed fetches of the OTA metadata.",
+                &self.ota_fetch_errors,
+            ),
+            (
+                "leonardo_rate_limit_waits_total",
+                "counter",
+                "Requests to external hosts that waited for the rate limit.",
+                &self.rate_limit_waits,
+            ),
+            (
+                "leonardo_rate_limit_skips_total",
+                "counter",
+                "Requests to external hosts skipped because of the rate limit.",
+                &self.rate_limit_skips,
+            ),
+            (
+                "leonardo_last_release_poll_timestamp_seconds",
+                "gauge",
+                "When the OTA metadata was last fetched successfully.",
+                &self.last_release_poll,
+            ),
+            (
+                "leonardo_last_telegram_contact_timestamp_seconds",
+                "gauge",
+                "When Telegram last answered.",
+                &self.last_telegram_contact,
+            ),
+        ] {
+            let _ = writeln!(text, "# HELP {name} {help}");
+            let _ = writeln!(text, "# TYPE {name} {kind}");
+            let _ = writeln!(text, "{name} {}", value.load(Ordering::Relaxed));
+        }
+
+        text
+    }
+}
+
+fn now() -> u64 {
+    OffsetDateTime::now_utc().unix_timestamp().max(0) as u64
+}
+
+/// Serves `/healthz` and `/metrics` on all interfaces at `port` until the
+/// process exits. `/healthz` fails once Telegram didn't answer for longer
+/// than `max_age`.
+pub async fn serve(port: u16, metrics: Arc<Metrics>, max_age: Duration) {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let metrics = metrics.clone();
+
+                async move { Ok::<_, Infallible>(respond(&request, &metrics, max_age)) }
+            }))
+        }
+    });
+
+    let server = match Server::try_bind(&addr) {
+        Ok(server) => server,
+        Err(e) => {
+            log::error!("Failed to listen for health checks on {addr}: {e}");
+
+            return;
+        }
+    };
+
+    log::info!("Serving health checks and metrics on {addr}");
+
+    if let Err(e) = server.serve(make_service).await {
+        log::error!("The health check server stopped: {e}");
+    }
+}
+
+fn respond(request: &Request<Body>, metrics: &Metrics, max_age: Duration) -> Response<Body> {
+    let (status, content_type, body) = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/healthz") if metrics.is_healthy(max_age) => {
+            (StatusCode::OK, "text/plain", String::from("ok\n"))
+        }
+        (&Method::GET, "/healthz") => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "text/plain",
+            String::from("telegram unreachable\n"),
+        ),
+        (&Method::GET, "/metrics") => (
+            StatusCode::OK,
+            "text/plain; version=0.0.4",
+            metrics.render(),
+        ),
+        _ => (
+            StatusCode::NOT_FOUND,
+            "text/plain",
+            String::from("not found\n"),
+        ),
+    };
+
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exceeded_deadlines_are_counted_by_stage() {
+        let metrics = Metrics::new(&[]);
+        metrics.deadline_exceeded(Stage::Trace);
+        metrics.deadline_exceeded(Stage::Trace);
+        metrics.deadline_exceeded(Stage::Push);
+
+        let text = metrics.render();
+
+        assert!(text.contains("leonardo_deadlines_exceeded_total{stage=\"trace\"} 2\n"));
+        assert!(text.contains("leonardo_deadlines_exceeded_total{stage=\"push\"} 1\n"));
+        assert!(text.contains("leonardo_deadlines_exceeded_total{stage=\"download\"} 0\n"));
+    }
+}
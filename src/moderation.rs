@@ -0,0 +1,329 @@
+use std::{
+    collections::HashMap, error::Error, fmt, future::Future, pin::Pin, sync::Mutex, time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{access::AccessList, config::Config};
+
+/// Outcome of screening an uploaded image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Allowed,
+    Rejected,
+}
+
+/// The verdict of a [`ModerationBackend`], or why it couldn't give one.
+pub type VerdictFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Verdict, Box<dyn Error + Send + Sync>>> + Send + 'a>>;
+
+/// Judges uploaded images for [`Moderator::screen`].
+pub trait ModerationBackend: Send + Sync {
+    /// Fails if `image` couldn't be judged.
+    fn judge<'a>(&'a self, image: &'a [u8]) -> VerdictFuture<'a>;
+}
+
+#[derive(Deserialize)]
+struct VerdictResponse {
+    rejected: bool,
+}
+
+/// Posts the raw image as request body to an HTTP endpoint, which answers
+/// with `{"rejected": bool}`.
+pub struct HttpModeration {
+    client: reqwest::Client,
+    url: String,
+    timeout: Duration,
+}
+
+impl HttpModeration {
+    pub fn new(client: reqwest::Client, url: String, timeout: Duration) -> Self {
+        Self {
+            client,
+            url,
+            timeout,
+        }
+    }
+}
+
+impl ModerationBackend for HttpModeration {
+    fn judge<'a>(&'a self, image: &'a [u8]) -> VerdictFuture<'a> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .post(&self.url)
+                .timeout(self.timeout)
+                .header("Content-Type", "application/octet-stream")
+                .body(image.to_vec())
+                .send()
+                .await?
+                .error_for_status()?;
+
+            Ok(match response.json::<VerdictResponse>().await? {
+                VerdictResponse { rejected: true } => Verdict::Rejected,
+                VerdictResponse { rejected: false } => Verdict::Allowed,
+            })
+        })
+    }
+}
+
+/// A rejected upload as it is audited. Only the size of the image is kept,
+/// the image itself is never stored.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rejection {
+    pub user_id: i64,
+    pub size: usize,
+    /// How many uploads of the user were rejected so far, this one included.
+    pub rejections: u32,
+    /// Whether the user was banned because of this rejection.
+    pub banned: bool,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#rejection user={} bytes={} rejections={} banned={}",
+            self.user_id, self.size, self.rejections, self.banned
+        )
+    }
+}
+
+/// Screens uploaded images before they are processed. Without a backend
+/// every upload is allowed.
+pub struct Moderator {
+    backend: Option<Box<dyn ModerationBackend>>,
+    /// Rejections after which a user is banned, 0 to never ban.
+    max_rejections: u32,
+    rejections: Mutex<HashMap<i64, u32>>,
+}
+
+impl Moderator {
+    /// Screens with the HTTP endpoint in `config`, if one is set.
+    pub fn new(config: &Config, client: reqwest::Client) -> Self {
+        let backend = config.moderation_url.clone().map(|url| {
+            Box::new(HttpModeration::new(client, url, config.moderation_timeout))
+                as Box<dyn ModerationBackend>
+        });
+
+        Self::with_backend(backend, config.moderation_max_rejections)
+    }
+
+    pub fn with_backend(backend: Option<Box<dyn ModerationBackend>>, max_rejections: u32) -> Self {
+        Self {
+            backend,
+            max_rejections,
+            rejections: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Screens an image uploaded by `user_id`. Returns the rejection to
+    /// audit if it was rejected. Users whose uploads were rejected too often
+    /// are banned in `access`. If the backend fails, the image is allowed so
+    /// an outage doesn't block all submissions.
+    pub async fn screen(
+        &self,
+        access: &AccessList,
+        user_id: i64,
+        image: &[u8],
+    ) -> Option<Rejection> {
+        let backend = self.backend.as_ref()?;
+
+        match backend.judge(image).await {
+            Ok(Verdict::Allowed) => return None,
+            Ok(Verdict::Rejected) => {}
+            Err(e) => {
+                log::warn!("Moderation unavailable, allowing upload: {e}");
+
+                return None;
+            }
+        }
+
+        let rejections = {
+            let mut rejections = self.rejections.lock().unwrap();
+            let count = rejections.entry(user_id).or_insert(0);
+            *count += 1;
+            *count
+        };
+        let banned =
+            self.max_rejections > 0 && rejections >= self.max_rejections && access.ban(user_id);
+        let rejection = Rejection {
+            user_id,
+            size: image.len(),
+            rejections,
+            banned,
+        };
+
+        log::warn!("Rejected an upload: {rejection}");
+
+        Some(rejection)
+    }
+
+    /// Whether uploads are screened at all.
+    pub fn is_enabled(&self) -> bool {
+        self.backend.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        matchers::{body_bytes, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    const USER_ID: i64 = 42;
+    const IMAGE: &[u8] = b"image content";
+
+    struct Fixed(Verdict);
+
+    impl ModerationBackend for Fixed {
+        fn judge<'a>(&'a self, _image: &'a [u8]) -> VerdictFuture<'a> {
+            let verdict = self.0;
+
+            Box::pin(async move { Ok(verdict) })
+        }
+    }
+
+    /// An access list whose banlist is kept in `dir`.
+    fn access_list(dir: &tempfile::TempDir) -> AccessList {
+        let mut config = Config::for_tests();
+        config.banlist_path = dir.path().join("banlist.txt").display().to_string();
+
+        AccessList::load(&config)
+    }
+
+    /// A moderator asking a mock verdict server that answers with `response`.
+    async fn http_moderator(response: ResponseTemplate) -> (Moderator, MockServer) {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_bytes(IMAGE))
+            .respond_with(response)
+            .mount(&server)
+            .await;
+
+        let backend = HttpModeration::new(
+            reqwest::Client::new(),
+            server.uri(),
+            Duration::from_millis(200),
+        );
+
+        (Moderator::with_backend(Some(Box::new(backend)), 3), server)
+    }
+
+    #[tokio::test]
+    async fn without_backend_everything_is_allowed() {
+        let dir = tempfile::tempdir().unwrap();
+        let moderator = Moderator::with_backend(None, 3);
+
+        assert!(!moderator.is_enabled());
+        assert_eq!(
+            moderator.screen(&access_list(&dir), USER_ID, IMAGE).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn repeated_rejections_ban_the_user() {
+        let dir = tempfile::tempdir().unwrap();
+        let access = access_list(&dir);
+        let moderator = Moderator::with_backend(Some(Box::new(Fixed(Verdict::Rejected))), 2);
+
+        let first = moderator.screen(&access, USER_ID, IMAGE).await.unwrap();
+        assert_eq!(first.rejections, 1);
+        assert!(!first.banned);
+        assert!(access.banned().is_empty());
+
+        let second = moderator.screen(&access, USER_ID, IMAGE).await.unwrap();
+        assert_eq!(second.rejections, 2);
+        assert!(second.banned);
+        assert_eq!(access.banned(), [USER_ID]);
+    }
+
+    #[tokio::test]
+    async fn zero_max_rejections_never_bans() {
+        let dir = tempfile::tempdir().unwrap();
+        let access = access_list(&dir);
+        let moderator = Moderator::with_backend(Some(Box::new(Fixed(Verdict::Rejected))), 0);
+
+        for _ in 0..5 {
+            assert!(
+                !moderator
+                    .screen(&access, USER_ID, IMAGE)
+                    .await
+                    .unwrap()
+                    .banned
+            );
+        }
+        assert!(access.banned().is_empty());
+    }
+
+    #[tokio::test]
+    async fn http_verdicts_are_followed() {
+        let dir = tempfile::tempdir().unwrap();
+        let access = access_list(&dir);
+
+        let (moderator, _server) =
+            http_moderator(ResponseTemplate::new(200).set_body_string(r#"{"rejected": false}"#))
+                .await;
+        assert_eq!(moderator.screen(&access, USER_ID, IMAGE).await, None);
+
+        let (moderator, _server) =
+            http_moderator(ResponseTemplate::new(200).set_body_string(r#"{"rejected": true}"#))
+                .await;
+        assert!(moderator.screen(&access, USER_ID, IMAGE).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn timeout_fails_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let (moderator, _server) = http_moderator(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"rejected": true}"#)
+                .set_delay(Duration::from_secs(2)),
+        )
+        .await;
+
+        assert_eq!(
+            moderator.screen(&access_list(&dir), USER_ID, IMAGE).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn server_error_and_garbage_fail_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let access = access_list(&dir);
+
+        let (moderator, _server) = http_moderator(ResponseTemplate::new(500)).await;
+        assert_eq!(moderator.screen(&access, USER_ID, IMAGE).await, None);
+
+        let (moderator, _server) =
+            http_moderator(ResponseTemplate::new(200).set_body_string("<html>")).await;
+        assert_eq!(moderator.screen(&access, USER_ID, IMAGE).await, None);
+    }
+
+    #[tokio::test]
+    async fn audit_has_no_image_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let (moderator, _server) =
+            http_moderator(ResponseTemplate::new(200).set_body_string(r#"{"rejected": true}"#))
+                .await;
+
+        let rejection = moderator
+            .screen(&access_list(&dir), USER_ID, IMAGE)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            rejection.to_string(),
+            format!(
+                "#rejection user={USER_ID} bytes={} rejections=1 banned=false",
+                IMAGE.len()
+            )
+        );
+        assert!(!rejection.to_string().contains("image content"));
+    }
+}
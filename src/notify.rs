@@ -0,0 +1,162 @@
+use std::{collections::HashMap, fmt, sync::Mutex, time::Duration};
+
+use teloxide::{
+    prelude::*,
+    types::{ChatId, InputFile},
+};
+use tokio::time::Instant;
+
+use crate::{deadline::Stage, moderation::Rejection, LeonardoBot};
+
+/// How long an identical failure is not reported again.
+const REPEAT_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Reports failures of the submission pipeline to the maintainers, if
+/// `MAINTAINER_CHAT_ID` is set.
+pub struct MaintainerNotifier {
+    chat_id: Option<ChatId>,
+    /// When each failure was last reported, keyed by stage and error text.
+    reported: Mutex<HashMap<String, Instant>>,
+}
+
+impl MaintainerNotifier {
+    pub fn new(chat_id: Option<ChatId>) -> Self {
+        Self {
+            chat_id,
+            reported: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sends the failure of `user`'s submission of `icon_name` for `package`
+    /// to the maintainer chat. Never fails, a notification that can't be
+    /// sent is only logged.
+    pub async fn pipeline_failed(
+        &self,
+        bot: &LeonardoBot,
+        user: ChatId,
+        stage: Option<Stage>,
+        package: &str,
+        icon_name: Option<&str>,
+        error: &dyn fmt::Display,
+    ) {
+        let chat_id = match self.chat_id {
+            Some(chat_id) => chat_id,
+            None => return,
+        };
+
+        let stage = match stage {
+            Some(stage) => format!(" while {stage}"),
+            None => String::new(),
+        };
+        let error = error.to_string();
+
+        if !self.first_in_interval(format!("{stage}: {error}")) {
+            log::debug!("Not reporting repeated failure{stage}: {error}");
+
+            return;
+        }
+
+        let username = match username(bot, user).await {
+            Some(username) => format!(" (@{username})"),
+            None => String::new(),
+        };
+        let text = format!(
+            "Icon submission failed{stage}\nUser: {}{username}\nIcon: {} for {package}\nError: {error}",
+            user.0,
+            icon_name.unwrap_or("(no name yet)"),
+        );
+
+        if let Err(e) = bot.send_message(chat_id, text).await {
+            log::warn!("Failed to notify the maintainer chat: {e}");
+        }
+    }
+
+    /// Whether `key` wasn't reported within the last [`REPEAT_INTERVAL`].
+    fn first_in_interval(&self, key: String) -> bool {
+        let now = Instant::now();
+        let mut reported = self.reported.lock().unwrap();
+        reported.retain(|_, at| now.duration_since(*at) < REPEAT_INTERVAL);
+
+        if reported.contains_key(&key) {
+            false
+        } else {
+            reported.insert(key, now);
+
+            true
+        }
+    }
+}
+
+/// A merge request opened or updated by a submission.
+pub struct AuditEntry<'a> {
+    pub user: ChatId,
+    pub package: &'a str,
+    pub icon_name: &'a str,
+    pub target_branch: &'a str,
+    pub merge_request_url: &'a str,
+    pub updated: bool,
+}
+
+/// Posts `rejection` to `audit_chat_id`, if set. Never fails, like
+/// [`post_audit_entry`].
+pub async fn post_rejection(
+    bot: &LeonardoBot,
+    audit_chat_id: Option<ChatId>,
+    rejection: &Rejection,
+) {
+    let chat_id = match audit_chat_id {
+        Some(chat_id) => chat_id,
+        None => return,
+    };
+
+    if let Err(e) = bot.send_message(chat_id, rejection.to_string()).await {
+        log::warn!("Failed to post the audit entry for {rejection}: {e}");
+    }
+}
+
+/// Posts `entry` with the submitted `document` to `audit_chat_id`, if set.
+/// Never fails, an entry that can't be posted is only logged.
+pub async fn post_audit_entry(
+    bot: &LeonardoBot,
+    audit_chat_id: Option<ChatId>,
+    entry: AuditEntry<'_>,
+    document: InputFile,
+) {
+    let chat_id = match audit_chat_id {
+        Some(chat_id) => chat_id,
+        None => return,
+    };
+
+    let username = match username(bot, entry.user).await {
+        Some(username) => format!("@{username}"),
+        None => String::from("-"),
+    };
+
+    // A single line of key=value pairs, so the channel can be searched and
+    // exported easily.
+    let caption = format!(
+        "#submission action={} user={} username={username} package={} icon={} branch={} mr={}",
+        if entry.updated { "updated" } else { "created" },
+        entry.user.0,
+        entry.package,
+        entry.icon_name,
+        entry.target_branch,
+        entry.merge_request_url,
+    );
+
+    if let Err(e) = bot.send_document(chat_id, document).caption(caption).await {
+        log::warn!(
+            "Failed to post the audit entry for {}: {e}",
+            entry.merge_request_url
+        );
+    }
+}
+
+/// Looks up the username of `user`, private chats share their id with the
+/// user.
+pub async fn username(bot: &LeonardoBot, user: ChatId) -> Option<String> {
+    match bot.get_chat(user).await {
+        Ok(chat) => chat.username().map(str::to_owned),
+        Err(_) => None,
+    }
+}
@@ -0,0 +1,823 @@
+use std::{
+    env,
+    error::Error,
+    fmt, fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use git2::{
+    build::CheckoutBuilder, Cred, CredentialType, DiffStatsFormat, ErrorClass, ErrorCode,
+    FetchOptions, IndexAddOption, Oid, PushOptions, RemoteCallbacks, Repository, Signature, Tree,
+};
+use tempfile::TempDir;
+
+use crate::{config::Config, icon_map::IconMap};
+
+pub const DRAWABLE_DIR: &str = "PixelLauncherIconsOverlay/res/drawable";
+pub const ICON_MAP_PATH: &str = "PixelLauncherIconsOverlay/res/xml/grayscale_icon_map.xml";
+
+const CACHE_DIR_NAME: &str = "leonardo-overlay.git";
+
+/// Returned when committing or pushing an icon failed. The icon was prepared
+/// in a temporary clone, so nothing was left behind.
+#[derive(Debug)]
+pub struct PushFailed(Box<dyn Error + Send + Sync>);
+
+impl fmt::Display for PushFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Error for PushFailed {}
+
+/// Returned when origin rejected every credential the bot has.
+#[derive(Debug)]
+struct RemoteAuthFailed(git2::Error);
+
+impl fmt::Display for RemoteAuthFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "authentication with the overlay remote failed, check GITLAB_TOKEN for HTTPS or SSH_KEY_PATH and the ssh agent for ssh remotes: {}",
+            self.0.message()
+        )
+    }
+}
+
+impl Error for RemoteAuthFailed {}
+
+/// A drawable to add to the overlay and the package to map to it.
+#[derive(Clone, Debug)]
+pub struct NewIcon {
+    pub icon_name: String,
+    pub app_path: String,
+    pub vd_bytes: Vec<u8>,
+}
+
+/// Everything needed to commit one or more icons to the overlay.
+#[derive(Clone, Debug)]
+pub struct IconCommit {
+    pub target_branch: String,
+    pub branch_name: String,
+    pub icons: Vec<NewIcon>,
+    pub commit_msg: String,
+    /// Whether `branch_name` may replace an existing remote branch.
+    pub force: bool,
+}
+
+/// Everything needed to remove the icon of a package from the overlay.
+#[derive(Clone, Debug)]
+pub struct IconRemoval {
+    pub target_branch: String,
+    pub branch_name: String,
+    pub app_path: String,
+    pub commit_msg: String,
+    /// Whether `branch_name` may replace an existing remote branch.
+    pub force: bool,
+}
+
+/// A drawable that already exists in the overlay.
+pub struct ExistingDrawable {
+    pub branch: String,
+    /// The package the drawable is mapped to, if any.
+    pub package: Option<String>,
+}
+
+/// Path of the file behind a `@drawable/...` reference, relative to the
+/// repository root.
+pub fn drawable_file(drawable: &str) -> Option<String> {
+    drawable
+        .strip_prefix("@drawable/")
+        .map(|name| format!("{DRAWABLE_DIR}/{name}.xml"))
+}
+
+/// The overlay remote, `OVERLAY_REMOTE_URL` or else the origin of the
+/// checkout at `PATH_TO_ICONS_OVERLAY`.
+fn remote_url(config: &Config) -> Result<String, Box<dyn Error + Send + Sync>> {
+    if let Some(url) = &config.overlay_remote_url {
+        return Ok(url.clone());
+    }
+
+    let path = config
+        .overlay_path
+        .as_ref()
+        .ok_or("neither OVERLAY_REMOTE_URL nor PATH_TO_ICONS_OVERLAY is set")?;
+    let repo = Repository::open(path)?;
+    let remote = repo.find_remote("origin")?;
+    let url = remote
+        .url()
+        .ok_or("origin of the overlay checkout has no URL")?;
+
+    Ok(url.to_owned())
+}
+
+/// Opens the local cache of the overlay. This is the checkout at
+/// `PATH_TO_ICONS_OVERLAY` if there is one, otherwise a bare repository in the
+/// temporary directory. Only its objects and `refs/remotes/origin/*` are used.
+fn open_cache(config: &Config) -> Result<Repository, git2::Error> {
+    match &config.overlay_path {
+        Some(path) => Repository::open(path),
+        None => {
+            let path = env::temp_dir().join(CACHE_DIR_NAME);
+
+            Repository::open_bare(&path).or_else(|_| Repository::init_bare(&path))
+        }
+    }
+}
+
+/// Fetches `branches` from the overlay remote into the cache, so icons are
+/// always committed on top of the latest state.
+pub fn refresh_cache(
+    config: &Config,
+    branches: &[String],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    fetch_into_cache(config, &open_cache(config)?, branches)
+}
+
+fn fetch_into_cache(
+    config: &Config,
+    cache: &Repository,
+    branches: &[String],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut remote = cache.remote_anonymous(&remote_url(config)?)?;
+
+    let refspecs = branches
+        .iter()
+        .map(|branch| format!("+refs/heads/{branch}:refs/remotes/origin/{branch}"))
+        .collect::<Vec<_>>();
+    let mut fetch_opts = FetchOptions::new();
+    fetch_opts.remote_callbacks(remote_callbacks(config));
+    remote
+        .fetch(&refspecs, Some(&mut fetch_opts), None)
+        .map_err(remote_error)?;
+
+    Ok(())
+}
+
+/// The tree of `branch` as of the last fetch into the cache. Branches the
+/// cache has never seen are fetched first.
+fn cached_tree<'r>(
+    config: &Config,
+    cache: &'r Repository,
+    branch: &str,
+) -> Result<Tree<'r>, Box<dyn Error + Send + Sync>> {
+    let reference = format!("refs/remotes/origin/{branch}");
+
+    if cache.find_reference(&reference).is_err() {
+        fetch_into_cache(config, cache, &[branch.to_owned()])?;
+    }
+
+    Ok(cache.find_reference(&reference)?.peel_to_tree()?)
+}
+
+fn read_icon_map(cache: &Repository, tree: &Tree) -> Result<IconMap, Box<dyn Error + Send + Sync>> {
+    let blob = tree
+        .get_path(Path::new(ICON_MAP_PATH))?
+        .to_object(cache)?
+        .peel_to_blob()?;
+
+    Ok(IconMap::parse(std::str::from_utf8(blob.content())?)?)
+}
+
+/// Looks for a drawable named `icon_name` on any of `target_branches`.
+pub fn find_existing_drawable(
+    config: &Config,
+    target_branches: &[String],
+    icon_name: &str,
+) -> Result<Option<ExistingDrawable>, Box<dyn Error + Send + Sync>> {
+    let cache = open_cache(config)?;
+    let drawable_path = Path::new(DRAWABLE_DIR).join(format!("themed_icon_{icon_name}.xml"));
+    let drawable = format!("@drawable/themed_icon_{icon_name}");
+
+    for branch in target_branches {
+        let tree = cached_tree(config, &cache, branch)?;
+
+        if tree.get_path(&drawable_path).is_err() {
+            continue;
+        }
+
+        let package = read_icon_map(&cache, &tree).ok().and_then(|icon_map| {
+            icon_map
+                .icons
+                .into_iter()
+                .find(|icon| icon.drawable == drawable)
+                .map(|icon| icon.package)
+        });
+
+        return Ok(Some(ExistingDrawable {
+            branch: branch.clone(),
+            package,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Returns the drawable `package` is mapped to on each of `target_branches`,
+/// without the `@drawable/` prefix.
+pub fn mapped_drawables(
+    config: &Config,
+    target_branches: &[String],
+    package: &str,
+) -> Result<Vec<Option<String>>, Box<dyn Error + Send + Sync>> {
+    let cache = open_cache(config)?;
+
+    target_branches
+        .iter()
+        .map(|branch| -> Result<_, Box<dyn Error + Send + Sync>> {
+            let icon_map = read_icon_map(&cache, &cached_tree(config, &cache, branch)?)?;
+
+            Ok(icon_map.drawable_for(package).map(|drawable| {
+                drawable
+                    .strip_prefix("@drawable/")
+                    .unwrap_or(drawable)
+                    .to_owned()
+            }))
+        })
+        .collect()
+}
+
+/// The icon map of `branch` as of the last fetch into the cache.
+pub fn icon_map(config: &Config, branch: &str) -> Result<IconMap, Box<dyn Error + Send + Sync>> {
+    let cache = open_cache(config)?;
+
+    read_icon_map(&cache, &cached_tree(config, &cache, branch)?)
+}
+
+/// Adds the icons on top of their target branch in a new branch and pushes
+/// it. The work happens in a temporary clone that is removed again afterwards,
+/// failures are returned as [`PushFailed`]. In dry-run mode nothing is
+/// pushed and the diffstat of the commit is returned instead.
+pub fn commit_and_push_icon(
+    config: &Config,
+    icon: &IconCommit,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    try_commit_and_push_icon(config, icon).map_err(|e| {
+        log::error!("Failed to push {}: {e}", icon.branch_name);
+
+        PushFailed(e).into()
+    })
+}
+
+fn try_commit_and_push_icon(
+    config: &Config,
+    icon: &IconCommit,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    let IconCommit {
+        target_branch,
+        branch_name,
+        icons,
+        commit_msg,
+        force,
+    } = icon;
+
+    let cache = open_cache(config)?;
+    let workspace = Workspace::checkout(config, &cache, target_branch, branch_name)?;
+
+    let xml_file_path = workspace.path().join(ICON_MAP_PATH);
+    let mut icon_map = IconMap::parse(&fs::read_to_string(&xml_file_path)?)?;
+    let mut orphaned = Vec::new();
+
+    for NewIcon {
+        icon_name,
+        app_path,
+        vd_bytes,
+    } in icons
+    {
+        let vd_file_path = workspace
+            .path()
+            .join(DRAWABLE_DIR)
+            .join(format!("themed_icon_{icon_name}.xml"));
+
+        // When an icon is renamed, its old drawable is dropped unless another
+        // package still uses it.
+        orphaned.extend(icon_map.replace(
+            format!("@drawable/themed_icon_{icon_name}"),
+            app_path.to_owned(),
+        ));
+
+        fs::write(vd_file_path, vd_bytes)?;
+    }
+
+    fs::write(xml_file_path, icon_map.to_xml())?;
+
+    // A later icon of the batch may have taken over the old drawable.
+    let orphaned = orphaned
+        .into_iter()
+        .filter(|drawable| {
+            !icon_map
+                .icons
+                .iter()
+                .any(|mapped| &mapped.drawable == drawable)
+        })
+        .filter_map(|drawable| drawable_file(&drawable))
+        .collect();
+
+    workspace.commit_and_push(config, &cache, orphaned, commit_msg, *force)
+}
+
+/// Removes the icon map entry of the package, and its drawable if no other
+/// package uses it, in a new branch on top of the target branch and pushes
+/// it. Like [`commit_and_push_icon`], failures are returned as [`PushFailed`].
+pub fn commit_and_push_removal(
+    config: &Config,
+    removal: &IconRemoval,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    try_commit_and_push_removal(config, removal).map_err(|e| {
+        log::error!("Failed to push {}: {e}", removal.branch_name);
+
+        PushFailed(e).into()
+    })
+}
+
+fn try_commit_and_push_removal(
+    config: &Config,
+    removal: &IconRemoval,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    let IconRemoval {
+        target_branch,
+        branch_name,
+        app_path,
+        commit_msg,
+        force,
+    } = removal;
+
+    let cache = open_cache(config)?;
+    let workspace = Workspace::checkout(config, &cache, target_branch, branch_name)?;
+
+    let xml_file_path = workspace.path().join(ICON_MAP_PATH);
+    let mut icon_map = IconMap::parse(&fs::read_to_string(&xml_file_path)?)?;
+    let orphaned = icon_map
+        .remove(app_path)
+        .and_then(|drawable| drawable_file(&drawable));
+
+    fs::write(xml_file_path, icon_map.to_xml())?;
+
+    workspace.commit_and_push(
+        config,
+        &cache,
+        orphaned.into_iter().collect(),
+        commit_msg,
+        *force,
+    )
+}
+
+/// A temporary clone of the overlay with a new branch checked out on top of
+/// its target branch. The clone is removed again when this is dropped.
+struct Workspace {
+    // Declared first so the repository is closed before the directory is
+    // removed.
+    repo: Repository,
+    dir: TempDir,
+    branch_name: String,
+    target: Oid,
+}
+
+impl Workspace {
+    fn checkout(
+        config: &Config,
+        cache: &Repository,
+        target_branch: &str,
+        branch_name: &str,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let target_refspec = format!("refs/remotes/origin/{target_branch}");
+
+        let dir = tempfile::tempdir()?;
+        let repo = Repository::init(dir.path())?;
+
+        // Copying the branch out of the cache is a lot faster than cloning.
+        let cache_path = cache
+            .path()
+            .to_str()
+            .ok_or("the overlay cache path is not valid UTF-8")?;
+        repo.remote_anonymous(cache_path)?.fetch(
+            &[format!("+{target_refspec}:{target_refspec}")],
+            None,
+            None,
+        )?;
+        repo.remote("origin", &remote_url(config)?)?;
+
+        let target = {
+            let target = repo.find_reference(&target_refspec)?.peel_to_commit()?;
+            repo.branch(branch_name, &target, true)?;
+
+            target.id()
+        };
+        repo.set_head(&format!("refs/heads/{branch_name}"))?;
+        repo.checkout_head(Some(CheckoutBuilder::new().force()))?;
+
+        Ok(Self {
+            dir,
+            repo,
+            branch_name: branch_name.to_owned(),
+            target,
+        })
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Commits every change in the working directory plus the removal of
+    /// the `orphaned` files that exist, and pushes the branch. In dry-run mode
+    /// the diffstat of the commit is returned instead of pushing it.
+    fn commit_and_push(
+        &self,
+        config: &Config,
+        cache: &Repository,
+        orphaned: Vec<String>,
+        commit_msg: &str,
+        force: bool,
+    ) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+        let repo = &self.repo;
+
+        let tree_id = {
+            let mut index = repo.index()?;
+
+            for orphaned in &orphaned {
+                if self.path().join(orphaned).exists() {
+                    fs::remove_file(self.path().join(orphaned))?;
+                    index.remove_path(Path::new(orphaned))?;
+                }
+            }
+
+            index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
+            index.write()?;
+            index.write_tree()?
+        };
+        let tree = repo.find_tree(tree_id)?;
+        let target = repo.find_commit(self.target)?;
+
+        let signature = match (&config.git_author_name, &config.git_author_email) {
+            (Some(name), Some(email)) => Signature::now(name, email)?,
+            // The cache carries the identity configured for the bot.
+            _ => cache.signature()?,
+        };
+
+        match &config.git_signing_key {
+            Some(key_path) => {
+                let buffer = repo.commit_create_buffer(
+                    &signature,
+                    &signature,
+                    commit_msg,
+                    &tree,
+                    &[&target],
+                )?;
+                let content = buffer.as_str().ok_or("the commit is not valid UTF-8")?;
+                let commit = repo.commit_signed(content, &ssh_sign(key_path, content)?, None)?;
+
+                repo.reference(
+                    &format!("refs/heads/{}", self.branch_name),
+                    commit,
+                    true,
+                    commit_msg,
+                )?;
+            }
+            None => {
+                repo.commit(
+                    Some("HEAD"),
+                    &signature,
+                    &signature,
+                    commit_msg,
+                    &tree,
+                    &[&target],
+                )?;
+            }
+        }
+
+        if config.dry_run {
+            let diff = repo.diff_tree_to_tree(Some(&target.tree()?), Some(&tree), None)?;
+            let stats = diff.stats()?.to_buf(DiffStatsFormat::FULL, 80)?;
+
+            return Ok(Some(stats.as_str().unwrap_or_default().to_owned()));
+        }
+
+        // Replacing an existing remote branch needs a force push.
+        let branch_refspec = if force {
+            format!("+refs/heads/{}", self.branch_name)
+        } else {
+            format!("refs/heads/{}", self.branch_name)
+        };
+
+        let mut push_opts = PushOptions::new();
+        let mut remote = repo.find_remote("origin")?;
+        push_opts.remote_callbacks(remote_callbacks(config));
+        remote
+            .push(&[&branch_refspec], Some(&mut push_opts))
+            .map_err(remote_error)?;
+
+        Ok(None)
+    }
+}
+
+/// Signs the commit `content` with the ssh key at `key_path` like git does
+/// with `gpg.format=ssh`, and returns the armored signature.
+fn ssh_sign(key_path: &str, content: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let mut child = Command::new("ssh-keygen")
+        .args(["-Y", "sign", "-n", "git", "-f", key_path])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    child
+        .stdin
+        .take()
+        .ok_or("ssh-keygen has no stdin")?
+        .write_all(content.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "ssh-keygen failed to sign the commit: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Authenticates against origin. HTTPS remotes use `GITLAB_TOKEN`, ssh
+/// remotes the key file at `SSH_KEY_PATH` (or `SSH_KEY`) and then the ssh
+/// agent. Each is tried once, libgit2 asks again after a rejection.
+fn remote_callbacks<'a>(config: &Config) -> RemoteCallbacks<'a> {
+    let gitlab_token = config.gitlab_token.clone();
+    let ssh_key_path = config.ssh_key_path.clone();
+    let mut tried_token = false;
+    let mut tried_key_file = false;
+    let mut tried_agent = false;
+
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username_from_url, allowed_types| {
+        let username = username_from_url.unwrap_or("git");
+
+        if url.starts_with("https://")
+            && allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT)
+            && !tried_token
+        {
+            tried_token = true;
+
+            return Cred::userpass_plaintext("oauth2", &gitlab_token);
+        }
+
+        if allowed_types.contains(CredentialType::SSH_KEY) {
+            if !tried_key_file {
+                tried_key_file = true;
+
+                if let Some(key) = &ssh_key_path {
+                    return Cred::ssh_key(username, None, Path::new(key), None);
+                }
+            }
+
+            if !tried_agent {
+                tried_agent = true;
+
+                return Cred::ssh_key_from_agent(username);
+            }
+        }
+
+        Err(git2::Error::new(
+            ErrorCode::Auth,
+            ErrorClass::Net,
+            format!("no accepted credentials left for {url}"),
+        ))
+    });
+
+    callbacks
+}
+
+/// Turns authentication failures of fetches and pushes into
+/// [`RemoteAuthFailed`].
+fn remote_error(e: git2::Error) -> Box<dyn Error + Send + Sync> {
+    if e.code() == ErrorCode::Auth
+        || (matches!(e.class(), ErrorClass::Ssh | ErrorClass::Http)
+            && e.message().to_lowercase().contains("auth"))
+    {
+        log::error!("Authentication with the overlay remote failed: {e}");
+
+        RemoteAuthFailed(e).into()
+    } else {
+        e.into()
+    }
+}
+
+/// A checkout of the overlay in `dir` with the branches `12.1` and `13`. Only
+/// `12.1` has the drawable `themed_icon_leonardo` and maps
+/// `org.example.leonardo` to it.
+#[cfg(test)]
+pub fn two_branch_overlay(dir: &Path) -> Config {
+    let cache = Repository::init(dir.join("overlay")).unwrap();
+    let workdir = cache.workdir().unwrap().to_owned();
+    let signature = Signature::now("Leonardo", "leonardo@example.com").unwrap();
+    let drawable_path = format!("{DRAWABLE_DIR}/themed_icon_leonardo.xml");
+    let mapped = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<icons>\n    <icon drawable=\"@drawable/themed_icon_leonardo\" package=\"org.example.leonardo\" />\n</icons>\n";
+    let empty = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<icons>\n</icons>\n";
+
+    let branches: [(&str, &[(&str, &str)]); 2] = [
+        (
+            "12.1",
+            &[
+                (ICON_MAP_PATH, mapped),
+                (drawable_path.as_str(), "<vector />"),
+            ],
+        ),
+        ("13", &[(ICON_MAP_PATH, empty)]),
+    ];
+
+    for (branch, files) in branches {
+        let mut index = cache.index().unwrap();
+        index.clear().unwrap();
+
+        for (path, content) in files {
+            let file = workdir.join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, content).unwrap();
+            index.add_path(Path::new(path)).unwrap();
+        }
+
+        let tree = cache.find_tree(index.write_tree().unwrap()).unwrap();
+        let commit = cache
+            .commit(None, &signature, &signature, branch, &tree, &[])
+            .unwrap();
+        cache
+            .reference(&format!("refs/remotes/origin/{branch}"), commit, false, "")
+            .unwrap();
+    }
+
+    let mut config = Config::for_tests();
+    config.overlay_path = Some(workdir.display().to_string());
+
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET_BRANCH: &str = "12.1";
+    const BRANCH_NAME: &str = "bot/icon_leonardo";
+    const ICON_MAP: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<icons>\n</icons>\n";
+
+    /// A checkout of the overlay whose `origin/12.1` has an empty icon map,
+    /// with `remote` as the overlay remote.
+    fn overlay(dir: &TempDir, remote: &Path) -> (Config, Repository) {
+        let cache = Repository::init(dir.path().join("overlay")).unwrap();
+        let signature = Signature::now("Leonardo", "leonardo@example.com").unwrap();
+
+        let icon_map_path = cache.workdir().unwrap().join(ICON_MAP_PATH);
+        fs::create_dir_all(icon_map_path.parent().unwrap()).unwrap();
+        fs::write(icon_map_path, ICON_MAP).unwrap();
+
+        let commit = {
+            let mut index = cache.index().unwrap();
+            index.add_path(Path::new(ICON_MAP_PATH)).unwrap();
+            index.write().unwrap();
+            let tree = cache.find_tree(index.write_tree().unwrap()).unwrap();
+
+            cache
+                .commit(Some("HEAD"), &signature, &signature, "Init", &tree, &[])
+                .unwrap()
+        };
+        cache
+            .reference(
+                &format!("refs/remotes/origin/{TARGET_BRANCH}"),
+                commit,
+                false,
+                "",
+            )
+            .unwrap();
+
+        let mut config = Config::for_tests();
+        config.overlay_path = Some(cache.workdir().unwrap().display().to_string());
+        config.overlay_remote_url = Some(remote.display().to_string());
+        config.git_author_name = Some(String::from("Leonardo"));
+        config.git_author_email = Some(String::from("leonardo@example.com"));
+
+        (config, cache)
+    }
+
+    fn icon_commit() -> IconCommit {
+        IconCommit {
+            target_branch: String::from(TARGET_BRANCH),
+            branch_name: String::from(BRANCH_NAME),
+            icons: vec![NewIcon {
+                icon_name: String::from("leonardo"),
+                app_path: String::from("org.example.leonardo"),
+                vd_bytes: b"<vector />".to_vec(),
+            }],
+            commit_msg: String::from("Add leonardo"),
+            force: false,
+        }
+    }
+
+    #[test]
+    fn failing_push_leaves_nothing_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        // Nothing exists at the remote path yet, so the push fails.
+        let remote_path = dir.path().join("remote.git");
+        let (config, cache) = overlay(&dir, &remote_path);
+
+        let error = commit_and_push_icon(&config, &icon_commit()).unwrap_err();
+
+        assert!(error.downcast_ref::<PushFailed>().is_some());
+        assert!(cache
+            .find_reference(&format!("refs/heads/{BRANCH_NAME}"))
+            .is_err());
+        assert!(cache.statuses(None).unwrap().is_empty());
+        assert_eq!(
+            fs::read_to_string(cache.workdir().unwrap().join(ICON_MAP_PATH)).unwrap(),
+            ICON_MAP
+        );
+
+        // Once the remote is back, the next submission starts from the
+        // target branch and contains only its own icon.
+        let remote = Repository::init_bare(&remote_path).unwrap();
+
+        assert_eq!(commit_and_push_icon(&config, &icon_commit()).unwrap(), None);
+
+        let pushed = remote
+            .find_reference(&format!("refs/heads/{BRANCH_NAME}"))
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        let target = cache
+            .find_reference(&format!("refs/remotes/origin/{TARGET_BRANCH}"))
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(pushed.parent_ids().collect::<Vec<_>>(), [target.id()]);
+
+        let diff = remote
+            .diff_tree_to_tree(
+                Some(&remote.find_commit(target.id()).unwrap().tree().unwrap()),
+                Some(&pushed.tree().unwrap()),
+                None,
+            )
+            .unwrap();
+        let changed = diff
+            .deltas()
+            .map(|delta| delta.new_file().path().unwrap().display().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changed,
+            [
+                format!("{DRAWABLE_DIR}/themed_icon_leonardo.xml"),
+                String::from(ICON_MAP_PATH),
+            ]
+        );
+    }
+
+    fn branches(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn drawables_are_found_on_any_target_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = two_branch_overlay(dir.path());
+
+        let existing = find_existing_drawable(&config, &branches(&["13", "12.1"]), "leonardo")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(existing.branch, "12.1");
+        assert_eq!(existing.package.as_deref(), Some("org.example.leonardo"));
+    }
+
+    #[test]
+    fn drawables_on_other_branches_are_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = two_branch_overlay(dir.path());
+
+        assert!(
+            find_existing_drawable(&config, &branches(&["13"]), "leonardo")
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            find_existing_drawable(&config, &branches(&["12.1", "13"]), "raffaello")
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn packages_are_mapped_per_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = two_branch_overlay(dir.path());
+
+        assert_eq!(
+            mapped_drawables(&config, &branches(&["12.1", "13"]), "org.example.leonardo").unwrap(),
+            [Some(String::from("themed_icon_leonardo")), None]
+        );
+        assert_eq!(
+            mapped_drawables(&config, &branches(&["12.1", "13"]), "org.example.other").unwrap(),
+            [None, None]
+        );
+    }
+}
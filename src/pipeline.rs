@@ -0,0 +1,514 @@
+use std::{error::Error, fmt, future::Future, io::Cursor};
+
+use image::{load_from_memory, ImageOutputFormat, Rgba, RgbaImage};
+use svg_trace::{convert_image_to_svg, Config as TraceConfig, Preset};
+use teloxide::DownloadError;
+
+use crate::{
+    config::{Config, MAX_LENGTH_THRESHOLD, MIN_LENGTH_THRESHOLD},
+    error::BotError,
+    preprocess::{
+        check_dimensions, check_transparency, check_vector_drawable, crop_to_content, downscale,
+        remove_background, Cropped,
+    },
+    retry::{with_retry, DEFAULT_ATTEMPTS, DEFAULT_BASE_DELAY},
+    tools::run_with_stdin,
+};
+
+const ALPHA_THRESHOLD_STEP: u8 = 32;
+const MAX_FILTER_SPECKLE: usize = 256;
+const CORNER_THRESHOLD_STEP: i32 = 15;
+
+/// Settings for tracing a PNG that the submitter can adjust after seeing the
+/// result.
+#[derive(Clone, Copy, Debug)]
+pub struct TraceOptions {
+    /// Alpha value from which on a pixel is considered part of the icon.
+    alpha_threshold: u8,
+    /// Patches smaller than this many pixels are discarded as speckles.
+    filter_speckle: usize,
+    /// Minimum angle in degrees for a turn in a path to be kept as a corner.
+    corner_threshold: i32,
+    /// Minimum length of a path segment, higher values give smoother curves.
+    length_threshold: f64,
+}
+
+impl TraceOptions {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            alpha_threshold: config.alpha_threshold,
+            filter_speckle: config.filter_speckle,
+            corner_threshold: config.corner_threshold,
+            length_threshold: config.length_threshold,
+        }
+    }
+
+    /// Applies the adjustment for one of the re-trace buttons. Returns `None`
+    /// if `answer` isn't one of them.
+    pub fn adjust(self, answer: &str) -> Option<Self> {
+        let mut options = self;
+
+        match answer {
+            // A higher threshold means fewer semi-transparent edge pixels
+            // end up as part of the icon.
+            "Thinner" => {
+                options.alpha_threshold = self.alpha_threshold.saturating_add(ALPHA_THRESHOLD_STEP)
+            }
+            "Thicker" => {
+                options.alpha_threshold = self
+                    .alpha_threshold
+                    .saturating_sub(ALPHA_THRESHOLD_STEP)
+                    .max(1)
+            }
+            "Remove speckles" => {
+                options.filter_speckle = (self.filter_speckle * 2).clamp(2, MAX_FILTER_SPECKLE)
+            }
+            "More detail" => {
+                options.filter_speckle = self.filter_speckle / 2;
+                options.corner_threshold = (self.corner_threshold - CORNER_THRESHOLD_STEP).max(0);
+                options.length_threshold = (self.length_threshold - 1.0).max(MIN_LENGTH_THRESHOLD);
+            }
+            "Smoother curves" => {
+                options.corner_threshold = (self.corner_threshold + CORNER_THRESHOLD_STEP).min(180);
+                options.length_threshold = (self.length_threshold + 1.0).min(MAX_LENGTH_THRESHOLD);
+            }
+            _ => return None,
+        }
+
+        Some(options)
+    }
+
+    fn to_config(self) -> TraceConfig {
+        let mut config = TraceConfig::from_preset(Preset::Bw);
+        config.filter_speckle = self.filter_speckle;
+        config.corner_threshold = self.corner_threshold;
+        config.length_threshold = self.length_threshold;
+
+        config
+    }
+}
+
+impl fmt::Display for TraceOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "alpha threshold {}, speckle size {}, corner angle {}°, segment length {}",
+            self.alpha_threshold, self.filter_speckle, self.corner_threshold, self.length_threshold
+        )
+    }
+}
+
+/// Returned when a download from Telegram ended with a different number of
+/// bytes than the file has, e.g. because the connection dropped.
+#[derive(Debug)]
+pub struct IncompleteDownload {
+    expected: u32,
+    received: usize,
+}
+
+impl fmt::Display for IncompleteDownload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "received {} of {} bytes", self.received, self.expected)
+    }
+}
+
+impl Error for IncompleteDownload {}
+
+/// Downloads the uploaded file at `path` with `download`, retrying transient
+/// failures and downloads that didn't come out `expected_size` bytes long.
+/// Telegram reports a size of 0 if it doesn't know it, then any length is
+/// accepted.
+pub async fn download_complete<F, Fut>(
+    path: &str,
+    expected_size: u32,
+    mut download: F,
+) -> Result<Vec<u8>, BotError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Vec<u8>, DownloadError>>,
+{
+    with_retry(
+        &format!("Downloading {path}"),
+        DEFAULT_ATTEMPTS,
+        DEFAULT_BASE_DELAY,
+        || {
+            let download = download();
+
+            async move {
+                let bytes = download.await.map_err(|e| BotError::Download(e.into()))?;
+
+                if expected_size > 0 && bytes.len() != expected_size as usize {
+                    return Err(BotError::Download(Box::new(IncompleteDownload {
+                        expected: expected_size,
+                        received: bytes.len(),
+                    })));
+                }
+
+                Ok(bytes)
+            }
+        },
+    )
+    .await
+}
+
+/// A PNG traced into an SVG.
+pub struct Traced {
+    pub svg: String,
+    /// Whether the icon wasn't square and was padded to be.
+    pub padded: bool,
+    /// The dimensions of the PNG if they are below the recommended ones, so
+    /// the result may look rough.
+    pub low_resolution: Option<(u32, u32)>,
+}
+
+/// Turns every pixel that is at least `alpha_threshold` opaque black, keeping
+/// its transparency, and every other pixel opaque white, which is what the
+/// tracer expects.
+pub fn to_black_and_white(img: &mut RgbaImage, alpha_threshold: u8) {
+    for pixel in img.pixels_mut() {
+        if pixel.0[3] >= alpha_threshold {
+            pixel.0[0] = 0;
+            pixel.0[1] = 0;
+            pixel.0[2] = 0;
+        } else {
+            pixel.0 = [255; 4];
+        }
+    }
+}
+
+/// Traces a PNG into a black and white SVG, padding it to a square first.
+pub async fn trace_png(
+    config: &Config,
+    png_bytes: Vec<u8>,
+    options: TraceOptions,
+) -> Result<Traced, Box<dyn Error + Send + Sync>> {
+    let alpha_threshold = options.alpha_threshold;
+    let margin = config.crop_margin;
+    let max_dimension = config.max_icon_dimension;
+    let min_dimension = config.min_icon_dimension;
+    let recommended_dimension = config.recommended_icon_dimension;
+
+    tokio::task::spawn_blocking(move || {
+        let img = load_from_memory(&png_bytes)?.into_rgba8();
+        check_dimensions(&img, min_dimension)?;
+
+        let (width, height) = img.dimensions();
+        let low_resolution = (width.min(height) < recommended_dimension).then_some((width, height));
+
+        let img = downscale(img, max_dimension);
+        check_transparency(&img)?;
+        let Cropped { mut image, padded } = crop_to_content(&img, alpha_threshold, margin)?;
+        to_black_and_white(&mut image, alpha_threshold);
+
+        let svg = convert_image_to_svg(options.to_config(), image)
+            .map_err(|e| BotError::Trace(e.to_string()))?;
+
+        Ok::<_, Box<dyn Error + Send + Sync>>(Traced {
+            svg,
+            padded,
+            low_resolution,
+        })
+    })
+    .await?
+}
+
+/// Makes the solid `background` color of a PNG transparent and returns the
+/// result as a new PNG.
+pub async fn remove_png_background(
+    max_dimension: u32,
+    png_bytes: Vec<u8>,
+    background: [u8; 4],
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    tokio::task::spawn_blocking(move || {
+        let mut img = downscale(load_from_memory(&png_bytes)?.into_rgba8(), max_dimension);
+        remove_background(&mut img, Rgba(background));
+
+        let mut png_bytes = Vec::new();
+        img.write_to(&mut Cursor::new(&mut png_bytes), ImageOutputFormat::Png)?;
+
+        Ok::<_, Box<dyn Error + Send + Sync>>(png_bytes)
+    })
+    .await?
+}
+
+/// Converts an SVG to an Android VectorDrawable with svg2vd. Fails with
+/// [`InvalidVectorDrawable`](crate::preprocess::InvalidVectorDrawable) if
+/// Android would reject the result.
+pub async fn svg_to_vd(
+    svg2vd_bin: &str,
+    svg: &[u8],
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let output = run_with_stdin(svg2vd_bin, &["-i", "-", "-o", "-"], svg).await?;
+
+    Ok(check_vector_drawable(output)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, io};
+
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+    use crate::{
+        preprocess::{EmptyImage, NoTransparency},
+        tools::Tools,
+    };
+
+    const SIDE: u32 = 256;
+    const SQUARE: u32 = 32;
+
+    fn io_error() -> DownloadError {
+        DownloadError::Io(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+    }
+
+    fn png(img: impl Into<image::DynamicImage>) -> Vec<u8> {
+        let mut png_bytes = Vec::new();
+        img.into()
+            .write_to(&mut Cursor::new(&mut png_bytes), ImageOutputFormat::Png)
+            .unwrap();
+
+        png_bytes
+    }
+
+    /// Encodes `img` as a lossless WebP like a static sticker. Every pixel has
+    /// to be opaque or fully transparent black, so only the alpha channel
+    /// needs a bit per pixel.
+    fn webp(img: &RgbaImage) -> Vec<u8> {
+        let mut bits = Vec::new();
+        let mut put =
+            |value: u32, count: u32| bits.extend((0..count).map(|i| (value >> i) & 1 == 1));
+
+        // Size, alpha in use, version 0, no transforms, color cache or meta
+        // prefix codes.
+        put(img.width() - 1, 14);
+        put(img.height() - 1, 14);
+        put(1, 1);
+        put(0, 3);
+        put(0, 3);
+        // Simple prefix codes for green, red and blue with only 0 in them,
+        // alpha with 0 and 255 and a distance code that is never used.
+        for _ in 0..3 {
+            put(0b0001, 4);
+        }
+        put(0b0011, 4);
+        put(255, 8);
+        put(0b0001, 4);
+
+        for pixel in img.pixels() {
+            assert!(matches!(pixel.0, [0, 0, 0, 0 | 255]));
+            put(u32::from(pixel.0[3] == 255), 1);
+        }
+
+        let mut data = vec![0x2f];
+        data.extend(bits.chunks(8).map(|byte| {
+            byte.iter()
+                .enumerate()
+                .fold(0u8, |acc, (i, &bit)| acc | (u8::from(bit) << i))
+        }));
+        let chunk_size = data.len() as u32;
+        // Chunks are padded to an even size.
+        if data.len() % 2 == 1 {
+            data.push(0);
+        }
+
+        let mut webp_bytes = b"RIFF".to_vec();
+        webp_bytes.extend((12 + data.len() as u32).to_le_bytes());
+        webp_bytes.extend(b"WEBPVP8L");
+        webp_bytes.extend(chunk_size.to_le_bytes());
+        webp_bytes.extend(data);
+
+        webp_bytes
+    }
+
+    /// Alternating opaque black and fully transparent squares.
+    fn checkerboard() -> RgbaImage {
+        RgbaImage::from_fn(SIDE, SIDE, |x, y| {
+            if (x / SQUARE + y / SQUARE) % 2 == 0 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        })
+    }
+
+    /// A black square on a white background, without an alpha channel.
+    fn without_alpha() -> RgbImage {
+        RgbImage::from_fn(SIDE, SIDE, |x, y| {
+            if (SIDE / 4..SIDE * 3 / 4).contains(&x) && (SIDE / 4..SIDE * 3 / 4).contains(&y) {
+                Rgb([0, 0, 0])
+            } else {
+                Rgb([255, 255, 255])
+            }
+        })
+    }
+
+    async fn trace(png_bytes: Vec<u8>) -> Result<Traced, Box<dyn Error + Send + Sync>> {
+        let config = Config::for_tests();
+
+        trace_png(&config, png_bytes, TraceOptions::from_config(&config)).await
+    }
+
+    /// The svg2vd binary of the test config, or `None` if it isn't installed.
+    /// The tests needing it pass without checking anything then.
+    async fn svg2vd() -> Option<String> {
+        let bin = Config::for_tests().svg2vd_bin;
+
+        if Tools::detect(&bin).await.svg2vd {
+            Some(bin)
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn black_and_white_follows_the_alpha_threshold() {
+        let mut img = checkerboard();
+        img.put_pixel(0, 0, Rgba([200, 100, 50, 127]));
+        img.put_pixel(1, 0, Rgba([200, 100, 50, 128]));
+
+        to_black_and_white(&mut img, 128);
+
+        assert_eq!(*img.get_pixel(0, 0), Rgba([255; 4]));
+        assert_eq!(*img.get_pixel(1, 0), Rgba([0, 0, 0, 128]));
+        assert_eq!(*img.get_pixel(SQUARE, 0), Rgba([255; 4]));
+        assert_eq!(*img.get_pixel(SQUARE, SQUARE), Rgba([0, 0, 0, 255]));
+    }
+
+    #[tokio::test]
+    async fn transparent_image_has_nothing_to_trace() {
+        let err = trace(png(RgbaImage::new(SIDE, SIDE))).await.err().unwrap();
+
+        assert!(err.is::<EmptyImage>(), "{err}");
+    }
+
+    #[tokio::test]
+    async fn checkerboard_alpha_is_traced() {
+        let traced = trace(png(checkerboard())).await.unwrap();
+
+        assert!(traced.svg.contains("<svg"));
+        assert!(traced.svg.contains("<path"));
+        assert!(!traced.padded);
+        assert_eq!(traced.low_resolution, None);
+    }
+
+    #[tokio::test]
+    async fn webp_alpha_is_traced_like_png() {
+        let webp_bytes = webp(&checkerboard());
+        assert_eq!(
+            load_from_memory(&webp_bytes).unwrap().into_rgba8(),
+            checkerboard()
+        );
+
+        let traced = trace(webp_bytes).await.unwrap();
+
+        assert_eq!(traced.svg, trace(png(checkerboard())).await.unwrap().svg);
+    }
+
+    #[tokio::test]
+    async fn image_without_alpha_is_traced_once_its_background_is_removed() {
+        let err = trace(png(without_alpha())).await.err().unwrap();
+        let background = match err.downcast_ref::<NoTransparency>() {
+            Some(NoTransparency {
+                background: Some(background),
+            }) => *background,
+            _ => panic!("expected a solid background, got {err}"),
+        };
+        assert_eq!(background, Rgba([255, 255, 255, 255]));
+
+        let png_bytes = remove_png_background(SIDE, png(without_alpha()), background.0)
+            .await
+            .unwrap();
+        let traced = trace(png_bytes).await.unwrap();
+
+        assert!(traced.svg.contains("<path"));
+        assert!(!traced.padded);
+    }
+
+    #[tokio::test]
+    async fn missing_svg2vd_fails() {
+        let result = svg_to_vd("/nonexistent/svg2vd", b"<svg />").await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn svg2vd_converts_a_traced_icon() {
+        let bin = match svg2vd().await {
+            Some(bin) => bin,
+            None => return,
+        };
+        let traced = trace(png(checkerboard())).await.unwrap();
+
+        let vd = svg_to_vd(&bin, traced.svg.as_bytes()).await.unwrap();
+
+        assert!(String::from_utf8(vd).unwrap().contains("<vector"));
+    }
+
+    #[tokio::test]
+    async fn svg2vd_rejects_what_is_not_an_svg() {
+        let bin = match svg2vd().await {
+            Some(bin) => bin,
+            None => return,
+        };
+
+        assert!(svg_to_vd(&bin, b"not an svg").await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn download_is_retried_until_complete() {
+        let calls = Cell::new(0);
+
+        let bytes = download_complete("icon.png", 4, || {
+            calls.set(calls.get() + 1);
+            let attempt = calls.get();
+
+            async move {
+                match attempt {
+                    1 => Err(io_error()),
+                    2 => Ok(vec![1, 2, 3]),
+                    _ => Ok(vec![1, 2, 3, 4]),
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(bytes, [1, 2, 3, 4]);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn incomplete_download_fails_after_every_attempt() {
+        let calls = Cell::new(0);
+
+        let result = download_complete("icon.png", 4, || {
+            calls.set(calls.get() + 1);
+
+            async { Ok(vec![1, 2, 3]) }
+        })
+        .await;
+
+        match result {
+            Err(BotError::Download(e)) => assert!(e.is::<IncompleteDownload>()),
+            other => panic!("expected an incomplete download, got {other:?}"),
+        }
+        assert_eq!(calls.get(), DEFAULT_ATTEMPTS);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unknown_size_accepts_any_length() {
+        let calls = Cell::new(0);
+
+        let bytes = download_complete("icon.png", 0, || {
+            calls.set(calls.get() + 1);
+
+            async { Ok(vec![1, 2, 3]) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(bytes, [1, 2, 3]);
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,403 @@
+use image::{
+    imageops::{self, FilterType},
+    Rgba, RgbaImage,
+};
+
+use std::{error::Error, fmt};
+
+/// Returned when an image contains no pixels that would end up in the icon.
+#[derive(Debug)]
+pub struct EmptyImage;
+
+impl fmt::Display for EmptyImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("image has no visible pixels")
+    }
+}
+
+impl Error for EmptyImage {}
+
+/// Returned when an image is too small to trace into a clean icon.
+#[derive(Debug)]
+pub struct ImageTooSmall {
+    pub width: u32,
+    pub height: u32,
+    pub min_dimension: u32,
+}
+
+impl fmt::Display for ImageTooSmall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "it is only {}×{} pixels, but icons need to be at least {}×{}",
+            self.width, self.height, self.min_dimension, self.min_dimension
+        )
+    }
+}
+
+impl Error for ImageTooSmall {}
+
+/// Returned when the content of an image is too wide or tall to be an icon.
+#[derive(Debug)]
+pub struct ExtremeAspectRatio {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl fmt::Display for ExtremeAspectRatio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "its content is {}×{} pixels, which looks like a banner rather than an icon",
+            self.width, self.height
+        )
+    }
+}
+
+impl Error for ExtremeAspectRatio {}
+
+/// Returned when an uploaded SVG can't be passed on to svg2vd.
+#[derive(Debug)]
+pub enum InvalidSvg {
+    NotText,
+    Malformed(roxmltree::Error),
+    WrongRoot(String),
+}
+
+impl fmt::Display for InvalidSvg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotText => f.write_str("it is not a text file"),
+            Self::Malformed(e) => write!(f, "it is not valid XML ({e})"),
+            Self::WrongRoot(name) => write!(f, "its root element is <{name}> instead of <svg>"),
+        }
+    }
+}
+
+impl Error for InvalidSvg {}
+
+/// Returned when a VectorDrawable would be rejected by Android.
+#[derive(Debug)]
+pub enum InvalidVectorDrawable {
+    NotText,
+    Malformed(roxmltree::Error),
+    WrongRoot(String),
+    MissingNamespace,
+    NoPaths,
+}
+
+impl fmt::Display for InvalidVectorDrawable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotText => f.write_str("it is not a text file"),
+            Self::Malformed(e) => write!(f, "it is not valid XML ({e})"),
+            Self::WrongRoot(name) => write!(f, "its root element is <{name}> instead of <vector>"),
+            Self::MissingNamespace => f.write_str("it does not declare the android namespace"),
+            Self::NoPaths => f.write_str("it contains no <path> with pathData"),
+        }
+    }
+}
+
+impl Error for InvalidVectorDrawable {}
+
+const ANDROID_NS: &str = "http://schemas.android.com/apk/res/android";
+
+/// Share of fully opaque pixels from which on an image is considered to have
+/// no transparent background.
+const MAX_OPAQUE_FRACTION: f32 = 0.95;
+
+/// Maximum difference per channel for a pixel to still count as background.
+const BACKGROUND_TOLERANCE: u8 = 16;
+
+/// Content with a longer side more than this many times its shorter side is
+/// rejected instead of padded into a mostly empty square.
+const MAX_ASPECT_RATIO: f32 = 3.0;
+
+/// Returned when an image has (almost) no transparent pixels, so tracing it
+/// would only produce a filled square.
+#[derive(Debug)]
+pub struct NoTransparency {
+    /// The color of the background, if all pixels along the border share it.
+    pub background: Option<Rgba<u8>>,
+}
+
+impl fmt::Display for NoTransparency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("image has no transparent background")
+    }
+}
+
+impl Error for NoTransparency {}
+
+/// An image cropped to its content.
+pub struct Cropped {
+    pub image: RgbaImage,
+    /// Whether the content wasn't square and was padded to be.
+    pub padded: bool,
+}
+
+/// Crops `img` to the bounding box of all pixels with at least
+/// `alpha_threshold` alpha and centers it on a transparent square, so the
+/// icon isn't squished on the device. Then adds a transparent margin of
+/// `margin` times the side of the square on every side.
+pub fn crop_to_content(
+    img: &RgbaImage,
+    alpha_threshold: u8,
+    margin: f32,
+) -> Result<Cropped, Box<dyn Error + Send + Sync>> {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if pixel.0[3] >= alpha_threshold {
+            bounds = Some(match bounds {
+                Some((min_x, min_y, max_x, max_y)) => {
+                    (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+                }
+                None => (x, y, x, y),
+            });
+        }
+    }
+
+    let (min_x, min_y, max_x, max_y) = bounds.ok_or(EmptyImage)?;
+    let width = max_x - min_x + 1;
+    let height = max_y - min_y + 1;
+    let side = width.max(height);
+
+    if side as f32 / width.min(height) as f32 > MAX_ASPECT_RATIO {
+        return Err(ExtremeAspectRatio { width, height }.into());
+    }
+
+    let padding = (side as f32 * margin).round() as u32;
+
+    let content = imageops::crop_imm(img, min_x, min_y, width, height).to_image();
+    let mut cropped = RgbaImage::new(side + 2 * padding, side + 2 * padding);
+    imageops::replace(
+        &mut cropped,
+        &content,
+        i64::from(padding + (side - width) / 2),
+        i64::from(padding + (side - height) / 2),
+    );
+
+    Ok(Cropped {
+        image: cropped,
+        padded: width != height,
+    })
+}
+
+/// Fails with [`ImageTooSmall`] if either side of `img` is shorter than
+/// `min_dimension`.
+pub fn check_dimensions(img: &RgbaImage, min_dimension: u32) -> Result<(), ImageTooSmall> {
+    let (width, height) = img.dimensions();
+
+    if width < min_dimension || height < min_dimension {
+        Err(ImageTooSmall {
+            width,
+            height,
+            min_dimension,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Scales `img` down so that neither side exceeds `max_dimension`, keeping the
+/// aspect ratio. Images that already fit are returned unchanged.
+pub fn downscale(img: RgbaImage, max_dimension: u32) -> RgbaImage {
+    let (width, height) = img.dimensions();
+
+    if width <= max_dimension && height <= max_dimension {
+        return img;
+    }
+
+    let scale = max_dimension as f32 / width.max(height) as f32;
+    let new_width = ((width as f32 * scale).round() as u32).clamp(1, max_dimension);
+    let new_height = ((height as f32 * scale).round() as u32).clamp(1, max_dimension);
+
+    imageops::resize(&img, new_width, new_height, FilterType::Triangle)
+}
+
+/// Fails with [`NoTransparency`] if nearly all pixels of `img` are fully
+/// opaque, which is the case for images without an alpha channel too.
+pub fn check_transparency(img: &RgbaImage) -> Result<(), NoTransparency> {
+    let pixel_count = u64::from(img.width()) * u64::from(img.height());
+    let opaque = img.pixels().filter(|pixel| pixel.0[3] == 255).count();
+
+    if pixel_count > 0 && opaque as f32 / pixel_count as f32 > MAX_OPAQUE_FRACTION {
+        Err(NoTransparency {
+            background: solid_background(img),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the background color of `img` if every pixel along its border has
+/// roughly the same color as the top left corner.
+fn solid_background(img: &RgbaImage) -> Option<Rgba<u8>> {
+    let (width, height) = img.dimensions();
+
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let color = *img.get_pixel(0, 0);
+    let mut border = (0..width)
+        .flat_map(|x| [(x, 0), (x, height - 1)])
+        .chain((0..height).flat_map(|y| [(0, y), (width - 1, y)]));
+
+    if border.all(|(x, y)| is_similar(img.get_pixel(x, y), &color)) {
+        Some(color)
+    } else {
+        None
+    }
+}
+
+/// Makes every pixel of `img` that roughly matches `color` transparent.
+pub fn remove_background(img: &mut RgbaImage, color: Rgba<u8>) {
+    for pixel in img.pixels_mut() {
+        if is_similar(pixel, &color) {
+            pixel.0[3] = 0;
+        }
+    }
+}
+
+fn is_similar(a: &Rgba<u8>, b: &Rgba<u8>) -> bool {
+    a.0.iter()
+        .zip(b.0.iter())
+        .take(3)
+        .all(|(a, b)| a.abs_diff(*b) <= BACKGROUND_TOLERANCE)
+}
+
+/// Makes sure `bytes` are an XML document with an `<svg>` root element and
+/// returns them as a string.
+pub fn check_svg(bytes: Vec<u8>) -> Result<String, InvalidSvg> {
+    let svg = String::from_utf8(bytes).map_err(|_| InvalidSvg::NotText)?;
+    let root = roxmltree::Document::parse(&svg)
+        .map_err(InvalidSvg::Malformed)?
+        .root_element()
+        .tag_name()
+        .name()
+        .to_owned();
+
+    if root == "svg" {
+        Ok(svg)
+    } else {
+        Err(InvalidSvg::WrongRoot(root))
+    }
+}
+
+/// Makes sure `bytes` are a VectorDrawable Android can use and returns them
+/// without any text svg2vd sometimes prints before the XML.
+pub fn check_vector_drawable(bytes: Vec<u8>) -> Result<Vec<u8>, InvalidVectorDrawable> {
+    let text = String::from_utf8(bytes).map_err(|_| InvalidVectorDrawable::NotText)?;
+    let start = text
+        .find("<?xml")
+        .or_else(|| text.find("<vector"))
+        .unwrap_or(0);
+    let xml = &text[start..];
+
+    let doc = roxmltree::Document::parse(xml).map_err(InvalidVectorDrawable::Malformed)?;
+    let root = doc.root_element();
+
+    if root.tag_name().name() != "vector" {
+        return Err(InvalidVectorDrawable::WrongRoot(
+            root.tag_name().name().to_owned(),
+        ));
+    }
+
+    if root.lookup_prefix(ANDROID_NS).is_none() {
+        return Err(InvalidVectorDrawable::MissingNamespace);
+    }
+
+    let has_path = root.descendants().any(|node| {
+        node.has_tag_name("path")
+            && node
+                .attribute((ANDROID_NS, "pathData"))
+                .map_or(false, |data| !data.trim().is_empty())
+    });
+
+    if has_path {
+        Ok(xml.as_bytes().to_vec())
+    } else {
+        Err(InvalidVectorDrawable::NoPaths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPAQUE: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+    /// A transparent `side`×`side` image with an opaque `width`×`height`
+    /// rectangle at `x`, `y`.
+    fn with_rectangle(side: u32, x: u32, y: u32, width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(side, side, |px, py| {
+            if (x..x + width).contains(&px) && (y..y + height).contains(&py) {
+                OPAQUE
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        })
+    }
+
+    #[test]
+    fn crop_centers_content_on_a_square_with_margin() {
+        let img = with_rectangle(100, 30, 40, 20, 10);
+
+        let Cropped { image, padded } = crop_to_content(&img, 128, 0.1).unwrap();
+
+        // 20 pixels of content plus 2 pixels of margin on each side.
+        assert_eq!(image.dimensions(), (24, 24));
+        assert!(padded);
+        // The content is centered vertically, 5 rows below the margin.
+        assert_eq!(*image.get_pixel(2, 7), OPAQUE);
+        assert_eq!(*image.get_pixel(21, 16), OPAQUE);
+        assert_eq!(image.get_pixel(2, 6).0[3], 0);
+        assert_eq!(image.get_pixel(2, 17).0[3], 0);
+        assert_eq!(image.get_pixel(1, 7).0[3], 0);
+        assert_eq!(image.get_pixel(22, 7).0[3], 0);
+    }
+
+    #[test]
+    fn square_content_is_not_padded() {
+        let img = with_rectangle(100, 10, 20, 30, 30);
+
+        let Cropped { image, padded } = crop_to_content(&img, 128, 0.0).unwrap();
+
+        assert_eq!(image.dimensions(), (30, 30));
+        assert!(!padded);
+        assert!(image.pixels().all(|pixel| *pixel == OPAQUE));
+    }
+
+    #[test]
+    fn pixels_below_the_alpha_threshold_are_not_content() {
+        let mut img = with_rectangle(100, 40, 40, 10, 10);
+        img.put_pixel(0, 0, Rgba([0, 0, 0, 127]));
+
+        let Cropped { image, .. } = crop_to_content(&img, 128, 0.0).unwrap();
+
+        assert_eq!(image.dimensions(), (10, 10));
+    }
+
+    #[test]
+    fn crop_fails_without_content() {
+        let err = crop_to_content(&RgbaImage::new(10, 10), 128, 0.1)
+            .err()
+            .unwrap();
+
+        assert!(err.is::<EmptyImage>());
+    }
+
+    #[test]
+    fn crop_fails_for_banners() {
+        let err = crop_to_content(&with_rectangle(100, 0, 0, 40, 10), 128, 0.1)
+            .err()
+            .unwrap();
+
+        match err.downcast_ref::<ExtremeAspectRatio>() {
+            Some(ExtremeAspectRatio { width, height }) => assert_eq!((*width, *height), (40, 10)),
+            None => panic!("expected an extreme aspect ratio, got {err}"),
+        }
+    }
+}
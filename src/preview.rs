@@ -0,0 +1,45 @@
+use std::{error::Error, fmt};
+
+/// Background color behind rendered previews, similar to a themed icon in
+/// the launcher.
+const BACKGROUND: (u8, u8, u8) = (0xd3, 0xe3, 0xfd);
+
+/// Returned when an SVG can't be rendered to a preview.
+#[derive(Debug)]
+pub struct RenderFailed;
+
+impl fmt::Display for RenderFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("failed to render SVG preview")
+    }
+}
+
+impl Error for RenderFailed {}
+
+/// Renders `svg` into a `size`×`size` PNG on the launcher background color.
+pub fn render_png(svg: &[u8], size: u32) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let opt = usvg::Options::default();
+    let tree = usvg::Tree::from_data(svg, &opt.to_ref())?;
+
+    let mut pixmap = tiny_skia::Pixmap::new(size, size).ok_or(RenderFailed)?;
+    pixmap.fill(tiny_skia::Color::from_rgba8(
+        BACKGROUND.0,
+        BACKGROUND.1,
+        BACKGROUND.2,
+        255,
+    ));
+
+    // Leave some room around the icon like the launcher does.
+    let padding = size / 8;
+    let icon_size = size - 2 * padding;
+
+    resvg::render(
+        &tree,
+        usvg::FitTo::Size(icon_size, icon_size),
+        tiny_skia::Transform::from_translate(padding as f32, padding as f32),
+        pixmap.as_mut(),
+    )
+    .ok_or(RenderFailed)?;
+
+    Ok(pixmap.encode_png()?)
+}
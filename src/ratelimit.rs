@@ -0,0 +1,262 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::{sleep, Instant};
+
+use crate::{config::Config, metrics::Metrics};
+
+struct Bucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+    consumed: u64,
+    waits: u64,
+}
+
+impl Bucket {
+    fn new(interval: Duration, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: 1.0 / interval.as_secs_f64(),
+            last_refill: Instant::now(),
+            consumed: 0,
+            waits: 0,
+        }
+    }
+
+    /// Takes a token if one is available, otherwise returns how long it will
+    /// take until the next one is.
+    fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            self.consumed += 1;
+
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.refill_per_sec,
+            ))
+        }
+    }
+}
+
+/// Token-bucket rate limiter shared by everything that talks to external
+/// hosts, keyed by host name. Hosts without a bucket are not limited.
+pub struct RateLimiter {
+    buckets: HashMap<String, Mutex<Bucket>>,
+    /// Counts the requests that were held up or skipped.
+    metrics: Arc<Metrics>,
+}
+
+impl RateLimiter {
+    /// A limiter without any limited hosts.
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self {
+            buckets: HashMap::new(),
+            metrics,
+        }
+    }
+
+    /// Limits the Play Store, F-Droid and the OTA metadata on GitHub to the
+    /// intervals in `config`.
+    pub fn from_config(config: &Config, metrics: Arc<Metrics>) -> Self {
+        let mut limiter = Self::new(metrics);
+
+        limiter.add_host("play.google.com", config.playstore_rate_limit, 1);
+        limiter.add_host("f-droid.org", config.fdroid_rate_limit, 2);
+        limiter.add_host("raw.githubusercontent.com", config.github_rate_limit, 4);
+
+        limiter
+    }
+
+    /// Allows one request per `interval` to `host`, with up to `burst`
+    /// requests in quick succession after an idle period.
+    pub fn add_host(&mut self, host: &str, interval: Duration, burst: u32) {
+        self.buckets
+            .insert(host.to_owned(), Mutex::new(Bucket::new(interval, burst)));
+    }
+
+    /// Acquires a token for the host of `url`. Returns `false` if no token
+    /// could be acquired within `max_wait`.
+    pub async fn acquire(&self, url: &str, max_wait: Duration) -> bool {
+        let host = match reqwest::Url::parse(url) {
+            Ok(url) => url.host_str().map(ToString::to_string),
+            Err(_) => None,
+        };
+        let bucket = match host.as_deref().and_then(|host| self.buckets.get(host)) {
+            Some(bucket) => bucket,
+            None => return true,
+        };
+        let host = host.unwrap_or_default();
+
+        let start = Instant::now();
+        let mut waited = false;
+
+        loop {
+            let now = Instant::now();
+            let result = {
+                let mut bucket = bucket.lock().unwrap();
+                let result = bucket.try_take(now);
+
+                if result.is_ok() {
+                    if waited {
+                        bucket.waits += 1;
+                    }
+
+                    log::debug!(
+                        "Rate limiter for {host}: {} tokens consumed, {} waits",
+                        bucket.consumed,
+                        bucket.waits
+                    );
+                }
+
+                result
+            };
+
+            let wait = match result {
+                Ok(()) => {
+                    if waited {
+                        self.metrics.rate_limit_waited();
+                    }
+
+                    return true;
+                }
+                Err(wait) => wait,
+            };
+
+            if now.duration_since(start) + wait > max_wait {
+                log::warn!("Rate limit for {host} exceeded, skipping request");
+                self.metrics.rate_limit_skipped();
+
+                return false;
+            }
+
+            waited = true;
+            sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://example.com/file.json";
+
+    /// A limiter allowing one request per second to example.com, with a
+    /// burst of two.
+    fn limiter() -> (RateLimiter, Arc<Metrics>) {
+        let metrics = Arc::new(Metrics::new(&[]));
+        let mut limiter = RateLimiter::new(metrics.clone());
+        limiter.add_host("example.com", Duration::from_secs(1), 2);
+
+        (limiter, metrics)
+    }
+
+    fn counted(metrics: &Metrics, name: &str) -> String {
+        metrics
+            .render()
+            .lines()
+            .find(|line| line.starts_with(&format!("{name} ")))
+            .unwrap()
+            .to_owned()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_is_allowed_right_away() {
+        let (limiter, metrics) = limiter();
+        let start = Instant::now();
+
+        assert!(limiter.acquire(URL, Duration::ZERO).await);
+        assert!(limiter.acquire(URL, Duration::ZERO).await);
+
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        assert_eq!(
+            counted(&metrics, "leonardo_rate_limit_waits_total"),
+            "leonardo_rate_limit_waits_total 0"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_the_next_token() {
+        let (limiter, metrics) = limiter();
+        limiter.acquire(URL, Duration::ZERO).await;
+        limiter.acquire(URL, Duration::ZERO).await;
+        let start = Instant::now();
+
+        assert!(limiter.acquire(URL, Duration::from_secs(2)).await);
+
+        assert!((Duration::from_secs(1)..Duration::from_secs(2)).contains(&start.elapsed()));
+        assert_eq!(
+            counted(&metrics, "leonardo_rate_limit_waits_total"),
+            "leonardo_rate_limit_waits_total 1"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_without_waiting_if_the_token_comes_too_late() {
+        let (limiter, metrics) = limiter();
+        limiter.acquire(URL, Duration::ZERO).await;
+        limiter.acquire(URL, Duration::ZERO).await;
+        let start = Instant::now();
+
+        assert!(!limiter.acquire(URL, Duration::from_millis(500)).await);
+
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        assert_eq!(
+            counted(&metrics, "leonardo_rate_limit_skips_total"),
+            "leonardo_rate_limit_skips_total 1"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tokens_refill_over_time() {
+        let (limiter, _) = limiter();
+        limiter.acquire(URL, Duration::ZERO).await;
+        limiter.acquire(URL, Duration::ZERO).await;
+
+        tokio::time::advance(Duration::from_secs(2)).await;
+
+        assert!(limiter.acquire(URL, Duration::ZERO).await);
+        assert!(limiter.acquire(URL, Duration::ZERO).await);
+        assert!(!limiter.acquire(URL, Duration::ZERO).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn other_hosts_are_not_limited() {
+        let (limiter, _) = limiter();
+
+        for _ in 0..10 {
+            assert!(
+                limiter
+                    .acquire("https://example.org/file.json", Duration::ZERO)
+                    .await
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn intervals_come_from_the_config() {
+        let mut config = Config::for_tests();
+        config.playstore_rate_limit = Duration::from_secs(30);
+        let limiter = RateLimiter::from_config(&config, Arc::new(Metrics::new(&[])));
+        let url = "https://play.google.com/store/apps/details?id=org.example.app";
+
+        assert!(limiter.acquire(url, Duration::ZERO).await);
+        assert!(!limiter.acquire(url, Duration::from_secs(29)).await);
+        assert!(limiter.acquire(url, Duration::from_secs(31)).await);
+    }
+}
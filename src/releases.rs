@@ -0,0 +1,519 @@
+use reqwest::{
+    header::{HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use serde::Deserialize;
+use teloxide::{
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+    utils::markdown,
+};
+use time::OffsetDateTime;
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::ReleaseChannel,
+    messages::{Language, Text},
+    metrics::Metrics,
+    ratelimit::RateLimiter,
+    retry::{with_retry, DEFAULT_ATTEMPTS, DEFAULT_BASE_DELAY},
+};
+
+/// How long fetched releases are reused before the OTA metadata is fetched
+/// again.
+const CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct OtaData {
+    pub datetime: i64,
+    pub url: String,
+}
+
+/// What fetching the OTA metadata of a channel found.
+#[derive(Debug)]
+pub enum ReleaseFetch {
+    Found(OtaData),
+    /// The metadata doesn't exist (404), the channel has no release.
+    Missing,
+}
+
+/// Why the OTA metadata of a channel couldn't be fetched. Unlike
+/// [`ReleaseFetch::Missing`], these don't say anything about the release.
+#[derive(Debug, thiserror::Error)]
+pub enum OtaError {
+    #[error("request failed")]
+    Network(#[source] reqwest::Error),
+    #[error("rate limited")]
+    RateLimited,
+    #[error("unexpected status {0}")]
+    Status(StatusCode),
+    #[error("invalid OTA metadata: {0}")]
+    Invalid(String),
+}
+
+#[derive(Debug)]
+pub struct AllReleases(Vec<(ReleaseChannel, Result<ReleaseFetch, OtaError>)>);
+
+impl AllReleases {
+    /// What was fetched for every channel, in the order they are shown.
+    pub fn fetches(
+        &self,
+    ) -> impl Iterator<Item = (&ReleaseChannel, &Result<ReleaseFetch, OtaError>)> {
+        self.0.iter().map(|(channel, fetch)| (channel, fetch))
+    }
+
+    /// Every channel with its release, `None` if it has none or it couldn't
+    /// be fetched.
+    pub fn channels(&self) -> impl Iterator<Item = (&ReleaseChannel, Option<&OtaData>)> {
+        self.fetches().map(|(channel, fetch)| match fetch {
+            Ok(ReleaseFetch::Found(release)) => (channel, Some(release)),
+            _ => (channel, None),
+        })
+    }
+}
+
+/// An OTA file as last fetched, with the validators the server sent for it so
+/// it is only downloaded again once it changed.
+#[derive(Clone, Debug)]
+struct Validated {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    data: OtaData,
+}
+
+/// The latest releases shared by `/latest` and inline queries, so they don't
+/// fetch the OTA metadata on every request.
+#[derive(Default)]
+pub struct ReleaseCache {
+    latest: tokio::sync::Mutex<Option<(Instant, Arc<AllReleases>)>>,
+    /// Keyed by OTA URL.
+    validated: tokio::sync::Mutex<HashMap<String, Validated>>,
+}
+
+impl ReleaseCache {
+    /// The cached releases, fetched again once they are older than
+    /// [`CACHE_TTL`].
+    pub async fn get(
+        &self,
+        client: &reqwest::Client,
+        limiter: &RateLimiter,
+        metrics: &Metrics,
+        channels: &[ReleaseChannel],
+    ) -> Result<Arc<AllReleases>, reqwest::Error> {
+        // Holding the lock while fetching makes concurrent requests wait for
+        // one fetch instead of starting their own.
+        let mut latest = self.latest.lock().await;
+        let mut validated = self.validated.lock().await;
+
+        if let Some((fetched_at, releases)) = latest.as_ref() {
+            if fetched_at.elapsed() < CACHE_TTL {
+                return Ok(releases.clone());
+            }
+        }
+
+        let releases = match fetch_channels(client, limiter, channels, &mut validated).await {
+            Ok(fetches) => Arc::new(AllReleases(fetches)),
+            Err(e) => {
+                metrics.ota_fetch_failed();
+
+                return Err(e);
+            }
+        };
+
+        // Channels that couldn't be fetched are tried again next time
+        // instead of being shown as unavailable until the cache expires.
+        if releases.fetches().all(|(_, fetch)| fetch.is_ok()) {
+            metrics.releases_polled();
+            *latest = Some((Instant::now(), releases.clone()));
+        } else {
+            metrics.ota_fetch_failed();
+        }
+
+        Ok(releases)
+    }
+}
+
+/// Formats the line of the channel `name` for a MarkdownV2 message, escaping
+/// everything that comes from the OTA metadata.
+pub fn format_release(
+    language: Language,
+    name: &str,
+    release: Option<&OtaData>,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    Ok(match release {
+        Some(release) => format!(
+            "{}: [{}]({}) {}",
+            markdown::escape(name),
+            markdown::escape(&language.text(Text::ReleaseDownload)),
+            markdown::escape_link_url(&release.url),
+            markdown::escape(&format!(
+                "({})",
+                language.text(Text::ReleaseUpdated {
+                    time: &format_release_time(release.datetime)?
+                })
+            ))
+        ),
+        None => markdown::escape(&language.text(Text::NoRelease { name })),
+    })
+}
+
+/// Formats `releases` for a MarkdownV2 message, one line per channel.
+pub fn format_releases(
+    language: Language,
+    releases: &AllReleases,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let mut text = String::new();
+
+    for (channel, fetch) in releases.fetches() {
+        let name = channel.display_name.as_str();
+        let line = match fetch {
+            Ok(ReleaseFetch::Found(release)) => format_release(language, name, Some(release))?,
+            Ok(ReleaseFetch::Missing) => format_release(language, name, None)?,
+            Err(_) => markdown::escape(&language.text(Text::ReleaseUnavailable { name })),
+        };
+
+        text.push_str(&line);
+        text.push('\n');
+    }
+
+    Ok(text)
+}
+
+/// A download button for `release`, labelled `name`. `None` if its URL is
+/// invalid.
+pub fn download_button(name: &str, release: &OtaData) -> Option<InlineKeyboardButton> {
+    let url = reqwest::Url::parse(&release.url).ok()?;
+
+    Some(InlineKeyboardButton::url(name.to_owned(), url))
+}
+
+/// A download button for each available release, two per row. `None` if no
+/// release is available at all.
+pub fn releases_keyboard(releases: &AllReleases) -> Option<InlineKeyboardMarkup> {
+    let buttons = releases
+        .channels()
+        .filter_map(|(channel, release)| download_button(&channel.display_name, release?))
+        .collect::<Vec<_>>();
+
+    if buttons.is_empty() {
+        None
+    } else {
+        Some(InlineKeyboardMarkup::new(
+            buttons.chunks(2).map(|row| row.to_vec()),
+        ))
+    }
+}
+
+/// Whether the channel `key` matches every word of an inline query like
+/// "dcosx pre". The first part of the key has to match in full, e.g. `dcosx`,
+/// the parts after it by prefix. "latest" matches every channel.
+pub fn matches_query(key: &str, query: &str) -> bool {
+    let (base, kind) = key.split_once('-').unwrap_or((key, ""));
+
+    query.to_lowercase().split_whitespace().all(|word| {
+        word == "latest"
+            || word == key
+            || word == base
+            || kind.split('-').any(|part| part.starts_with(word))
+    })
+}
+
+pub fn format_release_time(datetime: i64) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let dt = OffsetDateTime::from_unix_timestamp(datetime)?;
+    let format = time::format_description::parse("[year]-[month]-[day] [hour]:[minute]:[second]")?;
+
+    Ok(dt.format(&format)?)
+}
+
+/// Fetches the OTA file at `url`. If it was fetched before, the server is
+/// asked to only send it if it changed, otherwise the `validated` copy is
+/// reused.
+async fn get_release(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    validated: &mut HashMap<String, Validated>,
+    url: &str,
+) -> Result<ReleaseFetch, OtaError> {
+    if !limiter.acquire(url, Duration::from_secs(10)).await {
+        return Err(OtaError::RateLimited);
+    }
+
+    let cached = validated.get(url).cloned();
+    let response = with_retry(
+        &format!("Fetching {url}"),
+        DEFAULT_ATTEMPTS,
+        DEFAULT_BASE_DELAY,
+        || async {
+            let mut request = client.get(url);
+
+            if let Some(cached) = &cached {
+                if let Some(etag) = &cached.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &cached.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+
+            request.send().await?.error_for_status()
+        },
+    )
+    .await;
+
+    let response = match response {
+        Ok(response) => response,
+        Err(e) if e.status() == Some(StatusCode::NOT_FOUND) => {
+            validated.remove(url);
+
+            return Ok(ReleaseFetch::Missing);
+        }
+        Err(e) => {
+            log::warn!("Failed to fetch {url}: {e}");
+
+            return Err(match e.status() {
+                Some(status) => OtaError::Status(status),
+                None => OtaError::Network(e),
+            });
+        }
+    };
+
+    match response.status() {
+        StatusCode::NOT_MODIFIED => match cached {
+            Some(cached) => Ok(ReleaseFetch::Found(cached.data)),
+            None => Err(OtaError::Status(StatusCode::NOT_MODIFIED)),
+        },
+        _ => {
+            let header = |name: HeaderName| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_owned)
+            };
+            let etag = header(ETAG);
+            let last_modified = header(LAST_MODIFIED);
+            let data = match response.json::<OtaData>().await {
+                Ok(data) => data,
+                Err(e) if e.is_decode() => {
+                    log::warn!("Invalid OTA metadata at {url}: {e}");
+
+                    // The message of the underlying serde error says what
+                    // is wrong with the file.
+                    let message = e
+                        .source()
+                        .map_or_else(|| e.to_string(), ToString::to_string);
+
+                    return Err(OtaError::Invalid(message));
+                }
+                Err(e) => return Err(OtaError::Network(e)),
+            };
+
+            // Without validators the file is simply fetched in full again.
+            if etag.is_some() || last_modified.is_some() {
+                validated.insert(
+                    url.to_owned(),
+                    Validated {
+                        etag,
+                        last_modified,
+                        data: data.clone(),
+                    },
+                );
+            } else {
+                validated.remove(url);
+            }
+
+            Ok(ReleaseFetch::Found(data))
+        }
+    }
+}
+
+/// Fetches the OTA metadata of every channel, in order.
+async fn fetch_channels(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    channels: &[ReleaseChannel],
+    validated: &mut HashMap<String, Validated>,
+) -> Result<Vec<(ReleaseChannel, Result<ReleaseFetch, OtaError>)>, reqwest::Error> {
+    let mut fetches = Vec::with_capacity(channels.len());
+
+    for channel in channels {
+        let fetch = get_release(client, limiter, validated, &channel.url).await;
+        fetches.push((channel.clone(), fetch));
+    }
+
+    // Only when no channel could be reached at all is the whole fetch a
+    // failure, otherwise each channel shows what happened to it.
+    if fetches
+        .iter()
+        .all(|(_, fetch)| matches!(fetch, Err(OtaError::Network(_))))
+    {
+        if let Some((_, Err(OtaError::Network(e)))) = fetches.pop() {
+            return Err(e);
+        }
+    }
+
+    Ok(fetches)
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    /// Serves `response` for the OTA file and fetches it once.
+    async fn fetch(
+        response: ResponseTemplate,
+        expected_requests: u64,
+    ) -> Result<ReleaseFetch, OtaError> {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/davinci.json"))
+            .respond_with(response)
+            .expect(expected_requests)
+            .mount(&server)
+            .await;
+
+        get_release(
+            &reqwest::Client::new(),
+            &RateLimiter::new(Arc::new(Metrics::new(&[]))),
+            &mut HashMap::new(),
+            &format!("{}/davinci.json", server.uri()),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn valid_metadata_is_found() {
+        let response = ResponseTemplate::new(200).set_body_string(
+            r#"{"datetime": 1656633600, "url": "https://example.com/dcos.zip", "size": 1}"#,
+        );
+
+        match fetch(response, 1).await {
+            Ok(ReleaseFetch::Found(release)) => {
+                assert_eq!(release.datetime, 1656633600);
+                assert_eq!(release.url, "https://example.com/dcos.zip");
+            }
+            other => panic!("expected a release, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn garbage_is_invalid() {
+        let response = ResponseTemplate::new(200).set_body_string("<html>Not JSON</html>");
+
+        assert!(matches!(
+            fetch(response, 1).await,
+            Err(OtaError::Invalid(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn not_found_is_missing() {
+        assert!(matches!(
+            fetch(ResponseTemplate::new(404), 1).await,
+            Ok(ReleaseFetch::Missing)
+        ));
+    }
+
+    #[tokio::test]
+    async fn server_error_is_retried_and_reported() {
+        assert!(matches!(
+            fetch(ResponseTemplate::new(500), u64::from(DEFAULT_ATTEMPTS)).await,
+            Err(OtaError::Status(StatusCode::INTERNAL_SERVER_ERROR))
+        ));
+    }
+
+    /// Characters Telegram requires to be escaped outside of entities.
+    const RESERVED: &str = "_*[]()~`>#+-=|{}.!";
+
+    /// Parses `text` like Telegram parses MarkdownV2 and panics on anything
+    /// it would reject. Returns the plain text and the inline link URLs.
+    fn parse_markdown_v2(text: &str) -> (String, Vec<String>) {
+        let mut plain = String::new();
+        let mut urls = Vec::new();
+        let mut in_link_text = false;
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => plain.push(chars.next().expect("dangling backslash")),
+                '[' if !in_link_text => in_link_text = true,
+                ']' if in_link_text => {
+                    in_link_text = false;
+                    assert_eq!(chars.next(), Some('('), "link without URL in {text:?}");
+
+                    // Only `)` and `\` have to be escaped in the URL.
+                    let mut url = String::new();
+                    loop {
+                        match chars.next().expect("unterminated link URL") {
+                            '\\' => url.push(chars.next().expect("dangling backslash")),
+                            ')' => break,
+                            c => url.push(c),
+                        }
+                    }
+                    urls.push(url);
+                }
+                c if RESERVED.contains(c) => panic!("unescaped {c:?} in {text:?}"),
+                c => plain.push(c),
+            }
+        }
+        assert!(!in_link_text, "unterminated link in {text:?}");
+
+        (plain, urls)
+    }
+
+    fn channel(display_name: &str) -> ReleaseChannel {
+        ReleaseChannel {
+            key: String::from("dcos"),
+            display_name: display_name.to_owned(),
+            url: String::from("https://example.com/davinci.json"),
+        }
+    }
+
+    #[test]
+    fn releases_are_valid_markdown_v2() {
+        let url = "https://example.com/dcos_davinci-(12.1)_v1.2.zip?a=b&c=d\\e";
+        let releases = AllReleases(vec![
+            (
+                channel("DavinciCodeOS (12.1) pre-release!"),
+                Ok(ReleaseFetch::Found(OtaData {
+                    datetime: 1656633600,
+                    url: url.to_owned(),
+                })),
+            ),
+            (channel("DavinciCodeOS_X"), Ok(ReleaseFetch::Missing)),
+            (channel("DavinciCodeOS [beta]"), Err(OtaError::RateLimited)),
+        ]);
+
+        for language in [Language::English, Language::German] {
+            let (plain, urls) = parse_markdown_v2(&format_releases(language, &releases).unwrap());
+
+            assert_eq!(urls, [url]);
+            assert_eq!(
+                plain,
+                format!(
+                    "DavinciCodeOS (12.1) pre-release!: {} ({})\n{}\n{}\n",
+                    language.text(Text::ReleaseDownload),
+                    language.text(Text::ReleaseUpdated {
+                        time: "2022-07-01 00:00:00"
+                    }),
+                    language.text(Text::NoRelease {
+                        name: "DavinciCodeOS_X"
+                    }),
+                    language.text(Text::ReleaseUnavailable {
+                        name: "DavinciCodeOS [beta]"
+                    }),
+                )
+            );
+        }
+    }
+}
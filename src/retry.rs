@@ -0,0 +1,124 @@
+use std::{
+    fmt,
+    future::Future,
+    time::{Duration, Instant, SystemTime},
+};
+
+use reqwest::StatusCode;
+use teloxide::DownloadError;
+use tokio::time::sleep;
+
+use crate::{error::BotError, pipeline::IncompleteDownload};
+
+pub const DEFAULT_ATTEMPTS: u32 = 3;
+pub const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Retries give up once they would take longer than this in total, so a
+/// handler can't hang for minutes on a service that is down.
+const MAX_RETRY_TIME: Duration = Duration::from_secs(20);
+
+/// Errors that may go away when the request is repeated.
+pub trait Retryable {
+    fn is_retryable(&self) -> bool;
+}
+
+impl Retryable for reqwest::Error {
+    fn is_retryable(&self) -> bool {
+        self.is_connect() || self.is_timeout() || self.status().map_or(false, retryable_status)
+    }
+}
+
+impl Retryable for DownloadError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            // A connection that drops halfway through the file fails while
+            // reading the body.
+            Self::Network(e) => e.is_retryable() || e.is_body(),
+            Self::Io(_) => true,
+        }
+    }
+}
+
+impl Retryable for BotError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::Download(e) => match e.downcast_ref::<DownloadError>() {
+                Some(e) => e.is_retryable(),
+                None => e.is::<IncompleteDownload>(),
+            },
+            Self::Http(e) | Self::Ota(e) => e.is_retryable(),
+            Self::GitLabApi { status, .. } => retryable_status(*status),
+            _ => false,
+        }
+    }
+}
+
+/// Too many requests and server errors, everything else is our fault and
+/// fails the same way again.
+fn retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Runs `f` up to `attempts` times while it fails with retryable errors,
+/// waiting `base_delay` doubled after every attempt plus some jitter.
+/// `what` names the call in the logs.
+pub async fn with_retry<F, Fut, T, E>(
+    what: &str,
+    attempts: u32,
+    base_delay: Duration,
+    mut f: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Retryable + fmt::Display,
+{
+    let started = Instant::now();
+    let mut attempt = 1;
+
+    loop {
+        match f().await {
+            Ok(value) => {
+                if attempt > 1 {
+                    log::info!("{what} succeeded on attempt {attempt}/{attempts}");
+                }
+
+                return Ok(value);
+            }
+            Err(e) if e.is_retryable() && attempt < attempts => {
+                let delay = backoff(base_delay, attempt);
+
+                if started.elapsed() + delay > MAX_RETRY_TIME {
+                    log::warn!("{what} failed on attempt {attempt}/{attempts}, out of time: {e}");
+
+                    return Err(e);
+                }
+
+                log::warn!(
+                    "{what} failed on attempt {attempt}/{attempts}, retrying in {}ms: {e}",
+                    delay.as_millis()
+                );
+                sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                if attempt > 1 {
+                    log::warn!("{what} failed on attempt {attempt}/{attempts}: {e}");
+                }
+
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// `base_delay * 2^(attempt - 1)`, plus up to half of that again so
+/// concurrent retries don't hit the service at the same time.
+fn backoff(base_delay: Duration, attempt: u32) -> Duration {
+    let delay = base_delay * 2u32.saturating_pow(attempt - 1);
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |now| now.subsec_nanos());
+
+    delay + delay.mul_f64(f64::from(nanos % 1000) / 2000.0)
+}
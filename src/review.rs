@@ -0,0 +1,104 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use teloxide::types::ChatId;
+use tokio::time::Instant;
+
+use crate::batch::BatchedIcon;
+
+/// A submission waiting for a reviewer to approve or reject it.
+pub struct Review {
+    pub submitter: ChatId,
+    /// The Telegram user id of the submitter, whose limit a completed
+    /// submission counts against.
+    pub submitter_id: i64,
+    pub icons: Vec<BatchedIcon>,
+    pub target_branches: Vec<String>,
+    /// The message with the Approve/Reject buttons in the reviewers chat.
+    pub message_id: i32,
+}
+
+/// Submissions waiting for review, keyed by the id embedded in the callback
+/// data of their buttons.
+pub struct PendingReviews {
+    timeout: Duration,
+    next_id: AtomicU64,
+    reviews: Mutex<HashMap<u64, (Review, Instant)>>,
+}
+
+impl PendingReviews {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            next_id: AtomicU64::new(0),
+            reviews: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reserves the id for the next review, so its buttons can be sent
+    /// before it is inserted.
+    pub fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn insert(&self, id: u64, review: Review) {
+        let expires_at = Instant::now() + self.timeout;
+
+        self.reviews
+            .lock()
+            .unwrap()
+            .insert(id, (review, expires_at));
+    }
+
+    /// Removes the review so only one reviewer can act on it.
+    pub fn take(&self, id: u64) -> Option<Review> {
+        self.reviews
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .map(|(review, _)| review)
+    }
+
+    /// Removes and returns every review that has been waiting for longer
+    /// than the timeout.
+    pub fn take_expired(&self) -> Vec<Review> {
+        let now = Instant::now();
+        let mut reviews = self.reviews.lock().unwrap();
+        let expired = reviews
+            .iter()
+            .filter(|(_, (_, expires_at))| *expires_at <= now)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        expired
+            .into_iter()
+            .filter_map(|id| reviews.remove(&id))
+            .map(|(review, _)| review)
+            .collect()
+    }
+}
+
+/// Builds the callback data of a review button.
+pub fn callback_data(approve: bool, id: u64) -> String {
+    let action = if approve { "approve" } else { "reject" };
+
+    format!("review:{action}:{id}")
+}
+
+/// Parses callback data built by [`callback_data`].
+pub fn parse_callback_data(data: &str) -> Option<(bool, u64)> {
+    let (action, id) = data.strip_prefix("review:")?.split_once(':')?;
+    let approve = match action {
+        "approve" => true,
+        "reject" => false,
+        _ => return None,
+    };
+
+    Some((approve, id.parse().ok()?))
+}
@@ -0,0 +1,89 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use crate::deadline::Stage;
+
+/// Git and merge request work in progress, so a shutdown that runs out of
+/// time can tell what it interrupted.
+#[derive(Default)]
+pub struct InFlight {
+    next_id: AtomicU64,
+    work: Mutex<HashMap<u64, (String, Option<Stage>)>>,
+}
+
+impl InFlight {
+    /// Tracks `description` until the returned guard is dropped.
+    pub fn start(&self, description: String) -> Work<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.work.lock().unwrap().insert(id, (description, None));
+
+        Work {
+            in_flight: self,
+            id,
+        }
+    }
+
+    /// Describes the work that is still running, with its stage if known.
+    pub fn running(&self) -> Vec<String> {
+        self.work
+            .lock()
+            .unwrap()
+            .values()
+            .map(|(description, stage)| match stage {
+                Some(stage) => format!("{description} ({stage})"),
+                None => description.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A piece of tracked work, forgotten once dropped.
+pub struct Work<'a> {
+    in_flight: &'a InFlight,
+    id: u64,
+}
+
+impl Work<'_> {
+    pub fn stage(&self, stage: Stage) {
+        if let Some((_, current)) = self.in_flight.work.lock().unwrap().get_mut(&self.id) {
+            *current = Some(stage);
+        }
+    }
+}
+
+impl Drop for Work<'_> {
+    fn drop(&mut self) {
+        self.in_flight.work.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Resolves on Ctrl+C, or SIGTERM on unix.
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to listen for SIGTERM: {e}");
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
@@ -0,0 +1,340 @@
+use std::{collections::HashMap, fmt, sync::Mutex, time::Duration};
+
+use reqwest::{redirect::Policy, StatusCode};
+
+use crate::{
+    ratelimit::RateLimiter,
+    retry::{with_retry, DEFAULT_ATTEMPTS, DEFAULT_BASE_DELAY},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Google likes to bounce requests through consent pages first.
+const MAX_REDIRECTS: usize = 10;
+
+/// A store an app can be published in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Store {
+    PlayStore,
+    FDroid,
+}
+
+impl Store {
+    /// The page of the app for people.
+    pub fn page_url(self, app_path: &str) -> String {
+        match self {
+            Self::PlayStore => format!("https://play.google.com/store/apps/details?id={app_path}"),
+            Self::FDroid => format!("https://f-droid.org/packages/{app_path}/"),
+        }
+    }
+
+    /// Where `app_path` is looked up, on `base` instead of the store's own
+    /// host if it is set.
+    fn lookup_url(self, base: Option<&str>, app_path: &str) -> String {
+        match self {
+            Self::PlayStore => format!(
+                "{}/store/apps/details?id={app_path}&gl=US",
+                base.unwrap_or("https://play.google.com")
+            ),
+            Self::FDroid => format!(
+                "{}/api/v1/packages/{app_path}",
+                base.unwrap_or("https://f-droid.org")
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Store {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PlayStore => write!(f, "the Play Store"),
+            Self::FDroid => write!(f, "F-Droid"),
+        }
+    }
+}
+
+/// Outcome of looking up an app in the stores.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StoreCheck {
+    Found(Store),
+    /// Every store answered with a 404.
+    NotFound,
+    /// A store could not be asked or gave no definite answer, e.g. because
+    /// it rate limited us.
+    Unknown,
+}
+
+/// What a store told us about an app.
+#[derive(Clone, Debug)]
+pub struct AppDetails {
+    pub store: Store,
+    /// The name of the app, if the store page could be parsed.
+    pub title: Option<String>,
+}
+
+/// Checks whether apps exist in the Play Store or on F-Droid.
+pub struct AppStores {
+    client: reqwest::Client,
+    /// Host both stores are asked on instead of their own, for tests.
+    lookup_base: Option<String>,
+    /// The details of the apps that were found, keyed by app path.
+    found: Mutex<HashMap<String, AppDetails>>,
+}
+
+impl Default for AppStores {
+    fn default() -> Self {
+        let client = reqwest::Client::builder()
+            .redirect(Policy::limited(MAX_REDIRECTS))
+            .timeout(TIMEOUT)
+            .build()
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to build the app store client, using the defaults: {e}");
+
+                reqwest::Client::new()
+            });
+
+        Self {
+            client,
+            lookup_base: None,
+            found: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl AppStores {
+    #[cfg(test)]
+    fn with_lookup_base(base: String) -> Self {
+        Self {
+            lookup_base: Some(base),
+            ..Self::default()
+        }
+    }
+
+    /// Looks up `app_path` in the Play Store and, if it isn't found there, on
+    /// F-Droid. FOSS apps are often only published on the latter.
+    pub async fn check(&self, limiter: &RateLimiter, app_path: &str) -> StoreCheck {
+        let play_store = self.check_store(limiter, Store::PlayStore, app_path).await;
+
+        if let StoreCheck::Found(_) = play_store {
+            return play_store;
+        }
+
+        match self.check_store(limiter, Store::FDroid, app_path).await {
+            StoreCheck::Found(store) => StoreCheck::Found(store),
+            // Only a definite answer from both means the app doesn't exist.
+            StoreCheck::NotFound => play_store,
+            StoreCheck::Unknown => StoreCheck::Unknown,
+        }
+    }
+
+    /// The details of `app_path` if an earlier [`AppStores::check`] found it.
+    pub fn details(&self, app_path: &str) -> Option<AppDetails> {
+        self.found.lock().unwrap().get(app_path).cloned()
+    }
+
+    /// Looks up `app_path` in `store`, retrying connection problems and
+    /// server errors.
+    async fn check_store(&self, limiter: &RateLimiter, store: Store, app_path: &str) -> StoreCheck {
+        let url = store.lookup_url(self.lookup_base.as_deref(), app_path);
+
+        // Don't hold up the dialogue for too long, and rather skip the check
+        // than get our IP flagged.
+        if !limiter.acquire(&url, Duration::from_secs(10)).await {
+            return StoreCheck::Unknown;
+        }
+
+        let response = with_retry(
+            &format!("Looking up {app_path} in {store}"),
+            DEFAULT_ATTEMPTS,
+            DEFAULT_BASE_DELAY,
+            || async {
+                let response = self.client.get(&url).send().await?;
+
+                if response.status() == StatusCode::NOT_FOUND {
+                    Ok(response)
+                } else {
+                    response.error_for_status()
+                }
+            },
+        )
+        .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => {
+                // The name is only nice to have, the app exists either way.
+                let title = match store {
+                    Store::PlayStore => match response.text().await {
+                        Ok(page) => page_title(&page),
+                        Err(e) => {
+                            log::debug!("Failed to read the store page of {app_path}: {e}");
+
+                            None
+                        }
+                    },
+                    Store::FDroid => None,
+                };
+
+                self.found
+                    .lock()
+                    .unwrap()
+                    .insert(app_path.to_owned(), AppDetails { store, title });
+
+                StoreCheck::Found(store)
+            }
+            Ok(response) if response.status() == StatusCode::NOT_FOUND => StoreCheck::NotFound,
+            Ok(response) => {
+                log::warn!("{store} answered {} for {app_path}", response.status());
+
+                StoreCheck::Unknown
+            }
+            Err(e) => {
+                log::warn!("Failed to look up {app_path} in {store}: {e}");
+
+                StoreCheck::Unknown
+            }
+        }
+    }
+}
+
+/// Extracts the app name from a Play Store page, preferring its `og:title`
+/// over the `<title>`.
+fn page_title(page: &str) -> Option<String> {
+    let og_title = page.find("property=\"og:title\"").and_then(|start| {
+        let tag_end = start + page[start..].find('>')?;
+        let tag = &page[page[..start].rfind('<')?..tag_end];
+        let content = tag.find("content=\"")? + "content=\"".len();
+
+        Some(&tag[content..content + tag[content..].find('"')?])
+    });
+    let title = og_title.or_else(|| {
+        let start = page.find("<title")?;
+        let start = start + page[start..].find('>')? + 1;
+
+        Some(&page[start..start + page[start..].find("</title>")?])
+    })?;
+
+    let title = unescape_html(title.trim());
+    let title = title
+        .strip_suffix(" - Apps on Google Play")
+        .unwrap_or(&title)
+        .trim();
+
+    if title.is_empty() {
+        None
+    } else {
+        Some(title.to_owned())
+    }
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::metrics::Metrics;
+
+    const APP_PATH: &str = "org.example.app";
+
+    /// A rate limiter that doesn't limit the mock server.
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(Arc::new(Metrics::new(&[])))
+    }
+
+    async fn mock_play_store(server: &MockServer, response: ResponseTemplate) {
+        Mock::given(method("GET"))
+            .and(path("/store/apps/details"))
+            .and(query_param("id", APP_PATH))
+            .respond_with(response)
+            .mount(server)
+            .await;
+    }
+
+    async fn mock_fdroid(server: &MockServer, response: ResponseTemplate) {
+        Mock::given(method("GET"))
+            .and(path(format!("/api/v1/packages/{APP_PATH}")))
+            .respond_with(response)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn found_in_play_store() {
+        let server = MockServer::start().await;
+        mock_play_store(
+            &server,
+            ResponseTemplate::new(200).set_body_string(
+                "<html><head><meta property=\"og:title\" content=\"Example &amp; Co - Apps on Google Play\"></head></html>",
+            ),
+        )
+        .await;
+        let stores = AppStores::with_lookup_base(server.uri());
+
+        let check = stores.check(&limiter(), APP_PATH).await;
+
+        assert_eq!(check, StoreCheck::Found(Store::PlayStore));
+        let details = stores.details(APP_PATH).unwrap();
+        assert_eq!(details.store, Store::PlayStore);
+        assert_eq!(details.title.as_deref(), Some("Example & Co"));
+    }
+
+    #[tokio::test]
+    async fn found_on_fdroid_only() {
+        let server = MockServer::start().await;
+        mock_play_store(&server, ResponseTemplate::new(404)).await;
+        mock_fdroid(&server, ResponseTemplate::new(200).set_body_string("{}")).await;
+        let stores = AppStores::with_lookup_base(server.uri());
+
+        let check = stores.check(&limiter(), APP_PATH).await;
+
+        assert_eq!(check, StoreCheck::Found(Store::FDroid));
+        assert_eq!(stores.details(APP_PATH).unwrap().store, Store::FDroid);
+    }
+
+    #[tokio::test]
+    async fn not_found_in_any_store() {
+        let server = MockServer::start().await;
+        mock_play_store(&server, ResponseTemplate::new(404)).await;
+        mock_fdroid(&server, ResponseTemplate::new(404)).await;
+        let stores = AppStores::with_lookup_base(server.uri());
+
+        let check = stores.check(&limiter(), APP_PATH).await;
+
+        assert_eq!(check, StoreCheck::NotFound);
+        assert!(stores.details(APP_PATH).is_none());
+    }
+
+    #[tokio::test]
+    async fn store_error_is_unknown() {
+        let server = MockServer::start().await;
+        mock_play_store(&server, ResponseTemplate::new(500)).await;
+        mock_fdroid(&server, ResponseTemplate::new(404)).await;
+        let stores = AppStores::with_lookup_base(server.uri());
+
+        let check = stores.check(&limiter(), APP_PATH).await;
+
+        // A store that failed can't tell whether the app exists.
+        assert_eq!(check, StoreCheck::Unknown);
+    }
+
+    #[test]
+    fn page_title_falls_back_to_title_tag() {
+        assert_eq!(
+            page_title("<html><title>Example - Apps on Google Play</title></html>").as_deref(),
+            Some("Example")
+        );
+        assert_eq!(page_title("<html><title> </title></html>"), None);
+    }
+}
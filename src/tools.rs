@@ -0,0 +1,116 @@
+use std::{error::Error, fmt, process::Stdio};
+
+use tokio::{io::AsyncWriteExt, process::Command};
+
+/// How much of the stderr of a failed command is shown to users.
+const STDERR_EXCERPT_CHARS: usize = 500;
+
+/// External programs needed for icon submissions and whether they were found
+/// at startup.
+#[derive(Clone, Debug)]
+pub struct Tools {
+    pub svg2vd: bool,
+}
+
+impl Tools {
+    pub async fn detect(svg2vd_bin: &str) -> Self {
+        let svg2vd = is_available(svg2vd_bin).await;
+
+        if !svg2vd {
+            log::warn!(
+                "svg2vd could not be run from {svg2vd_bin}, icon submissions are disabled. Install it or set SVG2VD_BIN."
+            );
+        }
+
+        Self { svg2vd }
+    }
+
+    pub fn icon_submissions_available(&self) -> bool {
+        self.svg2vd
+    }
+}
+
+/// Whether `program` can be started at all. Its exit code is ignored since
+/// not every tool has a `--version` flag.
+async fn is_available(program: &str) -> bool {
+    Command::new(program)
+        .arg("--help")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .status()
+        .await
+        .is_ok()
+}
+
+/// Returned when an external command exits unsuccessfully.
+#[derive(Debug)]
+pub struct CommandFailed {
+    pub program: String,
+    pub code: Option<i32>,
+    pub stderr: String,
+}
+
+impl fmt::Display for CommandFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{} exited with code {code}", self.program)?,
+            None => write!(f, "{} was killed", self.program)?,
+        }
+
+        let stderr = self.stderr.trim();
+
+        if !stderr.is_empty() {
+            let excerpt: String = stderr.chars().take(STDERR_EXCERPT_CHARS).collect();
+            let ellipsis = if excerpt.len() < stderr.len() {
+                "…"
+            } else {
+                ""
+            };
+
+            write!(f, ":\n{excerpt}{ellipsis}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for CommandFailed {}
+
+/// Runs `program`, feeds it `input` on stdin and returns what it wrote to
+/// stdout. Fails with [`CommandFailed`] if it exits unsuccessfully.
+pub async fn run_with_stdin(
+    program: &str,
+    args: &[&str],
+    input: &[u8],
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let mut command = Command::new(program);
+    command.args(args);
+    command.stdin(Stdio::piped());
+    command.stdout(Stdio::piped());
+    command.stderr(Stdio::piped());
+    command.kill_on_drop(true);
+
+    let mut child = command.spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+
+    stdin.write_all(input).await?;
+    drop(stdin);
+
+    let output = child.wait_with_output().await?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        log::error!("{program} failed with {}: {stderr}", output.status);
+
+        Err(CommandFailed {
+            program: program.to_owned(),
+            code: output.status.code(),
+            stderr,
+        }
+        .into())
+    }
+}
@@ -0,0 +1,161 @@
+use std::{fs, path::PathBuf, sync::Mutex};
+
+use teloxide::types::ChatId;
+use time::OffsetDateTime;
+
+use crate::config::Config;
+
+/// How long completed submissions are remembered for `/stats`.
+pub const HISTORY_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// A merge request opened for a submitter.
+#[derive(Clone, Debug)]
+pub struct TrackedMergeRequest {
+    pub chat_id: ChatId,
+    pub iid: u64,
+    pub target_branch: String,
+    /// The packages of the submitted icons, separated by commas.
+    pub app_path: String,
+}
+
+/// Merge requests whose submitters are told once they are merged or closed.
+/// Kept in a file, one `chat_id iid target_branch app_path` per line, so a
+/// restart doesn't lose them.
+///
+/// Also keeps a history of the icons submitted within [`HISTORY_SECS`], one
+/// Unix timestamp per icon and line in another file.
+pub struct TrackedMergeRequests {
+    path: PathBuf,
+    tracked: Mutex<Vec<TrackedMergeRequest>>,
+    history_path: PathBuf,
+    history: Mutex<Vec<i64>>,
+}
+
+impl TrackedMergeRequests {
+    pub fn load(config: &Config) -> Self {
+        let tracked = match fs::read_to_string(&config.tracked_merge_requests_path) {
+            Ok(tracked) => tracked.lines().filter_map(parse_line).collect(),
+            Err(e) => {
+                log::info!(
+                    "No tracked merge requests loaded from {}: {e}",
+                    config.tracked_merge_requests_path
+                );
+
+                Vec::new()
+            }
+        };
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let history = match fs::read_to_string(&config.submission_history_path) {
+            Ok(history) => history
+                .lines()
+                .filter_map(|line| line.trim().parse().ok())
+                .filter(|timestamp| now - timestamp < HISTORY_SECS)
+                .collect(),
+            Err(e) => {
+                log::info!(
+                    "No submission history loaded from {}: {e}",
+                    config.submission_history_path
+                );
+
+                Vec::new()
+            }
+        };
+
+        Self {
+            path: PathBuf::from(&config.tracked_merge_requests_path),
+            tracked: Mutex::new(tracked),
+            history_path: PathBuf::from(&config.submission_history_path),
+            history: Mutex::new(history),
+        }
+    }
+
+    /// Starts tracking `merge_request`, replacing an earlier entry for the
+    /// same merge request.
+    pub fn track(&self, merge_request: TrackedMergeRequest) {
+        let mut tracked = self.tracked.lock().unwrap();
+        tracked.retain(|other| other.iid != merge_request.iid);
+        tracked.push(merge_request);
+
+        self.save(&tracked);
+    }
+
+    pub fn all(&self) -> Vec<TrackedMergeRequest> {
+        self.tracked.lock().unwrap().clone()
+    }
+
+    /// Stops tracking the merge request `iid`, once its submitter was told
+    /// about it.
+    pub fn forget(&self, iid: u64) {
+        let mut tracked = self.tracked.lock().unwrap();
+        tracked.retain(|merge_request| merge_request.iid != iid);
+
+        self.save(&tracked);
+    }
+
+    /// Records that a submission of `icons` icons was completed.
+    pub fn completed(&self, icons: usize) {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let mut history = self.history.lock().unwrap();
+        history.retain(|timestamp| now - timestamp < HISTORY_SECS);
+        history.extend(std::iter::repeat(now).take(icons));
+
+        let lines = history
+            .iter()
+            .map(|timestamp| format!("{timestamp}\n"))
+            .collect::<String>();
+
+        if let Err(e) = fs::write(&self.history_path, lines) {
+            log::warn!(
+                "Failed to save the submission history to {}: {e}",
+                self.history_path.display()
+            );
+        }
+    }
+
+    /// How many icons were submitted within the last `secs` seconds, at most
+    /// [`HISTORY_SECS`].
+    pub fn completed_within(&self, secs: i64) -> usize {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+
+        self.history
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|timestamp| now - **timestamp < secs)
+            .count()
+    }
+
+    fn save(&self, tracked: &[TrackedMergeRequest]) {
+        let lines = tracked
+            .iter()
+            .map(|merge_request| {
+                format!(
+                    "{} {} {} {}\n",
+                    merge_request.chat_id.0,
+                    merge_request.iid,
+                    merge_request.target_branch,
+                    merge_request.app_path
+                )
+            })
+            .collect::<String>();
+
+        if let Err(e) = fs::write(&self.path, lines) {
+            log::warn!(
+                "Failed to save the tracked merge requests to {}: {e}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Option<TrackedMergeRequest> {
+    let mut fields = line.split_whitespace();
+
+    Some(TrackedMergeRequest {
+        chat_id: ChatId(fields.next()?.parse().ok()?),
+        iid: fields.next()?.parse().ok()?,
+        target_branch: fields.next()?.to_owned(),
+        app_path: fields.next()?.to_owned(),
+    })
+}